@@ -0,0 +1,673 @@
+//! Local stand-in for the Meteora DLMM program
+//!
+//! Implements `initialize_bin_array`, `initialize_position`,
+//! `add_liquidity_by_strategy`, `remove_all_liquidity`, `claim_fee` and
+//! `close_position` with DLMM's instruction names, arguments and account
+//! order so x-liquidity-engine's CPIs can be exercised on localnet. `LbPair`,
+//! `PositionV2`, `BinArray` and `Bin` share Meteora's leading field layout.
+//!
+//! Simplifications:
+//! - Accounts the mock does not model (the bitmap extension, the event
+//!   authority) are accepted unchecked and no events are emitted.
+//! - Deposits take `amount_x` / `amount_y` in full and credit them to the
+//!   pair's active bin, which must be within the strategy's bins; the
+//!   strategy is only recorded. Bin liquidity is `price * x + y`, in token Y.
+//! - Fees are owed per position (`fee_x` / `fee_y`) rather than per bin.
+
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Mint, Token, TokenAccount, Transfer};
+
+declare_id!("LBUZKhRxPF3XUpBCjp4YzTKgLccjZhTSDM9hT3doEjD");
+
+/// Maximum width of a position, in bins
+const MAX_BIN_PER_POSITION: i32 = 70;
+
+/// Bins per bin array
+const MAX_BIN_PER_ARRAY: i32 = 70;
+
+#[program]
+pub mod mock_meteora_dlmm {
+    use super::*;
+
+    /// Create a pair and its reserves (mock-only setup)
+    pub fn create_lb_pair(ctx: Context<CreateLbPair>, active_id: i32, bin_step: u16) -> Result<()> {
+        let pair = &mut ctx.accounts.lb_pair;
+        pair.parameters = [0; 32];
+        pair.v_parameters = [0; 32];
+        pair.bump_seed = [ctx.bumps.lb_pair];
+        pair.bin_step_seed = bin_step.to_le_bytes();
+        pair.pair_type = 0;
+        pair.active_id = active_id;
+        pair.bin_step = bin_step;
+        pair.token_x_mint = ctx.accounts.token_mint_x.key();
+        pair.token_y_mint = ctx.accounts.token_mint_y.key();
+        pair.reserve_x = ctx.accounts.reserve_x.key();
+        pair.reserve_y = ctx.accounts.reserve_y.key();
+        Ok(())
+    }
+
+    pub fn initialize_bin_array(ctx: Context<InitializeBinArray>, index: i64) -> Result<()> {
+        let mut bin_array = ctx.accounts.bin_array.load_init()?;
+        bin_array.index = index;
+        bin_array.lb_pair = ctx.accounts.lb_pair.key();
+        Ok(())
+    }
+
+    /// Credit swap fees to a position, funded by `funder` (mock-only setup)
+    pub fn accrue_fees(ctx: Context<AccrueFees>, amount_x: u64, amount_y: u64) -> Result<()> {
+        for (amount, from, to) in [
+            (amount_x, &ctx.accounts.funder_token_x, &ctx.accounts.reserve_x),
+            (amount_y, &ctx.accounts.funder_token_y, &ctx.accounts.reserve_y),
+        ] {
+            if amount > 0 {
+                token::transfer(
+                    CpiContext::new(
+                        ctx.accounts.token_program.to_account_info(),
+                        Transfer {
+                            from: from.to_account_info(),
+                            to: to.to_account_info(),
+                            authority: ctx.accounts.funder.to_account_info(),
+                        },
+                    ),
+                    amount,
+                )?;
+            }
+        }
+
+        let mut position = ctx.accounts.position.load_mut()?;
+        position.fee_x += amount_x;
+        position.fee_y += amount_y;
+        Ok(())
+    }
+
+    pub fn initialize_position(
+        ctx: Context<InitializePosition>,
+        lower_bin_id: i32,
+        width: i32,
+    ) -> Result<()> {
+        require!(
+            width > 0 && width <= MAX_BIN_PER_POSITION,
+            MockDlmmError::InvalidPositionWidth
+        );
+
+        let mut position = ctx.accounts.position.load_init()?;
+        position.lb_pair = ctx.accounts.lb_pair.key();
+        position.owner = ctx.accounts.owner.key();
+        position.lower_bin_id = lower_bin_id;
+        position.upper_bin_id = lower_bin_id + width - 1;
+        Ok(())
+    }
+
+    pub fn add_liquidity_by_strategy(
+        ctx: Context<ModifyLiquidity>,
+        liquidity_parameter: LiquidityParameterByStrategy,
+    ) -> Result<()> {
+        let mut position = ctx.accounts.position.load_mut()?;
+        let strategy = &liquidity_parameter.strategy_parameters;
+        require!(
+            strategy.min_bin_id >= position.lower_bin_id
+                && strategy.max_bin_id <= position.upper_bin_id
+                && strategy.min_bin_id <= strategy.max_bin_id,
+            MockDlmmError::InvalidStrategyParameters
+        );
+        let lb_pair = &ctx.accounts.lb_pair;
+        let active_id = lb_pair.active_id;
+        require!(
+            (active_id - liquidity_parameter.active_id).abs()
+                <= liquidity_parameter.max_active_bin_slippage,
+            MockDlmmError::ExceededBinSlippageTolerance
+        );
+        require!(
+            (strategy.min_bin_id..=strategy.max_bin_id).contains(&active_id),
+            MockDlmmError::InvalidStrategyParameters
+        );
+
+        for (amount, from, to) in [
+            (liquidity_parameter.amount_x, &ctx.accounts.user_token_x, &ctx.accounts.reserve_x),
+            (liquidity_parameter.amount_y, &ctx.accounts.user_token_y, &ctx.accounts.reserve_y),
+        ] {
+            if amount > 0 {
+                token::transfer(
+                    CpiContext::new(
+                        ctx.accounts.token_x_program.to_account_info(),
+                        Transfer {
+                            from: from.to_account_info(),
+                            to: to.to_account_info(),
+                            authority: ctx.accounts.sender.to_account_info(),
+                        },
+                    ),
+                    amount,
+                )?;
+            }
+        }
+
+        let loader = bin_array_for(
+            &ctx.accounts.bin_array_lower,
+            &ctx.accounts.bin_array_upper,
+            active_id,
+        )?;
+        let mut bin_array = loader.load_mut()?;
+        let bin = &mut bin_array.bins[active_id.rem_euclid(MAX_BIN_PER_ARRAY) as usize];
+        let price = price_of_bin(active_id, lb_pair.bin_step);
+        let liquidity = bin_liquidity(price, liquidity_parameter.amount_x, liquidity_parameter.amount_y)?;
+        let shares = if bin.liquidity_supply == 0 {
+            liquidity
+        } else {
+            liquidity * bin.liquidity_supply / bin_liquidity(bin.price, bin.amount_x, bin.amount_y)?
+        };
+        bin.price = price;
+        bin.amount_x += liquidity_parameter.amount_x;
+        bin.amount_y += liquidity_parameter.amount_y;
+        bin.liquidity_supply += shares;
+        let i = (active_id - position.lower_bin_id) as usize;
+        position.liquidity_shares[i] += shares;
+        position.strategy_type = strategy.strategy_type as u8;
+        Ok(())
+    }
+
+    /// Withdraw the position's share of each of its bins; fees stay owed
+    pub fn remove_all_liquidity(ctx: Context<ModifyLiquidity>) -> Result<()> {
+        let mut position = ctx.accounts.position.load_mut()?;
+        let mut amounts = [0u64; 2];
+        for bin_id in position.lower_bin_id..=position.upper_bin_id {
+            let i = (bin_id - position.lower_bin_id) as usize;
+            let shares = position.liquidity_shares[i];
+            if shares == 0 {
+                continue;
+            }
+            let loader = bin_array_for(
+                &ctx.accounts.bin_array_lower,
+                &ctx.accounts.bin_array_upper,
+                bin_id,
+            )?;
+            let mut bin_array = loader.load_mut()?;
+            let bin = &mut bin_array.bins[bin_id.rem_euclid(MAX_BIN_PER_ARRAY) as usize];
+            let amount_x = (bin.amount_x as u128 * shares / bin.liquidity_supply) as u64;
+            let amount_y = (bin.amount_y as u128 * shares / bin.liquidity_supply) as u64;
+            bin.amount_x -= amount_x;
+            bin.amount_y -= amount_y;
+            bin.liquidity_supply -= shares;
+            position.liquidity_shares[i] = 0;
+            amounts[0] += amount_x;
+            amounts[1] += amount_y;
+        }
+        drop(position);
+        pay_out(
+            &ctx.accounts.token_x_program,
+            &ctx.accounts.lb_pair,
+            [&ctx.accounts.reserve_x, &ctx.accounts.reserve_y],
+            [&ctx.accounts.user_token_x, &ctx.accounts.user_token_y],
+            amounts,
+        )
+    }
+
+    /// Pay out all fees owed to a position
+    pub fn claim_fee(ctx: Context<ClaimFee>) -> Result<()> {
+        let mut position = ctx.accounts.position.load_mut()?;
+        let amounts = [position.fee_x, position.fee_y];
+        position.fee_x = 0;
+        position.fee_y = 0;
+        drop(position);
+        pay_out(
+            &ctx.accounts.token_program,
+            &ctx.accounts.lb_pair,
+            [&ctx.accounts.reserve_x, &ctx.accounts.reserve_y],
+            [&ctx.accounts.user_token_x, &ctx.accounts.user_token_y],
+            amounts,
+        )
+    }
+
+    /// Close an empty position; its rent goes to `rent_receiver`
+    pub fn close_position(ctx: Context<ClosePosition>) -> Result<()> {
+        let position = ctx.accounts.position.load()?;
+        require!(
+            position.liquidity_shares.iter().all(|shares| *shares == 0)
+                && position.fee_x == 0
+                && position.fee_y == 0,
+            MockDlmmError::NonEmptyPosition
+        );
+        Ok(())
+    }
+}
+
+/// `bin_array_lower` or `bin_array_upper`, whichever holds `bin_id`
+fn bin_array_for<'a, 'info>(
+    lower: &'a AccountLoader<'info, BinArray>,
+    upper: &'a AccountLoader<'info, BinArray>,
+    bin_id: i32,
+) -> Result<&'a AccountLoader<'info, BinArray>> {
+    let index = bin_id.div_euclid(MAX_BIN_PER_ARRAY) as i64;
+    for loader in [lower, upper] {
+        if loader.load()?.index == index {
+            return Ok(loader);
+        }
+    }
+    err!(MockDlmmError::InvalidBinArray)
+}
+
+/// Q64.64 price of `bin_id`, `(1 + bin_step / 10_000) ^ bin_id` (floating point)
+fn price_of_bin(bin_id: i32, bin_step: u16) -> u128 {
+    ((1.0 + bin_step as f64 / 10_000.0).powi(bin_id) * (1u128 << 64) as f64) as u128
+}
+
+/// Liquidity of `amount_x` / `amount_y` at a Q64.64 `price`, in token Y
+fn bin_liquidity(price: u128, amount_x: u64, amount_y: u64) -> Result<u128> {
+    price
+        .checked_mul(amount_x as u128)
+        .map(|value| (value >> 64) + amount_y as u128)
+        .ok_or(MockDlmmError::MathOverflow.into())
+}
+
+fn pay_out<'info>(
+    token_program: &Program<'info, Token>,
+    lb_pair: &Account<'info, LbPair>,
+    from: [&Account<'info, TokenAccount>; 2],
+    to: [&Account<'info, TokenAccount>; 2],
+    amounts: [u64; 2],
+) -> Result<()> {
+    let seeds: &[&[u8]] = &[
+        b"lb_pair",
+        lb_pair.token_x_mint.as_ref(),
+        lb_pair.token_y_mint.as_ref(),
+        &lb_pair.bin_step_seed,
+        &lb_pair.bump_seed,
+    ];
+    for i in 0..2 {
+        if amounts[i] > 0 {
+            token::transfer(
+                CpiContext::new_with_signer(
+                    token_program.to_account_info(),
+                    Transfer {
+                        from: from[i].to_account_info(),
+                        to: to[i].to_account_info(),
+                        authority: lb_pair.to_account_info(),
+                    },
+                    &[seeds],
+                ),
+                amounts[i],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct StrategyParameters {
+    pub min_bin_id: i32,
+    pub max_bin_id: i32,
+    pub strategy_type: StrategyType,
+    pub parameteres: [u8; 64],
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct LiquidityParameterByStrategy {
+    pub amount_x: u64,
+    pub amount_y: u64,
+    pub active_id: i32,
+    pub max_active_bin_slippage: i32,
+    pub strategy_parameters: StrategyParameters,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq)]
+pub enum StrategyType {
+    SpotOneSide,
+    CurveOneSide,
+    BidAskOneSide,
+    SpotBalanced,
+    CurveBalanced,
+    BidAskBalanced,
+    SpotImBalanced,
+    CurveImBalanced,
+    BidAskImBalanced,
+}
+
+/// Leading fields follow Meteora's `LbPair` layout
+#[account]
+pub struct LbPair {
+    pub parameters: [u8; 32],
+    pub v_parameters: [u8; 32],
+    pub bump_seed: [u8; 1],
+    pub bin_step_seed: [u8; 2],
+    pub pair_type: u8,
+    pub active_id: i32,
+    pub bin_step: u16,
+    pub status: u8,
+    pub require_base_factor_seed: u8,
+    pub base_factor_seed: [u8; 2],
+    pub activation_type: u8,
+    pub creator_pool_on_off_control: u8,
+    pub token_x_mint: Pubkey,
+    pub token_y_mint: Pubkey,
+    pub reserve_x: Pubkey,
+    pub reserve_y: Pubkey,
+}
+
+impl LbPair {
+    pub const LEN: usize = 32 + 32 + 1 + 2 + 1 + 4 + 2 + 1 + 1 + 2 + 1 + 1 + 32 * 4;
+}
+
+/// Leading fields follow Meteora's `PositionV2` layout; `fee_x`, `fee_y`
+/// and `strategy_type` are mock-only
+#[account(zero_copy)]
+pub struct PositionV2 {
+    pub lb_pair: Pubkey,
+    pub owner: Pubkey,
+    pub liquidity_shares: [u128; 70],
+    pub reward_infos: [UserRewardInfo; 70],
+    pub fee_infos: [FeeInfo; 70],
+    pub lower_bin_id: i32,
+    pub upper_bin_id: i32,
+    pub fee_x: u64,
+    pub fee_y: u64,
+    pub strategy_type: u8,
+    pub padding: [u8; 7],
+}
+
+#[zero_copy]
+pub struct UserRewardInfo {
+    pub reward_per_token_completes: [u128; 2],
+    pub reward_pendings: [u64; 2],
+}
+
+#[zero_copy]
+pub struct FeeInfo {
+    pub fee_x_per_token_complete: u128,
+    pub fee_y_per_token_complete: u128,
+    pub fee_x_pending: u64,
+    pub fee_y_pending: u64,
+}
+
+/// Follows Meteora's `BinArray` layout
+#[account(zero_copy)]
+pub struct BinArray {
+    pub index: i64,
+    pub version: u8,
+    pub padding: [u8; 7],
+    pub lb_pair: Pubkey,
+    pub bins: [Bin; 70],
+}
+
+/// Follows Meteora's `Bin` layout
+#[zero_copy]
+pub struct Bin {
+    pub amount_x: u64,
+    pub amount_y: u64,
+    pub price: u128,
+    pub liquidity_supply: u128,
+    pub reward_per_token_stored: [u128; 2],
+    pub fee_amount_x_per_token_stored: u128,
+    pub fee_amount_y_per_token_stored: u128,
+    pub amount_x_in: u128,
+    pub amount_y_in: u128,
+}
+
+#[error_code]
+pub enum MockDlmmError {
+    #[msg("Invalid position width")]
+    InvalidPositionWidth,
+    #[msg("Invalid strategy parameters")]
+    InvalidStrategyParameters,
+    #[msg("Exceeded bin slippage tolerance")]
+    ExceededBinSlippageTolerance,
+    #[msg("Unauthorized access")]
+    UnauthorizedAccess,
+    #[msg("Non-empty position")]
+    NonEmptyPosition,
+    #[msg("Invalid bin array")]
+    InvalidBinArray,
+    #[msg("Math overflow")]
+    MathOverflow,
+}
+
+#[derive(Accounts)]
+#[instruction(active_id: i32, bin_step: u16)]
+pub struct CreateLbPair<'info> {
+    #[account(mut)]
+    pub funder: Signer<'info>,
+
+    pub token_mint_x: Account<'info, Mint>,
+
+    pub token_mint_y: Account<'info, Mint>,
+
+    #[account(
+        init,
+        payer = funder,
+        space = 8 + LbPair::LEN,
+        seeds = [
+            b"lb_pair",
+            token_mint_x.key().as_ref(),
+            token_mint_y.key().as_ref(),
+            &bin_step.to_le_bytes()
+        ],
+        bump
+    )]
+    pub lb_pair: Account<'info, LbPair>,
+
+    #[account(
+        init,
+        payer = funder,
+        seeds = [lb_pair.key().as_ref(), token_mint_x.key().as_ref()],
+        bump,
+        token::mint = token_mint_x,
+        token::authority = lb_pair
+    )]
+    pub reserve_x: Account<'info, TokenAccount>,
+
+    #[account(
+        init,
+        payer = funder,
+        seeds = [lb_pair.key().as_ref(), token_mint_y.key().as_ref()],
+        bump,
+        token::mint = token_mint_y,
+        token::authority = lb_pair
+    )]
+    pub reserve_y: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AccrueFees<'info> {
+    pub funder: Signer<'info>,
+
+    #[account(mut)]
+    pub funder_token_x: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub funder_token_y: Account<'info, TokenAccount>,
+
+    #[account(has_one = reserve_x, has_one = reserve_y)]
+    pub lb_pair: Account<'info, LbPair>,
+
+    #[account(mut, has_one = lb_pair)]
+    pub position: AccountLoader<'info, PositionV2>,
+
+    #[account(mut)]
+    pub reserve_x: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub reserve_y: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+#[instruction(index: i64)]
+pub struct InitializeBinArray<'info> {
+    pub lb_pair: Account<'info, LbPair>,
+
+    #[account(
+        init,
+        payer = funder,
+        space = 8 + std::mem::size_of::<BinArray>(),
+        seeds = [b"bin_array", lb_pair.key().as_ref(), &index.to_le_bytes()],
+        bump
+    )]
+    pub bin_array: AccountLoader<'info, BinArray>,
+
+    #[account(mut)]
+    pub funder: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializePosition<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(init, payer = payer, space = 8 + std::mem::size_of::<PositionV2>())]
+    pub position: AccountLoader<'info, PositionV2>,
+
+    pub lb_pair: Account<'info, LbPair>,
+
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    pub rent: Sysvar<'info, Rent>,
+
+    /// CHECK: Not modelled by the mock
+    pub event_authority: UncheckedAccount<'info>,
+
+    /// CHECK: Not modelled by the mock
+    pub program: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ModifyLiquidity<'info> {
+    #[account(
+        mut,
+        has_one = lb_pair,
+        constraint = position.load()?.owner == sender.key() @ MockDlmmError::UnauthorizedAccess
+    )]
+    pub position: AccountLoader<'info, PositionV2>,
+
+    #[account(mut, has_one = reserve_x, has_one = reserve_y)]
+    pub lb_pair: Account<'info, LbPair>,
+
+    /// CHECK: Not modelled by the mock
+    #[account(mut)]
+    pub bin_array_bitmap_extension: Option<UncheckedAccount<'info>>,
+
+    #[account(mut, token::mint = lb_pair.token_x_mint)]
+    pub user_token_x: Account<'info, TokenAccount>,
+
+    #[account(mut, token::mint = lb_pair.token_y_mint)]
+    pub user_token_y: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub reserve_x: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub reserve_y: Account<'info, TokenAccount>,
+
+    #[account(address = lb_pair.token_x_mint)]
+    pub token_x_mint: Account<'info, Mint>,
+
+    #[account(address = lb_pair.token_y_mint)]
+    pub token_y_mint: Account<'info, Mint>,
+
+    #[account(mut, has_one = lb_pair)]
+    pub bin_array_lower: AccountLoader<'info, BinArray>,
+
+    #[account(mut, has_one = lb_pair)]
+    pub bin_array_upper: AccountLoader<'info, BinArray>,
+
+    pub sender: Signer<'info>,
+
+    pub token_x_program: Program<'info, Token>,
+
+    pub token_y_program: Program<'info, Token>,
+
+    /// CHECK: Not modelled by the mock
+    pub event_authority: UncheckedAccount<'info>,
+
+    /// CHECK: Not modelled by the mock
+    pub program: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimFee<'info> {
+    #[account(mut, has_one = reserve_x, has_one = reserve_y)]
+    pub lb_pair: Account<'info, LbPair>,
+
+    #[account(
+        mut,
+        has_one = lb_pair,
+        constraint = position.load()?.owner == sender.key() @ MockDlmmError::UnauthorizedAccess
+    )]
+    pub position: AccountLoader<'info, PositionV2>,
+
+    /// CHECK: Not modelled by the mock
+    #[account(mut)]
+    pub bin_array_lower: UncheckedAccount<'info>,
+
+    /// CHECK: Not modelled by the mock
+    #[account(mut)]
+    pub bin_array_upper: UncheckedAccount<'info>,
+
+    pub sender: Signer<'info>,
+
+    #[account(mut)]
+    pub reserve_x: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub reserve_y: Account<'info, TokenAccount>,
+
+    #[account(mut, token::mint = lb_pair.token_x_mint)]
+    pub user_token_x: Account<'info, TokenAccount>,
+
+    #[account(mut, token::mint = lb_pair.token_y_mint)]
+    pub user_token_y: Account<'info, TokenAccount>,
+
+    #[account(address = lb_pair.token_x_mint)]
+    pub token_x_mint: Account<'info, Mint>,
+
+    #[account(address = lb_pair.token_y_mint)]
+    pub token_y_mint: Account<'info, Mint>,
+
+    pub token_program: Program<'info, Token>,
+
+    /// CHECK: Not modelled by the mock
+    pub event_authority: UncheckedAccount<'info>,
+
+    /// CHECK: Not modelled by the mock
+    pub program: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClosePosition<'info> {
+    #[account(
+        mut,
+        has_one = lb_pair,
+        close = rent_receiver,
+        constraint = position.load()?.owner == sender.key() @ MockDlmmError::UnauthorizedAccess
+    )]
+    pub position: AccountLoader<'info, PositionV2>,
+
+    #[account(mut)]
+    pub lb_pair: Account<'info, LbPair>,
+
+    /// CHECK: Not modelled by the mock
+    #[account(mut)]
+    pub bin_array_lower: UncheckedAccount<'info>,
+
+    /// CHECK: Not modelled by the mock
+    #[account(mut)]
+    pub bin_array_upper: UncheckedAccount<'info>,
+
+    pub sender: Signer<'info>,
+
+    /// CHECK: Receives the position's rent
+    #[account(mut)]
+    pub rent_receiver: UncheckedAccount<'info>,
+
+    /// CHECK: Not modelled by the mock
+    pub event_authority: UncheckedAccount<'info>,
+
+    /// CHECK: Not modelled by the mock
+    pub program: UncheckedAccount<'info>,
+}
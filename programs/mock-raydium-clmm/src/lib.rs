@@ -0,0 +1,620 @@
+//! Local stand-in for the Raydium CLMM program
+//!
+//! Implements `open_position_v2`, `increase_liquidity_v2`,
+//! `decrease_liquidity_v2` and `close_position` with Raydium's instruction names, arguments and
+//! account order so x-liquidity-engine's CPIs can be exercised on localnet.
+//! `PoolState` and `PersonalPositionState` share Raydium's leading field
+//! layout.
+//!
+//! Simplifications:
+//! - Accounts the mock does not model (tick arrays, protocol positions, the
+//!   position NFT account, metadata, ...) are accepted unchecked and no NFT
+//!   is minted or burned.
+//! - Every deposit uses `amount_0_max` / `amount_1_max` in full and is
+//!   credited the liquidity those amounts back at the pool's fixed sqrt price
+//!   (floating point, rounded down); removing liquidity pays out its share of
+//!   everything deposited.
+
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Mint, Token, TokenAccount, Transfer};
+
+declare_id!("CAMMCzo5YL8w4VFF8KVHrK22GGUsp5VTaW7grrKgrWqK");
+
+#[program]
+pub mod mock_raydium_clmm {
+    use super::*;
+
+    /// Create a pool and its token vaults (mock-only setup)
+    pub fn create_pool(
+        ctx: Context<CreatePool>,
+        tick_spacing: u16,
+        sqrt_price_x64: u128,
+        tick_current: i32,
+    ) -> Result<()> {
+        let pool = &mut ctx.accounts.pool_state;
+        pool.bump = [ctx.bumps.pool_state];
+        pool.amm_config = ctx.accounts.amm_config.key();
+        pool.owner = ctx.accounts.payer.key();
+        pool.token_mint_0 = ctx.accounts.token_mint_0.key();
+        pool.token_mint_1 = ctx.accounts.token_mint_1.key();
+        pool.token_vault_0 = ctx.accounts.token_vault_0.key();
+        pool.token_vault_1 = ctx.accounts.token_vault_1.key();
+        pool.observation_key = Pubkey::default();
+        pool.mint_decimals_0 = ctx.accounts.token_mint_0.decimals;
+        pool.mint_decimals_1 = ctx.accounts.token_mint_1.decimals;
+        pool.tick_spacing = tick_spacing;
+        pool.liquidity = 0;
+        pool.sqrt_price_x64 = sqrt_price_x64;
+        pool.tick_current = tick_current;
+        Ok(())
+    }
+
+    /// Credit trading fees to a position, funded by `funder` (mock-only setup)
+    pub fn accrue_fees(ctx: Context<AccrueFees>, amount_0: u64, amount_1: u64) -> Result<()> {
+        for (amount, from, to) in [
+            (amount_0, &ctx.accounts.funder_token_0, &ctx.accounts.token_vault_0),
+            (amount_1, &ctx.accounts.funder_token_1, &ctx.accounts.token_vault_1),
+        ] {
+            if amount > 0 {
+                token::transfer(
+                    CpiContext::new(
+                        ctx.accounts.token_program.to_account_info(),
+                        Transfer {
+                            from: from.to_account_info(),
+                            to: to.to_account_info(),
+                            authority: ctx.accounts.funder.to_account_info(),
+                        },
+                    ),
+                    amount,
+                )?;
+            }
+        }
+
+        let position = &mut ctx.accounts.personal_position;
+        position.token_fees_owed_0 += amount_0;
+        position.token_fees_owed_1 += amount_1;
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn open_position_v2(
+        ctx: Context<OpenPositionV2>,
+        tick_lower_index: i32,
+        tick_upper_index: i32,
+        _tick_array_lower_start_index: i32,
+        _tick_array_upper_start_index: i32,
+        liquidity: u128,
+        amount_0_max: u64,
+        amount_1_max: u64,
+        _with_metadata: bool,
+        base_flag: Option<bool>,
+    ) -> Result<()> {
+        require!(tick_lower_index < tick_upper_index, MockClmmError::InvalidTickIndex);
+
+        let position = &mut ctx.accounts.personal_position;
+        position.bump = ctx.bumps.personal_position;
+        position.nft_mint = ctx.accounts.position_nft_mint.key();
+        position.pool_id = ctx.accounts.pool_state.key();
+        position.tick_lower_index = tick_lower_index;
+        position.tick_upper_index = tick_upper_index;
+        position.owner = ctx.accounts.position_nft_owner.key();
+        if liquidity == 0 && base_flag.is_none() {
+            return Ok(());
+        }
+
+        deposit(
+            &ctx.accounts.token_program,
+            &ctx.accounts.payer,
+            [&ctx.accounts.token_account_0, &ctx.accounts.token_account_1],
+            [&ctx.accounts.token_vault_0, &ctx.accounts.token_vault_1],
+            [amount_0_max, amount_1_max],
+        )?;
+        add_liquidity(
+            &mut ctx.accounts.pool_state,
+            &mut ctx.accounts.personal_position,
+            amount_0_max,
+            amount_1_max,
+        )
+    }
+
+    pub fn increase_liquidity_v2(
+        ctx: Context<IncreaseLiquidityV2>,
+        _liquidity: u128,
+        amount_0_max: u64,
+        amount_1_max: u64,
+        _base_flag: Option<bool>,
+    ) -> Result<()> {
+        deposit(
+            &ctx.accounts.token_program,
+            &ctx.accounts.nft_owner,
+            [&ctx.accounts.token_account_0, &ctx.accounts.token_account_1],
+            [&ctx.accounts.token_vault_0, &ctx.accounts.token_vault_1],
+            [amount_0_max, amount_1_max],
+        )?;
+        add_liquidity(
+            &mut ctx.accounts.pool_state,
+            &mut ctx.accounts.personal_position,
+            amount_0_max,
+            amount_1_max,
+        )
+    }
+
+    /// Remove `liquidity` and pay out its share of the deposits plus all owed fees
+    pub fn decrease_liquidity_v2(
+        ctx: Context<DecreaseLiquidityV2>,
+        liquidity: u128,
+        amount_0_min: u64,
+        amount_1_min: u64,
+    ) -> Result<()> {
+        let position = &mut ctx.accounts.personal_position;
+        require!(liquidity <= position.liquidity, MockClmmError::InvalidLiquidity);
+
+        let share = |amount: u64| -> u64 {
+            if position.liquidity == 0 {
+                0
+            } else {
+                (amount as u128 * liquidity / position.liquidity) as u64
+            }
+        };
+        let amount_0 = share(position.amount_0);
+        let amount_1 = share(position.amount_1);
+        require!(
+            amount_0 >= amount_0_min && amount_1 >= amount_1_min,
+            MockClmmError::PriceSlippageCheck
+        );
+
+        let payout_0 = amount_0 + position.token_fees_owed_0;
+        let payout_1 = amount_1 + position.token_fees_owed_1;
+        position.amount_0 -= amount_0;
+        position.amount_1 -= amount_1;
+        position.liquidity -= liquidity;
+        position.token_fees_owed_0 = 0;
+        position.token_fees_owed_1 = 0;
+
+        let pool = &mut ctx.accounts.pool_state;
+        pool.liquidity -= liquidity;
+        let seeds: &[&[u8]] = &[
+            b"pool",
+            pool.amm_config.as_ref(),
+            pool.token_mint_0.as_ref(),
+            pool.token_mint_1.as_ref(),
+            &pool.bump,
+        ];
+        for (amount, from, to) in [
+            (payout_0, &ctx.accounts.token_vault_0, &ctx.accounts.recipient_token_account_0),
+            (payout_1, &ctx.accounts.token_vault_1, &ctx.accounts.recipient_token_account_1),
+        ] {
+            if amount > 0 {
+                token::transfer(
+                    CpiContext::new_with_signer(
+                        ctx.accounts.token_program.to_account_info(),
+                        Transfer {
+                            from: from.to_account_info(),
+                            to: to.to_account_info(),
+                            authority: pool.to_account_info(),
+                        },
+                        &[seeds],
+                    ),
+                    amount,
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Close an empty position; its rent goes to the NFT owner
+    pub fn close_position(ctx: Context<ClosePosition>) -> Result<()> {
+        let position = &ctx.accounts.personal_position;
+        require!(
+            position.liquidity == 0 && position.token_fees_owed_0 == 0 && position.token_fees_owed_1 == 0,
+            MockClmmError::ClosePositionErr
+        );
+        Ok(())
+    }
+}
+
+fn deposit<'info>(
+    token_program: &Program<'info, Token>,
+    authority: &Signer<'info>,
+    from: [&Account<'info, TokenAccount>; 2],
+    to: [&Account<'info, TokenAccount>; 2],
+    amounts: [u64; 2],
+) -> Result<()> {
+    for i in 0..2 {
+        if amounts[i] > 0 {
+            token::transfer(
+                CpiContext::new(
+                    token_program.to_account_info(),
+                    Transfer {
+                        from: from[i].to_account_info(),
+                        to: to[i].to_account_info(),
+                        authority: authority.to_account_info(),
+                    },
+                ),
+                amounts[i],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn add_liquidity(
+    pool: &mut PoolState,
+    position: &mut PersonalPositionState,
+    amount_0: u64,
+    amount_1: u64,
+) -> Result<()> {
+    let liquidity = liquidity_for_amounts(
+        pool.sqrt_price_x64,
+        position.tick_lower_index,
+        position.tick_upper_index,
+        amount_0,
+        amount_1,
+    );
+    position.amount_0 += amount_0;
+    position.amount_1 += amount_1;
+    position.liquidity += liquidity;
+    pool.liquidity += liquidity;
+    Ok(())
+}
+
+/// Largest liquidity `amount_0` / `amount_1` back over `[tick_lower, tick_upper]`
+fn liquidity_for_amounts(sqrt_price_x64: u128, tick_lower: i32, tick_upper: i32, amount_0: u64, amount_1: u64) -> u128 {
+    let price = sqrt_price_x64 as f64 / (1u128 << 64) as f64;
+    let lower = 1.0001f64.powf(tick_lower as f64 / 2.0);
+    let upper = 1.0001f64.powf(tick_upper as f64 / 2.0);
+    let price = price.clamp(lower, upper);
+    let from_0 = amount_0 as f64 * price * upper / (upper - price);
+    let from_1 = amount_1 as f64 / (price - lower);
+    let liquidity = if price <= lower {
+        from_0
+    } else if price >= upper {
+        from_1
+    } else {
+        from_0.min(from_1)
+    };
+    liquidity as u128
+}
+
+/// Leading fields follow Raydium's `PoolState` layout
+#[account]
+pub struct PoolState {
+    pub bump: [u8; 1],
+    pub amm_config: Pubkey,
+    pub owner: Pubkey,
+    pub token_mint_0: Pubkey,
+    pub token_mint_1: Pubkey,
+    pub token_vault_0: Pubkey,
+    pub token_vault_1: Pubkey,
+    pub observation_key: Pubkey,
+    pub mint_decimals_0: u8,
+    pub mint_decimals_1: u8,
+    pub tick_spacing: u16,
+    pub liquidity: u128,
+    pub sqrt_price_x64: u128,
+    pub tick_current: i32,
+}
+
+impl PoolState {
+    pub const LEN: usize = 1 + 32 * 7 + 1 + 1 + 2 + 16 + 16 + 4;
+}
+
+/// Leading fields follow Raydium's `PersonalPositionState` layout; `owner`,
+/// `amount_0` and `amount_1` are mock-only
+#[account]
+pub struct PersonalPositionState {
+    pub bump: u8,
+    pub nft_mint: Pubkey,
+    pub pool_id: Pubkey,
+    pub tick_lower_index: i32,
+    pub tick_upper_index: i32,
+    pub liquidity: u128,
+    pub fee_growth_inside_0_last_x64: u128,
+    pub fee_growth_inside_1_last_x64: u128,
+    pub token_fees_owed_0: u64,
+    pub token_fees_owed_1: u64,
+    pub owner: Pubkey,
+    pub amount_0: u64,
+    pub amount_1: u64,
+}
+
+impl PersonalPositionState {
+    pub const LEN: usize = 1 + 32 + 32 + 4 + 4 + 16 + 16 + 16 + 8 + 8 + 32 + 8 + 8;
+}
+
+#[error_code]
+pub enum MockClmmError {
+    #[msg("Invalid tick index")]
+    InvalidTickIndex,
+    #[msg("Invalid liquidity")]
+    InvalidLiquidity,
+    #[msg("Price slippage check")]
+    PriceSlippageCheck,
+    #[msg("Not approved")]
+    NotApproved,
+    #[msg("Remove liquidity and collect fees before closing the position")]
+    ClosePositionErr,
+}
+
+#[derive(Accounts)]
+pub struct CreatePool<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: Only used as a pool seed
+    pub amm_config: UncheckedAccount<'info>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + PoolState::LEN,
+        seeds = [b"pool", amm_config.key().as_ref(), token_mint_0.key().as_ref(), token_mint_1.key().as_ref()],
+        bump
+    )]
+    pub pool_state: Account<'info, PoolState>,
+
+    pub token_mint_0: Account<'info, Mint>,
+
+    pub token_mint_1: Account<'info, Mint>,
+
+    #[account(
+        init,
+        payer = payer,
+        seeds = [b"pool_vault", pool_state.key().as_ref(), token_mint_0.key().as_ref()],
+        bump,
+        token::mint = token_mint_0,
+        token::authority = pool_state
+    )]
+    pub token_vault_0: Account<'info, TokenAccount>,
+
+    #[account(
+        init,
+        payer = payer,
+        seeds = [b"pool_vault", pool_state.key().as_ref(), token_mint_1.key().as_ref()],
+        bump,
+        token::mint = token_mint_1,
+        token::authority = pool_state
+    )]
+    pub token_vault_1: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AccrueFees<'info> {
+    pub funder: Signer<'info>,
+
+    #[account(mut)]
+    pub funder_token_0: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub funder_token_1: Account<'info, TokenAccount>,
+
+    #[account(has_one = token_vault_0, has_one = token_vault_1)]
+    pub pool_state: Account<'info, PoolState>,
+
+    #[account(mut, constraint = personal_position.pool_id == pool_state.key())]
+    pub personal_position: Account<'info, PersonalPositionState>,
+
+    #[account(mut)]
+    pub token_vault_0: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub token_vault_1: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct OpenPositionV2<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: Recorded as the position owner
+    pub position_nft_owner: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub position_nft_mint: Signer<'info>,
+
+    /// CHECK: Not modelled by the mock
+    #[account(mut)]
+    pub position_nft_account: UncheckedAccount<'info>,
+
+    /// CHECK: Not modelled by the mock
+    #[account(mut)]
+    pub metadata_account: UncheckedAccount<'info>,
+
+    #[account(mut, has_one = token_vault_0, has_one = token_vault_1)]
+    pub pool_state: Account<'info, PoolState>,
+
+    /// CHECK: Not modelled by the mock
+    #[account(mut)]
+    pub protocol_position: UncheckedAccount<'info>,
+
+    /// CHECK: Not modelled by the mock
+    #[account(mut)]
+    pub tick_array_lower: UncheckedAccount<'info>,
+
+    /// CHECK: Not modelled by the mock
+    #[account(mut)]
+    pub tick_array_upper: UncheckedAccount<'info>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + PersonalPositionState::LEN,
+        seeds = [b"position", position_nft_mint.key().as_ref()],
+        bump
+    )]
+    pub personal_position: Account<'info, PersonalPositionState>,
+
+    #[account(mut, token::mint = pool_state.token_mint_0)]
+    pub token_account_0: Account<'info, TokenAccount>,
+
+    #[account(mut, token::mint = pool_state.token_mint_1)]
+    pub token_account_1: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub token_vault_0: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub token_vault_1: Account<'info, TokenAccount>,
+
+    pub rent: Sysvar<'info, Rent>,
+
+    pub system_program: Program<'info, System>,
+
+    pub token_program: Program<'info, Token>,
+
+    /// CHECK: Not modelled by the mock
+    pub associated_token_program: UncheckedAccount<'info>,
+
+    /// CHECK: Not modelled by the mock
+    pub metadata_program: UncheckedAccount<'info>,
+
+    /// CHECK: Not modelled by the mock
+    pub token_program_2022: UncheckedAccount<'info>,
+
+    #[account(address = pool_state.token_mint_0)]
+    pub vault_0_mint: Account<'info, Mint>,
+
+    #[account(address = pool_state.token_mint_1)]
+    pub vault_1_mint: Account<'info, Mint>,
+}
+
+#[derive(Accounts)]
+pub struct ClosePosition<'info> {
+    #[account(mut)]
+    pub nft_owner: Signer<'info>,
+
+    /// CHECK: Not modelled by the mock
+    #[account(mut)]
+    pub position_nft_mint: UncheckedAccount<'info>,
+
+    /// CHECK: Not modelled by the mock
+    #[account(mut)]
+    pub position_nft_account: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        close = nft_owner,
+        constraint = personal_position.nft_mint == position_nft_mint.key(),
+        constraint = personal_position.owner == nft_owner.key() @ MockClmmError::NotApproved
+    )]
+    pub personal_position: Account<'info, PersonalPositionState>,
+
+    pub system_program: Program<'info, System>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct IncreaseLiquidityV2<'info> {
+    pub nft_owner: Signer<'info>,
+
+    /// CHECK: Not modelled by the mock
+    pub nft_account: UncheckedAccount<'info>,
+
+    #[account(mut, has_one = token_vault_0, has_one = token_vault_1)]
+    pub pool_state: Account<'info, PoolState>,
+
+    /// CHECK: Not modelled by the mock
+    #[account(mut)]
+    pub protocol_position: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        constraint = personal_position.pool_id == pool_state.key(),
+        constraint = personal_position.owner == nft_owner.key() @ MockClmmError::NotApproved
+    )]
+    pub personal_position: Account<'info, PersonalPositionState>,
+
+    /// CHECK: Not modelled by the mock
+    #[account(mut)]
+    pub tick_array_lower: UncheckedAccount<'info>,
+
+    /// CHECK: Not modelled by the mock
+    #[account(mut)]
+    pub tick_array_upper: UncheckedAccount<'info>,
+
+    #[account(mut, token::mint = pool_state.token_mint_0)]
+    pub token_account_0: Account<'info, TokenAccount>,
+
+    #[account(mut, token::mint = pool_state.token_mint_1)]
+    pub token_account_1: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub token_vault_0: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub token_vault_1: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+
+    /// CHECK: Not modelled by the mock
+    pub token_program_2022: UncheckedAccount<'info>,
+
+    #[account(address = pool_state.token_mint_0)]
+    pub vault_0_mint: Account<'info, Mint>,
+
+    #[account(address = pool_state.token_mint_1)]
+    pub vault_1_mint: Account<'info, Mint>,
+}
+
+#[derive(Accounts)]
+pub struct DecreaseLiquidityV2<'info> {
+    pub nft_owner: Signer<'info>,
+
+    /// CHECK: Not modelled by the mock
+    pub nft_account: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        constraint = personal_position.pool_id == pool_state.key(),
+        constraint = personal_position.owner == nft_owner.key() @ MockClmmError::NotApproved
+    )]
+    pub personal_position: Account<'info, PersonalPositionState>,
+
+    #[account(mut, has_one = token_vault_0, has_one = token_vault_1)]
+    pub pool_state: Account<'info, PoolState>,
+
+    /// CHECK: Not modelled by the mock
+    #[account(mut)]
+    pub protocol_position: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub token_vault_0: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub token_vault_1: Account<'info, TokenAccount>,
+
+    /// CHECK: Not modelled by the mock
+    #[account(mut)]
+    pub tick_array_lower: UncheckedAccount<'info>,
+
+    /// CHECK: Not modelled by the mock
+    #[account(mut)]
+    pub tick_array_upper: UncheckedAccount<'info>,
+
+    #[account(mut, token::mint = pool_state.token_mint_0)]
+    pub recipient_token_account_0: Account<'info, TokenAccount>,
+
+    #[account(mut, token::mint = pool_state.token_mint_1)]
+    pub recipient_token_account_1: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+
+    /// CHECK: Not modelled by the mock
+    pub token_program_2022: UncheckedAccount<'info>,
+
+    /// CHECK: Not modelled by the mock
+    pub memo_program: UncheckedAccount<'info>,
+
+    #[account(address = pool_state.token_mint_0)]
+    pub vault_0_mint: Account<'info, Mint>,
+
+    #[account(address = pool_state.token_mint_1)]
+    pub vault_1_mint: Account<'info, Mint>,
+}
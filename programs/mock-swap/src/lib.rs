@@ -0,0 +1,187 @@
+//! Local stand-in for a Jupiter-compatible swap aggregator
+//!
+//! Implements `route` with Jupiter's instruction name and amount arguments,
+//! with the user transfer authority and the user's source and destination
+//! token accounts in Jupiter's order, so x-liquidity-engine's swap leg can be
+//! exercised on localnet.
+//!
+//! Simplifications:
+//! - There is no route plan: every swap goes through one mock pool that
+//!   pays out `in_amount * rate_bps / 10_000` in either direction.
+//! - `quoted_out_amount`, `slippage_bps` and `platform_fee_bps` are ignored;
+//!   the caller is left to check what it received.
+
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Mint, Token, TokenAccount, Transfer};
+
+declare_id!("HVkLtWKv6xdaR78uVq9X2RsL5PctFTbNcCxe86pcDBC9");
+
+const MAX_BPS: u16 = 10_000;
+
+#[program]
+pub mod mock_swap {
+    use super::*;
+
+    /// Create a pool and its token vaults (mock-only setup)
+    pub fn create_pool(ctx: Context<CreatePool>, rate_bps: u16) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+        pool.mint_a = ctx.accounts.mint_a.key();
+        pool.mint_b = ctx.accounts.mint_b.key();
+        pool.vault_a = ctx.accounts.vault_a.key();
+        pool.vault_b = ctx.accounts.vault_b.key();
+        pool.rate_bps = rate_bps;
+        pool.bump = ctx.bumps.pool;
+        Ok(())
+    }
+
+    /// Change the pool's exchange rate (mock-only setup)
+    pub fn set_rate(ctx: Context<SetRate>, rate_bps: u16) -> Result<()> {
+        ctx.accounts.pool.rate_bps = rate_bps;
+        Ok(())
+    }
+
+    /// Swap `in_amount` of the source token for the pool's rate
+    pub fn route(
+        ctx: Context<Route>,
+        in_amount: u64,
+        _quoted_out_amount: u64,
+        _slippage_bps: u16,
+        _platform_fee_bps: u8,
+    ) -> Result<()> {
+        let pool = &ctx.accounts.pool;
+        let source_mint = ctx.accounts.user_source_token_account.mint;
+        let destination_mint = ctx.accounts.user_destination_token_account.mint;
+        let (pool_source, pool_destination) = if source_mint == pool.mint_a && destination_mint == pool.mint_b {
+            (&ctx.accounts.pool_vault_a, &ctx.accounts.pool_vault_b)
+        } else if source_mint == pool.mint_b && destination_mint == pool.mint_a {
+            (&ctx.accounts.pool_vault_b, &ctx.accounts.pool_vault_a)
+        } else {
+            return err!(MockSwapError::InvalidMint);
+        };
+        let out_amount = (in_amount as u128 * pool.rate_bps as u128 / MAX_BPS as u128) as u64;
+
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.user_source_token_account.to_account_info(),
+                    to: pool_source.to_account_info(),
+                    authority: ctx.accounts.user_transfer_authority.to_account_info(),
+                },
+            ),
+            in_amount,
+        )?;
+        if out_amount > 0 {
+            let seeds: &[&[u8]] = &[
+                b"pool",
+                pool.mint_a.as_ref(),
+                pool.mint_b.as_ref(),
+                &[pool.bump],
+            ];
+            token::transfer(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    Transfer {
+                        from: pool_destination.to_account_info(),
+                        to: ctx.accounts.user_destination_token_account.to_account_info(),
+                        authority: pool.to_account_info(),
+                    },
+                    &[seeds],
+                ),
+                out_amount,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[account]
+pub struct SwapPool {
+    pub mint_a: Pubkey,
+    pub mint_b: Pubkey,
+    pub vault_a: Pubkey,
+    pub vault_b: Pubkey,
+    /// Output per input token, in basis points
+    pub rate_bps: u16,
+    pub bump: u8,
+}
+
+impl SwapPool {
+    pub const LEN: usize = 32 * 4 + 2 + 1;
+}
+
+#[error_code]
+pub enum MockSwapError {
+    #[msg("Token accounts do not match the pool's mints")]
+    InvalidMint,
+}
+
+#[derive(Accounts)]
+pub struct CreatePool<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub mint_a: Account<'info, Mint>,
+
+    pub mint_b: Account<'info, Mint>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + SwapPool::LEN,
+        seeds = [b"pool", mint_a.key().as_ref(), mint_b.key().as_ref()],
+        bump
+    )]
+    pub pool: Account<'info, SwapPool>,
+
+    #[account(
+        init,
+        payer = payer,
+        seeds = [b"pool_vault", pool.key().as_ref(), mint_a.key().as_ref()],
+        bump,
+        token::mint = mint_a,
+        token::authority = pool
+    )]
+    pub vault_a: Account<'info, TokenAccount>,
+
+    #[account(
+        init,
+        payer = payer,
+        seeds = [b"pool_vault", pool.key().as_ref(), mint_b.key().as_ref()],
+        bump,
+        token::mint = mint_b,
+        token::authority = pool
+    )]
+    pub vault_b: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetRate<'info> {
+    #[account(mut)]
+    pub pool: Account<'info, SwapPool>,
+}
+
+#[derive(Accounts)]
+pub struct Route<'info> {
+    pub token_program: Program<'info, Token>,
+
+    pub user_transfer_authority: Signer<'info>,
+
+    #[account(mut)]
+    pub user_source_token_account: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub user_destination_token_account: Account<'info, TokenAccount>,
+
+    pub pool: Account<'info, SwapPool>,
+
+    #[account(mut, address = pool.vault_a)]
+    pub pool_vault_a: Account<'info, TokenAccount>,
+
+    #[account(mut, address = pool.vault_b)]
+    pub pool_vault_b: Account<'info, TokenAccount>,
+}
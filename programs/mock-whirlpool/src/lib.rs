@@ -0,0 +1,547 @@
+//! Local stand-in for the Orca Whirlpools program
+//!
+//! Implements `open_position`, `increase_liquidity`, `decrease_liquidity`,
+//! `update_fees_and_rewards`, `collect_fees` and `close_position` with
+//! Whirlpool's instruction names, arguments and account order so
+//! x-liquidity-engine's CPIs can be exercised on localnet.
+//! `Whirlpool` and `Position` share Orca's leading field layout.
+//!
+//! Simplifications:
+//! - Accounts the mock does not model (tick arrays, the position token
+//!   account, ...) are accepted unchecked and no position NFT is minted or
+//!   burned.
+//! - `position_authority` must be the position's recorded `owner`.
+//! - `increase_liquidity` charges the token amounts for the liquidity at the
+//!   pool's fixed sqrt price (floating point, rounded up); `decrease_liquidity`
+//!   pays out the removed liquidity's share of everything deposited.
+//! - `accrue_fees` credits positions' owed fees directly, so
+//!   `update_fees_and_rewards` has nothing to update.
+
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Mint, Token, TokenAccount, Transfer};
+
+declare_id!("whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc");
+
+#[program]
+pub mod mock_whirlpool {
+    use super::*;
+
+    /// Create a Whirlpool and its token vaults (mock-only setup)
+    pub fn create_pool(
+        ctx: Context<CreatePool>,
+        tick_spacing: u16,
+        sqrt_price: u128,
+        tick_current_index: i32,
+    ) -> Result<()> {
+        let whirlpool = &mut ctx.accounts.whirlpool;
+        whirlpool.whirlpools_config = ctx.accounts.whirlpools_config.key();
+        whirlpool.whirlpool_bump = [ctx.bumps.whirlpool];
+        whirlpool.tick_spacing = tick_spacing;
+        whirlpool.tick_spacing_seed = tick_spacing.to_le_bytes();
+        whirlpool.fee_rate = 0;
+        whirlpool.protocol_fee_rate = 0;
+        whirlpool.liquidity = 0;
+        whirlpool.sqrt_price = sqrt_price;
+        whirlpool.tick_current_index = tick_current_index;
+        whirlpool.token_mint_a = ctx.accounts.token_mint_a.key();
+        whirlpool.token_vault_a = ctx.accounts.token_vault_a.key();
+        whirlpool.token_mint_b = ctx.accounts.token_mint_b.key();
+        whirlpool.token_vault_b = ctx.accounts.token_vault_b.key();
+        Ok(())
+    }
+
+    /// Credit trading fees to a position, funded by `funder` (mock-only setup)
+    pub fn accrue_fees(ctx: Context<AccrueFees>, amount_a: u64, amount_b: u64) -> Result<()> {
+        for (amount, from, to) in [
+            (amount_a, &ctx.accounts.funder_token_a, &ctx.accounts.token_vault_a),
+            (amount_b, &ctx.accounts.funder_token_b, &ctx.accounts.token_vault_b),
+        ] {
+            if amount > 0 {
+                token::transfer(
+                    CpiContext::new(
+                        ctx.accounts.token_program.to_account_info(),
+                        Transfer {
+                            from: from.to_account_info(),
+                            to: to.to_account_info(),
+                            authority: ctx.accounts.funder.to_account_info(),
+                        },
+                    ),
+                    amount,
+                )?;
+            }
+        }
+
+        let position = &mut ctx.accounts.position;
+        position.fee_owed_a += amount_a;
+        position.fee_owed_b += amount_b;
+        Ok(())
+    }
+
+    pub fn open_position(
+        ctx: Context<OpenPosition>,
+        _position_bump: u8,
+        tick_lower_index: i32,
+        tick_upper_index: i32,
+    ) -> Result<()> {
+        require!(tick_lower_index < tick_upper_index, MockWhirlpoolError::InvalidTickIndex);
+
+        let position = &mut ctx.accounts.position;
+        position.whirlpool = ctx.accounts.whirlpool.key();
+        position.position_mint = ctx.accounts.position_mint.key();
+        position.tick_lower_index = tick_lower_index;
+        position.tick_upper_index = tick_upper_index;
+        position.owner = ctx.accounts.owner.key();
+        Ok(())
+    }
+
+    pub fn increase_liquidity(
+        ctx: Context<ModifyLiquidity>,
+        liquidity_amount: u128,
+        token_max_a: u64,
+        token_max_b: u64,
+    ) -> Result<()> {
+        require!(liquidity_amount > 0, MockWhirlpoolError::LiquidityZero);
+        let position = &mut ctx.accounts.position;
+        let (amount_a, amount_b) = amounts_for_liquidity(
+            ctx.accounts.whirlpool.sqrt_price,
+            position.tick_lower_index,
+            position.tick_upper_index,
+            liquidity_amount,
+        );
+        require!(
+            amount_a <= token_max_a && amount_b <= token_max_b,
+            MockWhirlpoolError::TokenMaxExceeded
+        );
+
+        for (amount, from, to) in [
+            (amount_a, &ctx.accounts.token_owner_account_a, &ctx.accounts.token_vault_a),
+            (amount_b, &ctx.accounts.token_owner_account_b, &ctx.accounts.token_vault_b),
+        ] {
+            if amount > 0 {
+                token::transfer(
+                    CpiContext::new(
+                        ctx.accounts.token_program.to_account_info(),
+                        Transfer {
+                            from: from.to_account_info(),
+                            to: to.to_account_info(),
+                            authority: ctx.accounts.position_authority.to_account_info(),
+                        },
+                    ),
+                    amount,
+                )?;
+            }
+        }
+
+        position.amount_a += amount_a;
+        position.amount_b += amount_b;
+        position.liquidity += liquidity_amount;
+        ctx.accounts.whirlpool.liquidity += liquidity_amount;
+        Ok(())
+    }
+
+    /// Remove `liquidity_amount` and pay out its share of the deposits
+    pub fn decrease_liquidity(
+        ctx: Context<ModifyLiquidity>,
+        liquidity_amount: u128,
+        token_min_a: u64,
+        token_min_b: u64,
+    ) -> Result<()> {
+        require!(liquidity_amount > 0, MockWhirlpoolError::LiquidityZero);
+        let position = &mut ctx.accounts.position;
+        require!(
+            liquidity_amount <= position.liquidity,
+            MockWhirlpoolError::LiquidityUnderflow
+        );
+
+        let share = |amount: u64| (amount as u128 * liquidity_amount / position.liquidity) as u64;
+        let amount_a = share(position.amount_a);
+        let amount_b = share(position.amount_b);
+        require!(
+            amount_a >= token_min_a && amount_b >= token_min_b,
+            MockWhirlpoolError::TokenMinSubceeded
+        );
+        position.amount_a -= amount_a;
+        position.amount_b -= amount_b;
+        position.liquidity -= liquidity_amount;
+
+        let whirlpool = &mut ctx.accounts.whirlpool;
+        whirlpool.liquidity -= liquidity_amount;
+        pay_out(
+            &ctx.accounts.token_program,
+            whirlpool,
+            [&ctx.accounts.token_vault_a, &ctx.accounts.token_vault_b],
+            [&ctx.accounts.token_owner_account_a, &ctx.accounts.token_owner_account_b],
+            [amount_a, amount_b],
+        )
+    }
+
+    /// Bring a position's owed fees up to date (no-op in the mock)
+    pub fn update_fees_and_rewards(_ctx: Context<UpdateFeesAndRewards>) -> Result<()> {
+        Ok(())
+    }
+
+    /// Pay out all fees owed to a position
+    pub fn collect_fees(ctx: Context<CollectFees>) -> Result<()> {
+        let position = &mut ctx.accounts.position;
+        let amounts = [position.fee_owed_a, position.fee_owed_b];
+        position.fee_owed_a = 0;
+        position.fee_owed_b = 0;
+        pay_out(
+            &ctx.accounts.token_program,
+            &ctx.accounts.whirlpool,
+            [&ctx.accounts.token_vault_a, &ctx.accounts.token_vault_b],
+            [&ctx.accounts.token_owner_account_a, &ctx.accounts.token_owner_account_b],
+            amounts,
+        )
+    }
+
+    /// Close an empty position; its rent goes to `receiver`
+    pub fn close_position(ctx: Context<ClosePosition>) -> Result<()> {
+        let position = &ctx.accounts.position;
+        require!(
+            position.liquidity == 0 && position.fee_owed_a == 0 && position.fee_owed_b == 0,
+            MockWhirlpoolError::ClosePositionNotEmpty
+        );
+        Ok(())
+    }
+}
+
+/// Token amounts backing `liquidity` over `[tick_lower, tick_upper]`, rounded up
+fn amounts_for_liquidity(sqrt_price: u128, tick_lower: i32, tick_upper: i32, liquidity: u128) -> (u64, u64) {
+    let price = sqrt_price as f64 / (1u128 << 64) as f64;
+    let lower = 1.0001f64.powf(tick_lower as f64 / 2.0);
+    let upper = 1.0001f64.powf(tick_upper as f64 / 2.0);
+    let liquidity = liquidity as f64;
+    let price = price.clamp(lower, upper);
+    let amount_a = liquidity * (upper - price) / (price * upper);
+    let amount_b = liquidity * (price - lower);
+    (amount_a.ceil() as u64, amount_b.ceil() as u64)
+}
+
+fn pay_out<'info>(
+    token_program: &Program<'info, Token>,
+    whirlpool: &Account<'info, Whirlpool>,
+    from: [&Account<'info, TokenAccount>; 2],
+    to: [&Account<'info, TokenAccount>; 2],
+    amounts: [u64; 2],
+) -> Result<()> {
+    let tick_spacing_seed = whirlpool.tick_spacing_seed;
+    let seeds: &[&[u8]] = &[
+        b"whirlpool",
+        whirlpool.whirlpools_config.as_ref(),
+        whirlpool.token_mint_a.as_ref(),
+        whirlpool.token_mint_b.as_ref(),
+        &tick_spacing_seed,
+        &whirlpool.whirlpool_bump,
+    ];
+    for i in 0..2 {
+        if amounts[i] > 0 {
+            token::transfer(
+                CpiContext::new_with_signer(
+                    token_program.to_account_info(),
+                    Transfer {
+                        from: from[i].to_account_info(),
+                        to: to[i].to_account_info(),
+                        authority: whirlpool.to_account_info(),
+                    },
+                    &[seeds],
+                ),
+                amounts[i],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Leading fields follow Orca's `Whirlpool` layout
+#[account]
+pub struct Whirlpool {
+    pub whirlpools_config: Pubkey,
+    pub whirlpool_bump: [u8; 1],
+    pub tick_spacing: u16,
+    pub tick_spacing_seed: [u8; 2],
+    pub fee_rate: u16,
+    pub protocol_fee_rate: u16,
+    pub liquidity: u128,
+    pub sqrt_price: u128,
+    pub tick_current_index: i32,
+    pub protocol_fee_owed_a: u64,
+    pub protocol_fee_owed_b: u64,
+    pub token_mint_a: Pubkey,
+    pub token_vault_a: Pubkey,
+    pub fee_growth_global_a: u128,
+    pub token_mint_b: Pubkey,
+    pub token_vault_b: Pubkey,
+    pub fee_growth_global_b: u128,
+}
+
+impl Whirlpool {
+    pub const LEN: usize = 32 + 1 + 2 + 2 + 2 + 2 + 16 + 16 + 4 + 8 + 8 + 32 + 32 + 16 + 32 + 32 + 16;
+}
+
+/// Leading fields follow Orca's `Position` layout; `owner`, `amount_a` and
+/// `amount_b` are mock-only
+#[account]
+pub struct Position {
+    pub whirlpool: Pubkey,
+    pub position_mint: Pubkey,
+    pub liquidity: u128,
+    pub tick_lower_index: i32,
+    pub tick_upper_index: i32,
+    pub fee_growth_checkpoint_a: u128,
+    pub fee_owed_a: u64,
+    pub fee_growth_checkpoint_b: u128,
+    pub fee_owed_b: u64,
+    pub owner: Pubkey,
+    pub amount_a: u64,
+    pub amount_b: u64,
+}
+
+impl Position {
+    pub const LEN: usize = 32 + 32 + 16 + 4 + 4 + 16 + 8 + 16 + 8 + 32 + 8 + 8;
+}
+
+#[error_code]
+pub enum MockWhirlpoolError {
+    #[msg("Invalid tick index")]
+    InvalidTickIndex,
+    #[msg("Liquidity amount must be greater than zero")]
+    LiquidityZero,
+    #[msg("Liquidity underflow")]
+    LiquidityUnderflow,
+    #[msg("Exceeded token max")]
+    TokenMaxExceeded,
+    #[msg("Did not meet token min")]
+    TokenMinSubceeded,
+    #[msg("Missing or invalid delegate")]
+    MissingOrInvalidDelegate,
+    #[msg("Position is not empty It cannot be closed")]
+    ClosePositionNotEmpty,
+}
+
+#[derive(Accounts)]
+#[instruction(tick_spacing: u16)]
+pub struct CreatePool<'info> {
+    #[account(mut)]
+    pub funder: Signer<'info>,
+
+    /// CHECK: Only used as a pool seed
+    pub whirlpools_config: UncheckedAccount<'info>,
+
+    pub token_mint_a: Account<'info, Mint>,
+
+    pub token_mint_b: Account<'info, Mint>,
+
+    #[account(
+        init,
+        payer = funder,
+        space = 8 + Whirlpool::LEN,
+        seeds = [
+            b"whirlpool",
+            whirlpools_config.key().as_ref(),
+            token_mint_a.key().as_ref(),
+            token_mint_b.key().as_ref(),
+            &tick_spacing.to_le_bytes()
+        ],
+        bump
+    )]
+    pub whirlpool: Account<'info, Whirlpool>,
+
+    #[account(
+        init,
+        payer = funder,
+        seeds = [b"pool_vault", whirlpool.key().as_ref(), token_mint_a.key().as_ref()],
+        bump,
+        token::mint = token_mint_a,
+        token::authority = whirlpool
+    )]
+    pub token_vault_a: Account<'info, TokenAccount>,
+
+    #[account(
+        init,
+        payer = funder,
+        seeds = [b"pool_vault", whirlpool.key().as_ref(), token_mint_b.key().as_ref()],
+        bump,
+        token::mint = token_mint_b,
+        token::authority = whirlpool
+    )]
+    pub token_vault_b: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AccrueFees<'info> {
+    pub funder: Signer<'info>,
+
+    #[account(mut)]
+    pub funder_token_a: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub funder_token_b: Account<'info, TokenAccount>,
+
+    #[account(has_one = token_vault_a, has_one = token_vault_b)]
+    pub whirlpool: Account<'info, Whirlpool>,
+
+    #[account(mut, has_one = whirlpool)]
+    pub position: Account<'info, Position>,
+
+    #[account(mut)]
+    pub token_vault_a: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub token_vault_b: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct OpenPosition<'info> {
+    #[account(mut)]
+    pub funder: Signer<'info>,
+
+    /// CHECK: Recorded as the position owner
+    pub owner: UncheckedAccount<'info>,
+
+    #[account(
+        init,
+        payer = funder,
+        space = 8 + Position::LEN,
+        seeds = [b"position", position_mint.key().as_ref()],
+        bump
+    )]
+    pub position: Account<'info, Position>,
+
+    #[account(mut)]
+    pub position_mint: Signer<'info>,
+
+    /// CHECK: Not modelled by the mock
+    #[account(mut)]
+    pub position_token_account: UncheckedAccount<'info>,
+
+    pub whirlpool: Account<'info, Whirlpool>,
+
+    pub token_program: Program<'info, Token>,
+
+    pub system_program: Program<'info, System>,
+
+    pub rent: Sysvar<'info, Rent>,
+
+    /// CHECK: Not modelled by the mock
+    pub associated_token_program: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ModifyLiquidity<'info> {
+    #[account(mut, has_one = token_vault_a, has_one = token_vault_b)]
+    pub whirlpool: Account<'info, Whirlpool>,
+
+    pub token_program: Program<'info, Token>,
+
+    pub position_authority: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = whirlpool,
+        constraint = position.owner == position_authority.key() @ MockWhirlpoolError::MissingOrInvalidDelegate
+    )]
+    pub position: Account<'info, Position>,
+
+    /// CHECK: Not modelled by the mock
+    pub position_token_account: UncheckedAccount<'info>,
+
+    #[account(mut, token::mint = whirlpool.token_mint_a)]
+    pub token_owner_account_a: Account<'info, TokenAccount>,
+
+    #[account(mut, token::mint = whirlpool.token_mint_b)]
+    pub token_owner_account_b: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub token_vault_a: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub token_vault_b: Account<'info, TokenAccount>,
+
+    /// CHECK: Not modelled by the mock
+    #[account(mut)]
+    pub tick_array_lower: UncheckedAccount<'info>,
+
+    /// CHECK: Not modelled by the mock
+    #[account(mut)]
+    pub tick_array_upper: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateFeesAndRewards<'info> {
+    #[account(mut)]
+    pub whirlpool: Account<'info, Whirlpool>,
+
+    #[account(mut, has_one = whirlpool)]
+    pub position: Account<'info, Position>,
+
+    /// CHECK: Not modelled by the mock
+    pub tick_array_lower: UncheckedAccount<'info>,
+
+    /// CHECK: Not modelled by the mock
+    pub tick_array_upper: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CollectFees<'info> {
+    #[account(has_one = token_vault_a, has_one = token_vault_b)]
+    pub whirlpool: Account<'info, Whirlpool>,
+
+    pub position_authority: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = whirlpool,
+        constraint = position.owner == position_authority.key() @ MockWhirlpoolError::MissingOrInvalidDelegate
+    )]
+    pub position: Account<'info, Position>,
+
+    /// CHECK: Not modelled by the mock
+    pub position_token_account: UncheckedAccount<'info>,
+
+    #[account(mut, token::mint = whirlpool.token_mint_a)]
+    pub token_owner_account_a: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub token_vault_a: Account<'info, TokenAccount>,
+
+    #[account(mut, token::mint = whirlpool.token_mint_b)]
+    pub token_owner_account_b: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub token_vault_b: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ClosePosition<'info> {
+    pub position_authority: Signer<'info>,
+
+    /// CHECK: Receives the position's rent
+    #[account(mut)]
+    pub receiver: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        close = receiver,
+        constraint = position.owner == position_authority.key() @ MockWhirlpoolError::MissingOrInvalidDelegate
+    )]
+    pub position: Account<'info, Position>,
+
+    /// CHECK: Not modelled by the mock
+    #[account(mut, address = position.position_mint)]
+    pub position_mint: UncheckedAccount<'info>,
+
+    /// CHECK: Not modelled by the mock
+    #[account(mut)]
+    pub position_token_account: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
@@ -0,0 +1,71 @@
+//! Off-chain helpers for x402 API gateways
+//!
+//! Build the `check_x402_access` instruction, simulate it, and decode the
+//! program's return data with `decode_x402_access_status`. Facilitators sign
+//! `x402_payment_message` for the payment's endpoint and version.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::{InstructionData, ToAccountMetas};
+
+use crate::{x402_endpoint_hash, PaymentCurrency, X402AccessStatus};
+
+pub use crate::x402_payment_message;
+
+/// Pricing PDA of an x402 endpoint/version
+pub fn x402_pricing_address(api_endpoint: &str, api_version: &str) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            b"x402_pricing",
+            &x402_endpoint_hash(api_endpoint, api_version),
+        ],
+        &crate::ID,
+    )
+    .0
+}
+
+/// PDA of the `X402Payment` with `payment_id`
+pub fn x402_payment_address(payment_id: &[u8; 32]) -> Pubkey {
+    Pubkey::find_program_address(&[b"x402_payment", payment_id], &crate::ID).0
+}
+
+/// PDA of `owner`'s prepaid credit account in `currency`
+pub fn x402_credit_address(owner: &Pubkey, currency: PaymentCurrency) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"x402_credit", owner.as_ref(), &[currency as u8]],
+        &crate::ID,
+    )
+    .0
+}
+
+/// Build a `check_x402_access` instruction for `payer` and an endpoint
+///
+/// Pass the payment and/or credit account the payer claims access with.
+pub fn check_x402_access_ix(
+    payer: Pubkey,
+    api_endpoint: &str,
+    api_version: &str,
+    payment: Option<Pubkey>,
+    credit: Option<Pubkey>,
+) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: crate::accounts::CheckX402Access {
+            pricing: x402_pricing_address(api_endpoint, api_version),
+            payment,
+            credit,
+        }
+        .to_account_metas(None),
+        data: crate::instruction::CheckX402Access {
+            payer,
+            api_endpoint: api_endpoint.to_string(),
+            api_version: api_version.to_string(),
+        }
+        .data(),
+    }
+}
+
+/// Decode the return data of a simulated `check_x402_access` instruction
+pub fn decode_x402_access_status(return_data: &[u8]) -> Result<X402AccessStatus> {
+    X402AccessStatus::try_from_slice(return_data).map_err(Into::into)
+}
@@ -0,0 +1,297 @@
+//! DEX CPI integrations
+//!
+//! Each submodule builds one DEX's liquidity instructions and invokes them
+//! with the liquidity position PDA as signer, and implements [`DexAdapter`]
+//! on top of them. Handlers get an adapter for the position's DEX from
+//! [`load`] and drive it through [`rebalance`], [`collect_fees`] and
+//! [`withdraw_all`]. DEX accounts reach the engine's instructions as
+//! remaining accounts. [`swap`] is the aggregator swap a rebalance can run
+//! between removing and adding liquidity, and [`math`] holds the integer
+//! price math both use.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::program::invoke_signed;
+use anchor_spl::token::TokenAccount;
+
+use crate::{BinRange, DexType, LiquidityPosition, XLiquidityEngineError};
+
+pub mod math;
+pub mod meteora;
+pub mod raydium;
+pub mod swap;
+pub mod whirlpool;
+
+/// Accounts of the liquidity position taking part in a CPI
+pub struct PositionCpi<'a, 'info> {
+    /// Position PDA; owns the vaults and the DEX position
+    pub position: AccountInfo<'info>,
+    pub token_a_vault: AccountInfo<'info>,
+    pub token_b_vault: AccountInfo<'info>,
+    pub token_a_mint: AccountInfo<'info>,
+    pub token_b_mint: AccountInfo<'info>,
+    pub token_program: AccountInfo<'info>,
+    pub system_program: AccountInfo<'info>,
+    /// Pays for new DEX position accounts
+    pub payer: Option<AccountInfo<'info>>,
+    pub signer_seeds: &'a [&'a [&'a [u8]]],
+}
+
+/// Range liquidity is added over
+pub struct TargetRange {
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    /// Bins of a Meteora DLMM position
+    pub bin_range: Option<BinRange>,
+}
+
+/// Liquidity operations on a liquidity position's DEX position
+///
+/// An adapter holds the parsed remaining accounts of one instruction and the
+/// liquidity position's current DEX position, if any; removing liquidity and
+/// collecting fees do nothing without one.
+pub trait DexAdapter<'info> {
+    /// Pay the current DEX position's owed fees into the vaults
+    fn collect_fees(&self, cpi: &PositionCpi<'_, 'info>) -> Result<()>;
+
+    /// Move all liquidity of the current DEX position into the vaults,
+    /// failing if that pays out less than `min_amounts` of token A/B
+    ///
+    /// DEXs whose withdrawals take no minimums ignore `min_amounts`.
+    fn remove_liquidity(&self, cpi: &PositionCpi<'_, 'info>, min_amounts: (u64, u64)) -> Result<()>;
+
+    /// Deposit the vault balances over `range`
+    ///
+    /// Returns the DEX position now holding the liquidity.
+    fn add_liquidity(&self, cpi: &PositionCpi<'_, 'info>, range: &TargetRange) -> Result<Option<Pubkey>>;
+
+    /// Close the current DEX position, emptied by `remove_liquidity`, if the
+    /// accounts open a new one to replace it; its rent goes to the position PDA
+    fn close_replaced_position(&self, cpi: &PositionCpi<'_, 'info>) -> Result<()>;
+
+    /// Current pool price as a Q64.64 sqrt price (token B per token A)
+    fn read_pool_price(&self) -> Result<u128>;
+
+    /// Token A/B the current DEX position's liquidity is worth at
+    /// `sqrt_price_x64`, owed fees excluded
+    fn position_amounts(&self, sqrt_price_x64: u128) -> Result<(u64, u64)>;
+
+    /// Liquidity of `dex_position`, the current DEX position or the one
+    /// opened by this instruction, as of now
+    ///
+    /// `None` if the DEX has no single liquidity figure per position.
+    fn liquidity_of(&self, dex_position: Pubkey) -> Result<Option<u128>>;
+}
+
+/// Adapter for `position`'s DEX, parsed from `accounts`
+///
+/// `opens_new` tells whether `accounts` include a new DEX position after the
+/// current one (see each DEX's accounts type for the order).
+pub fn load<'info>(
+    accounts: &[AccountInfo<'info>],
+    position: &Account<'info, LiquidityPosition>,
+    opens_new: bool,
+) -> Result<Box<dyn DexAdapter<'info> + 'info>> {
+    Ok(match position.dex {
+        DexType::Raydium => Box::new(raydium::RaydiumAdapter::load(accounts, position, opens_new)?),
+        DexType::Orca => Box::new(whirlpool::WhirlpoolAdapter::load(accounts, position, opens_new)?),
+        DexType::Meteora => Box::new(meteora::MeteoraAdapter::load(
+            accounts,
+            position,
+            position.key(),
+            opens_new,
+        )?),
+        DexType::Unknown => return err!(XLiquidityEngineError::UnsupportedDex),
+    })
+}
+
+/// Current price of `pool`, a pool of `dex`, as a Q64.64 sqrt price (token B
+/// per token A)
+pub fn pool_price(dex: DexType, pool: &AccountInfo) -> Result<u128> {
+    Ok(match dex {
+        DexType::Raydium => raydium::read_pool(pool)?.sqrt_price_x64,
+        DexType::Orca => whirlpool::read_whirlpool(pool)?.sqrt_price,
+        DexType::Meteora => meteora::active_sqrt_price(&meteora::read_lb_pair(pool)?),
+        DexType::Unknown => return err!(XLiquidityEngineError::UnsupportedDex),
+    })
+}
+
+/// Result of [`rebalance`]
+pub struct Rebalanced {
+    /// DEX position now holding the liquidity
+    pub position: Option<Pubkey>,
+    /// Fees collected from the previous DEX position
+    pub fees_a: u64,
+    pub fees_b: u64,
+    /// Token A/B deposited over the new range
+    pub deployed_a: u64,
+    pub deployed_b: u64,
+    /// Liquidity of the DEX position now holding the liquidity, if the DEX
+    /// has a single liquidity figure
+    pub liquidity: Option<u128>,
+    /// Result of the swap leg, if one ran
+    pub swapped: Option<swap::Swapped>,
+    /// Value lost removing liquidity and swapping, at the reference price, in
+    /// basis points
+    pub slippage_bps: u16,
+}
+
+/// Move all of the position's liquidity and owed fees to `range`, swapping
+/// between the vaults first if `swap` is given
+///
+/// A DEX position replaced by a new one is closed once emptied.
+///
+/// `reference_sqrt_price_x64` is the price the rebalance was decided at. The
+/// pool must be within `slippage_tolerance_bps` of it before liquidity is
+/// removed and again before it is added back, the removal must pay out the
+/// position's worth at that price less the tolerance, and losses are valued
+/// at it rather than at a pool price the same transaction could have moved.
+pub fn rebalance<'info>(
+    adapter: &dyn DexAdapter<'info>,
+    cpi: &PositionCpi<'_, 'info>,
+    range: &TargetRange,
+    swap: Option<&swap::Swap<'_, 'info>>,
+    reference_sqrt_price_x64: u128,
+    slippage_tolerance_bps: u16,
+) -> Result<Rebalanced> {
+    check_pool_price(adapter, reference_sqrt_price_x64, slippage_tolerance_bps)?;
+    let (fees_a, fees_b) = collect_fees(adapter, cpi)?;
+    let (held_a, held_b) = vault_balances(cpi)?;
+    let (expected_a, expected_b) = adapter.position_amounts(reference_sqrt_price_x64)?;
+    let min_amounts = (
+        swap::min_amount_out(expected_a, slippage_tolerance_bps)?,
+        swap::min_amount_out(expected_b, slippage_tolerance_bps)?,
+    );
+    adapter.remove_liquidity(cpi, min_amounts)?;
+    adapter.close_replaced_position(cpi)?;
+    let (removed_a, removed_b) = vault_balances(cpi)?;
+    let swapped = swap.map(|swap| swap.execute(cpi)).transpose()?;
+    check_pool_price(adapter, reference_sqrt_price_x64, slippage_tolerance_bps)?;
+
+    let (available_a, available_b) = vault_balances(cpi)?;
+    let position = adapter.add_liquidity(cpi, range)?;
+    let (remaining_a, remaining_b) = vault_balances(cpi)?;
+    let liquidity = match position {
+        Some(position) => adapter.liquidity_of(position)?,
+        None => Some(0),
+    };
+    // Losses count from the larger of what the DEX position was worth and what
+    // it paid out, so a short removal can't hide behind a good swap or the
+    // other way round. What was deployed is still the position's.
+    let before = (
+        removed_a.max(held_a.saturating_add(expected_a)),
+        removed_b.max(held_b.saturating_add(expected_b)),
+    );
+    let slippage_bps = math::loss_bps(reference_sqrt_price_x64, before, (available_a, available_b))?;
+    Ok(Rebalanced {
+        position,
+        fees_a,
+        fees_b,
+        deployed_a: available_a
+            .checked_sub(remaining_a)
+            .ok_or(XLiquidityEngineError::MathOverflow)?,
+        deployed_b: available_b
+            .checked_sub(remaining_b)
+            .ok_or(XLiquidityEngineError::MathOverflow)?,
+        liquidity,
+        swapped,
+        slippage_bps,
+    })
+}
+
+/// Collect owed fees into the vaults; returns the token A/B collected
+pub fn collect_fees<'info>(
+    adapter: &dyn DexAdapter<'info>,
+    cpi: &PositionCpi<'_, 'info>,
+) -> Result<(u64, u64)> {
+    let (before_a, before_b) = vault_balances(cpi)?;
+    adapter.collect_fees(cpi)?;
+    let (after_a, after_b) = vault_balances(cpi)?;
+    Ok((
+        after_a
+            .checked_sub(before_a)
+            .ok_or(XLiquidityEngineError::MathOverflow)?,
+        after_b
+            .checked_sub(before_b)
+            .ok_or(XLiquidityEngineError::MathOverflow)?,
+    ))
+}
+
+/// Move all liquidity and owed fees into the vaults; returns the fees collected
+///
+/// Takes no minimums: closing a position has no reference price to derive
+/// them from.
+pub fn withdraw_all<'info>(
+    adapter: &dyn DexAdapter<'info>,
+    cpi: &PositionCpi<'_, 'info>,
+) -> Result<(u64, u64)> {
+    let fees = collect_fees(adapter, cpi)?;
+    adapter.remove_liquidity(cpi, (0, 0))?;
+    Ok(fees)
+}
+
+/// Require the pool price to be within `tolerance_bps` of the reference price
+fn check_pool_price(adapter: &dyn DexAdapter, reference_sqrt_price_x64: u128, tolerance_bps: u16) -> Result<()> {
+    let deviation_bps = math::price_deviation_bps(reference_sqrt_price_x64, adapter.read_pool_price()?)?;
+    require!(
+        deviation_bps <= tolerance_bps as u128,
+        XLiquidityEngineError::PoolPriceDeviation
+    );
+    Ok(())
+}
+
+fn vault_balances(cpi: &PositionCpi) -> Result<(u64, u64)> {
+    Ok((token_balance(&cpi.token_a_vault)?, token_balance(&cpi.token_b_vault)?))
+}
+
+/// Deserialize an account owned by `program_id` whose data starts with `discriminator`
+fn read_account<T: AnchorDeserialize>(
+    info: &AccountInfo,
+    program_id: &Pubkey,
+    discriminator: &[u8; 8],
+) -> Result<T> {
+    require_keys_eq!(*info.owner, *program_id, XLiquidityEngineError::InvalidDexAccounts);
+    let data = info.try_borrow_data()?;
+    require!(
+        data.len() >= 8 && data[..8] == discriminator[..],
+        XLiquidityEngineError::InvalidDexAccounts
+    );
+    T::deserialize(&mut &data[8..]).map_err(|_| XLiquidityEngineError::InvalidDexAccounts.into())
+}
+
+/// Current token balance of an SPL token account
+pub fn token_balance(info: &AccountInfo) -> Result<u64> {
+    Ok(TokenAccount::try_deserialize(&mut &info.try_borrow_data()?[..])?.amount)
+}
+
+fn meta(info: &AccountInfo, is_writable: bool) -> AccountMeta {
+    AccountMeta {
+        pubkey: info.key(),
+        is_signer: info.is_signer,
+        is_writable,
+    }
+}
+
+fn signer_meta(info: &AccountInfo, is_writable: bool) -> AccountMeta {
+    AccountMeta {
+        pubkey: info.key(),
+        is_signer: true,
+        is_writable,
+    }
+}
+
+fn invoke<'info>(
+    program: &AccountInfo<'info>,
+    data: Vec<u8>,
+    accounts: Vec<(AccountMeta, AccountInfo<'info>)>,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    let (metas, mut infos): (Vec<_>, Vec<_>) = accounts.into_iter().unzip();
+    infos.push(program.clone());
+    let ix = Instruction {
+        program_id: program.key(),
+        accounts: metas,
+        data,
+    };
+    invoke_signed(&ix, &infos, signer_seeds).map_err(Into::into)
+}
@@ -0,0 +1,253 @@
+//! Fixed-point price math
+//!
+//! Prices are Q64.64 square roots of token B per token A, as Raydium and Orca
+//! store them. Everything here is integer math on `u128` with 256-bit
+//! intermediate products, rounded down unless stated otherwise, so results
+//! don't depend on floating-point behaviour.
+
+use anchor_lang::prelude::*;
+
+use crate::{XLiquidityEngineError, MAX_BPS};
+
+/// 1.0 in Q64.64
+pub const Q64: u128 = 1 << 64;
+
+/// Lowest and highest tick with a Q64.64 sqrt price (Raydium's bounds)
+pub const MIN_TICK: i32 = -443_636;
+pub const MAX_TICK: i32 = 443_636;
+
+/// `2^64 / sqrt(1.0001) ^ (2^i)` for each bit `i` of a tick's magnitude
+const TICK_BIT_RATIOS: [u128; 19] = [
+    0xfffcb933bd6fad37,
+    0xfff97272373d4132,
+    0xfff2e50f5f656932,
+    0xffe5caca7e10e4e6,
+    0xffcb9843d60f6159,
+    0xff973b41fa98c081,
+    0xff2ea16466c96a38,
+    0xfe5dee046a99a2a8,
+    0xfcbe86c7900a88ae,
+    0xf987a7253ac41317,
+    0xf3392b0822b70005,
+    0xe7159475a2c29b74,
+    0xd097f3bdfd2022b8,
+    0xa9f746462d870fdf,
+    0x70d869a156d2a1b8,
+    0x31be135f97d08fd9,
+    0x09aa508b5b7a84e1,
+    0x005d6af8dedb8119,
+    0x00002216e584f5fa,
+];
+
+/// `a * b / denominator` and its remainder, or `None` if the quotient
+/// overflows or `denominator` is zero
+fn mul_div_rem(a: u128, b: u128, denominator: u128) -> Option<(u128, u128)> {
+    if denominator == 0 {
+        return None;
+    }
+    let (hi, lo) = full_mul(a, b);
+    if hi == 0 {
+        return Some((lo / denominator, lo % denominator));
+    }
+    if hi >= denominator {
+        return None;
+    }
+
+    // Long division of the 256-bit product, one bit of `lo` at a time
+    let mut remainder = hi;
+    let mut quotient = 0u128;
+    for i in (0..128).rev() {
+        let carry = remainder >> 127;
+        remainder = (remainder << 1) | ((lo >> i) & 1);
+        quotient <<= 1;
+        if carry == 1 || remainder >= denominator {
+            remainder = remainder.wrapping_sub(denominator);
+            quotient |= 1;
+        }
+    }
+    Some((quotient, remainder))
+}
+
+/// 256-bit product of `a` and `b` as `(high, low)` halves
+fn full_mul(a: u128, b: u128) -> (u128, u128) {
+    const MASK: u128 = u64::MAX as u128;
+    let (a1, a0) = (a >> 64, a & MASK);
+    let (b1, b0) = (b >> 64, b & MASK);
+    let p00 = a0 * b0;
+    let p01 = a0 * b1;
+    let p10 = a1 * b0;
+    let p11 = a1 * b1;
+    let mid = (p00 >> 64) + (p01 & MASK) + (p10 & MASK);
+    let lo = (p00 & MASK) | (mid << 64);
+    let hi = p11 + (p01 >> 64) + (p10 >> 64) + (mid >> 64);
+    (hi, lo)
+}
+
+/// `a * b / denominator`, rounded down
+pub fn mul_div(a: u128, b: u128, denominator: u128) -> Option<u128> {
+    mul_div_rem(a, b, denominator).map(|(quotient, _)| quotient)
+}
+
+/// `a * b / denominator`, rounded up
+pub fn mul_div_ceil(a: u128, b: u128, denominator: u128) -> Option<u128> {
+    let (quotient, remainder) = mul_div_rem(a, b, denominator)?;
+    quotient.checked_add((remainder > 0) as u128)
+}
+
+/// Q64.64 sqrt price at `tick`, `sqrt(1.0001 ^ tick)`
+pub fn sqrt_price_at_tick(tick: i32) -> Result<u128> {
+    require!(
+        (MIN_TICK..=MAX_TICK).contains(&tick),
+        XLiquidityEngineError::InvalidPriceRange
+    );
+    let magnitude = tick.unsigned_abs();
+    let mut ratio = Q64;
+    for (bit, bit_ratio) in TICK_BIT_RATIOS.iter().enumerate() {
+        if magnitude & (1 << bit) != 0 {
+            // Both factors are at most 2^64, so the product fits
+            ratio = (ratio * bit_ratio) >> 64;
+        }
+    }
+    // The ratios are for negative ticks; positive ones take the reciprocal
+    Ok(if tick > 0 { u128::MAX / ratio } else { ratio })
+}
+
+/// Token A/B backing `liquidity` over `[tick_lower, tick_upper]` at
+/// `sqrt_price_x64`, rounded down
+pub fn amounts_for_liquidity(
+    sqrt_price_x64: u128,
+    tick_lower: i32,
+    tick_upper: i32,
+    liquidity: u128,
+) -> Result<(u64, u64)> {
+    require!(sqrt_price_x64 > 0, XLiquidityEngineError::InvalidPriceRange);
+    let lower = sqrt_price_at_tick(tick_lower)?;
+    let upper = sqrt_price_at_tick(tick_upper)?;
+    require!(lower < upper, XLiquidityEngineError::InvalidPriceRange);
+    let price = sqrt_price_x64.clamp(lower, upper);
+
+    // a = L * (upper - price) / (price * upper), b = L * (price - lower)
+    let amount_a = mul_div(liquidity, upper - price, upper)
+        .and_then(|amount| mul_div(amount, Q64, price))
+        .ok_or(XLiquidityEngineError::MathOverflow)?;
+    let amount_b = mul_div(liquidity, price - lower, Q64).ok_or(XLiquidityEngineError::MathOverflow)?;
+    Ok((
+        u64::try_from(amount_a).map_err(|_| XLiquidityEngineError::MathOverflow)?,
+        u64::try_from(amount_b).map_err(|_| XLiquidityEngineError::MathOverflow)?,
+    ))
+}
+
+/// Token A/B amounts valued in token B at `sqrt_price_x64`
+pub fn value_in_b(sqrt_price_x64: u128, (amount_a, amount_b): (u64, u64)) -> Result<u128> {
+    mul_div(amount_a as u128, sqrt_price_x64, Q64)
+        .and_then(|value| mul_div(value, sqrt_price_x64, Q64))
+        .and_then(|value| value.checked_add(amount_b as u128))
+        .ok_or(XLiquidityEngineError::MathOverflow.into())
+}
+
+/// Output of swapping `amount_in` at exactly `sqrt_price_x64`, without fees
+/// or price impact
+pub fn swap_output(sqrt_price_x64: u128, a_to_b: bool, amount_in: u64) -> Result<u64> {
+    require!(sqrt_price_x64 > 0, XLiquidityEngineError::InvalidPriceRange);
+    let output = if a_to_b {
+        mul_div(amount_in as u128, sqrt_price_x64, Q64).and_then(|out| mul_div(out, sqrt_price_x64, Q64))
+    } else {
+        mul_div(amount_in as u128, Q64, sqrt_price_x64).and_then(|out| mul_div(out, Q64, sqrt_price_x64))
+    };
+    output
+        .and_then(|output| u64::try_from(output).ok())
+        .ok_or(XLiquidityEngineError::MathOverflow.into())
+}
+
+/// Value lost going from token amounts `before` to `after`, both valued at
+/// `sqrt_price_x64`, in basis points of `before` (rounded up)
+pub fn loss_bps(sqrt_price_x64: u128, before: (u64, u64), after: (u64, u64)) -> Result<u16> {
+    let before = value_in_b(sqrt_price_x64, before)?;
+    if before == 0 {
+        return Ok(0);
+    }
+    let lost = before.saturating_sub(value_in_b(sqrt_price_x64, after)?);
+    // `lost <= before`, so this is at most MAX_BPS
+    let bps = mul_div_ceil(lost, MAX_BPS as u128, before).ok_or(XLiquidityEngineError::MathOverflow)?;
+    Ok(bps as u16)
+}
+
+/// How far the price at `sqrt_price_x64` is from the one at
+/// `reference_sqrt_price_x64`, in basis points of the reference (rounded up)
+pub fn price_deviation_bps(reference_sqrt_price_x64: u128, sqrt_price_x64: u128) -> Result<u128> {
+    let price = |sqrt: u128| mul_div(sqrt, sqrt, Q64).ok_or(XLiquidityEngineError::MathOverflow);
+    let reference = price(reference_sqrt_price_x64)?;
+    require!(reference > 0, XLiquidityEngineError::InvalidPriceRange);
+    let deviation = reference.abs_diff(price(sqrt_price_x64)?);
+    Ok(mul_div_ceil(deviation, MAX_BPS as u128, reference).ok_or(XLiquidityEngineError::MathOverflow)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mul_div_handles_wide_products() {
+        assert_eq!(mul_div(u128::MAX, u128::MAX, u128::MAX), Some(u128::MAX));
+        assert_eq!(mul_div(u128::MAX, 2, 4), Some(u128::MAX / 2));
+        assert_eq!(mul_div(1 << 100, 1 << 100, 1 << 73), Some(1 << 127));
+        assert_eq!(mul_div(u128::MAX, 2, 1), None);
+        assert_eq!(mul_div(1, 1, 0), None);
+        assert_eq!(mul_div_ceil(10, 1, 3), Some(4));
+        assert_eq!(mul_div_ceil(9, 1, 3), Some(3));
+    }
+
+    #[test]
+    fn sqrt_price_at_tick_matches_known_values() {
+        assert_eq!(sqrt_price_at_tick(0).unwrap(), Q64);
+        // sqrt(1.0001) and its reciprocal, within one unit
+        assert!(sqrt_price_at_tick(1).unwrap().abs_diff(18_447_666_387_855_959_850) <= 1);
+        assert!(sqrt_price_at_tick(-1).unwrap().abs_diff(18_445_821_805_675_392_311) <= 1);
+        assert!(sqrt_price_at_tick(MIN_TICK).is_ok());
+        assert!(sqrt_price_at_tick(MAX_TICK).is_ok());
+        assert!(sqrt_price_at_tick(MAX_TICK + 1).is_err());
+    }
+
+    #[test]
+    fn amounts_for_liquidity_by_price_position() {
+        // Below the range: all token A; above: all token B; inside: both
+        let (a, b) = amounts_for_liquidity(sqrt_price_at_tick(-600).unwrap(), -500, 500, 1_000_000).unwrap();
+        assert!(a > 0 && b == 0);
+        let (a, b) = amounts_for_liquidity(sqrt_price_at_tick(600).unwrap(), -500, 500, 1_000_000).unwrap();
+        assert!(a == 0 && b > 0);
+        let (a, b) = amounts_for_liquidity(Q64, -500, 500, 1_000_000).unwrap();
+        assert_eq!(a, b);
+        // 1_000_000 * (1 - 1.0001 ^ -250) = 24_688.87
+        assert_eq!(a, 24_688);
+    }
+
+    #[test]
+    fn loss_bps_boundaries() {
+        // Nothing to lose
+        assert_eq!(loss_bps(Q64, (0, 0), (0, 0)).unwrap(), 0);
+        assert_eq!(loss_bps(Q64, (0, 0), (5, 5)).unwrap(), 0);
+        // No loss, or a gain
+        assert_eq!(loss_bps(Q64, (100, 100), (100, 100)).unwrap(), 0);
+        assert_eq!(loss_bps(Q64, (100, 100), (0, 300)).unwrap(), 0);
+        // Everything lost
+        assert_eq!(loss_bps(Q64, (100, 100), (0, 0)).unwrap(), MAX_BPS);
+        // One unit in 3_000 rounds up to 4 bps
+        assert_eq!(loss_bps(Q64, (1_000, 2_000), (1_000, 1_999)).unwrap(), 4);
+        // Token A is valued at the price: 4.0 B per A
+        assert_eq!(loss_bps(2 * Q64, (100, 0), (0, 396)).unwrap(), 100);
+        // Large balances don't overflow
+        assert_eq!(loss_bps(Q64, (u64::MAX, u64::MAX), (u64::MAX, 0)).unwrap(), 5_000);
+    }
+
+    #[test]
+    fn swap_output_and_price_deviation() {
+        assert_eq!(swap_output(2 * Q64, true, 100).unwrap(), 400);
+        assert_eq!(swap_output(2 * Q64, false, 400).unwrap(), 100);
+        assert_eq!(swap_output(Q64, true, 0).unwrap(), 0);
+        assert!(swap_output(0, true, 1).is_err());
+        assert_eq!(price_deviation_bps(Q64, Q64).unwrap(), 0);
+        // Sqrt price 1% up is a 2.01% price move
+        assert_eq!(price_deviation_bps(Q64, Q64 * 101 / 100).unwrap(), 201);
+        assert_eq!(price_deviation_bps(2 * Q64, Q64).unwrap(), 7_500);
+    }
+}
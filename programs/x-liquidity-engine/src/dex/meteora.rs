@@ -0,0 +1,621 @@
+//! Meteora DLMM CPI
+//!
+//! Builds DLMM's `initialize_position`, `add_liquidity_by_strategy`,
+//! `remove_all_liquidity`, `claim_fee` and `close_position` instructions and invokes them with
+//! the position PDA as the DLMM position owner. DLMM accounts are passed to
+//! the calling instruction as remaining accounts, in the order documented on
+//! [`MeteoraAccounts`].
+
+use std::cmp::Ordering;
+
+use anchor_lang::prelude::*;
+
+use super::math::{mul_div, Q64};
+use super::{invoke, meta, read_account, signer_meta, token_balance, DexAdapter, PositionCpi, TargetRange};
+use crate::{BinRange, DistributionShape, LiquidityPosition, XLiquidityEngineError, MAX_BINS_PER_POSITION, MAX_BPS};
+
+pub const METEORA_DLMM_PROGRAM_ID: Pubkey = pubkey!("LBUZKhRxPF3XUpBCjp4YzTKgLccjZhTSDM9hT3doEjD");
+
+// Anchor discriminators: sha256("global:<name>")[..8] / sha256("account:<name>")[..8]
+const INITIALIZE_POSITION: [u8; 8] = [219, 192, 234, 71, 190, 191, 102, 80];
+const ADD_LIQUIDITY_BY_STRATEGY: [u8; 8] = [7, 3, 150, 127, 148, 40, 61, 200];
+const REMOVE_ALL_LIQUIDITY: [u8; 8] = [10, 51, 61, 35, 112, 105, 24, 85];
+const CLAIM_FEE: [u8; 8] = [169, 32, 79, 137, 136, 232, 70, 137];
+const CLOSE_POSITION: [u8; 8] = [123, 134, 81, 0, 49, 68, 98, 98];
+const LB_PAIR_DISCRIMINATOR: [u8; 8] = [33, 11, 49, 98, 181, 101, 177, 13];
+const POSITION_V2_DISCRIMINATOR: [u8; 8] = [117, 176, 212, 199, 245, 180, 133, 182];
+const BIN_ARRAY_DISCRIMINATOR: [u8; 8] = [92, 142, 92, 220, 5, 148, 70, 181];
+
+/// Bins per DLMM bin array
+const MAX_BIN_PER_ARRAY: i32 = 70;
+
+// Data offsets past the discriminator. `PositionV2` holds a u128 liquidity
+// share per bin after `lb_pair` / `owner`, then 70 reward and 70 fee infos of
+// 48 bytes each before its bin ids; a `BinArray`'s 144-byte bins follow its
+// `index`, `version`, padding and `lb_pair`.
+const POSITION_LIQUIDITY_SHARES_OFFSET: usize = 64;
+const POSITION_BIN_IDS_OFFSET: usize = POSITION_LIQUIDITY_SHARES_OFFSET + 70 * 16 + 70 * 48 * 2;
+const BIN_ARRAY_BINS_OFFSET: usize = 48;
+const BIN_LEN: usize = 144;
+
+/// Accounts of one DLMM position
+pub struct MeteoraPositionAccounts<'info> {
+    pub position: AccountInfo<'info>,
+    pub bin_array_lower: AccountInfo<'info>,
+    pub bin_array_upper: AccountInfo<'info>,
+}
+
+impl<'info> MeteoraPositionAccounts<'info> {
+    const COUNT: usize = 3;
+
+    fn parse(accounts: &[AccountInfo<'info>]) -> Self {
+        Self {
+            position: accounts[0].clone(),
+            bin_array_lower: accounts[1].clone(),
+            bin_array_upper: accounts[2].clone(),
+        }
+    }
+}
+
+/// DLMM accounts, parsed from remaining accounts in this order:
+///
+/// 0. Meteora DLMM program
+/// 1. `lb_pair` (writable)
+/// 2. `reserve_x` (writable)
+/// 3. `reserve_y` (writable)
+/// 4. `event_authority`
+/// 5. `rent` sysvar
+///
+/// followed by the current position, if the liquidity position has one:
+/// `position`, `bin_array_lower`, `bin_array_upper` (all writable); and, when
+/// a new position is opened, the same three accounts for the new bin range,
+/// with `position` a new keypair signing the transaction.
+pub struct MeteoraAccounts<'info> {
+    pub program: AccountInfo<'info>,
+    pub lb_pair: AccountInfo<'info>,
+    pub reserve_x: AccountInfo<'info>,
+    pub reserve_y: AccountInfo<'info>,
+    pub event_authority: AccountInfo<'info>,
+    pub rent: AccountInfo<'info>,
+    pub current: Option<MeteoraPositionAccounts<'info>>,
+    pub new: Option<MeteoraPositionAccounts<'info>>,
+}
+
+impl<'info> MeteoraAccounts<'info> {
+    const FIXED_COUNT: usize = 6;
+
+    /// Parse and validate the accounts for `pool` (the position's `pool_address`)
+    pub fn parse(
+        accounts: &[AccountInfo<'info>],
+        pool: &Pubkey,
+        has_current: bool,
+        opens_new: bool,
+    ) -> Result<Self> {
+        let current_count = if has_current { MeteoraPositionAccounts::COUNT } else { 0 };
+        let new_count = if opens_new { MeteoraPositionAccounts::COUNT } else { 0 };
+        require!(
+            accounts.len() == Self::FIXED_COUNT + current_count + new_count,
+            XLiquidityEngineError::InvalidDexAccounts
+        );
+        require_keys_eq!(
+            accounts[0].key(),
+            METEORA_DLMM_PROGRAM_ID,
+            XLiquidityEngineError::InvalidDexAccounts
+        );
+        require_keys_eq!(accounts[1].key(), *pool, XLiquidityEngineError::InvalidDexAccounts);
+
+        let rest = &accounts[Self::FIXED_COUNT..];
+        let current = has_current.then(|| MeteoraPositionAccounts::parse(&rest[..current_count]));
+        let new = opens_new.then(|| MeteoraPositionAccounts::parse(&rest[current_count..]));
+        Ok(Self {
+            program: accounts[0].clone(),
+            lb_pair: accounts[1].clone(),
+            reserve_x: accounts[2].clone(),
+            reserve_y: accounts[3].clone(),
+            event_authority: accounts[4].clone(),
+            rent: accounts[5].clone(),
+            current,
+            new,
+        })
+    }
+}
+
+/// Leading fields of Meteora's `LbPair`
+#[derive(AnchorDeserialize)]
+pub struct LbPair {
+    pub parameters: [u8; 32],
+    pub v_parameters: [u8; 32],
+    pub bump_seed: [u8; 1],
+    pub bin_step_seed: [u8; 2],
+    pub pair_type: u8,
+    pub active_id: i32,
+    pub bin_step: u16,
+    pub status: u8,
+    pub require_base_factor_seed: u8,
+    pub base_factor_seed: [u8; 2],
+    pub activation_type: u8,
+    pub creator_pool_on_off_control: u8,
+    pub token_x_mint: Pubkey,
+    pub token_y_mint: Pubkey,
+    pub reserve_x: Pubkey,
+    pub reserve_y: Pubkey,
+}
+
+/// Leading fields of Meteora's `PositionV2`
+#[derive(AnchorDeserialize)]
+pub struct MeteoraPosition {
+    pub lb_pair: Pubkey,
+    pub owner: Pubkey,
+}
+
+/// Leading fields of Meteora's `BinArray`
+#[derive(AnchorDeserialize)]
+pub struct BinArrayHeader {
+    pub index: i64,
+    pub version: u8,
+    pub padding: [u8; 7],
+    pub lb_pair: Pubkey,
+}
+
+/// Leading fields of Meteora's `Bin`
+#[derive(AnchorDeserialize)]
+pub struct Bin {
+    pub amount_x: u64,
+    pub amount_y: u64,
+    /// Q64.64 price of the bin (token Y per token X)
+    pub price: u128,
+    pub liquidity_supply: u128,
+}
+
+/// `StrategyParameters` of `add_liquidity_by_strategy`
+#[derive(AnchorSerialize)]
+struct StrategyParameters {
+    min_bin_id: i32,
+    max_bin_id: i32,
+    strategy_type: u8,
+    parameters: [u8; 64],
+}
+
+/// `LiquidityParameterByStrategy` of `add_liquidity_by_strategy`
+#[derive(AnchorSerialize)]
+struct LiquidityParameterByStrategy {
+    amount_x: u64,
+    amount_y: u64,
+    active_id: i32,
+    max_active_bin_slippage: i32,
+    strategy_parameters: StrategyParameters,
+}
+
+/// DLMM `StrategyType` index for a distribution shape
+///
+/// The imbalanced variants deposit both amounts in full whatever their ratio.
+fn strategy_type(shape: DistributionShape) -> u8 {
+    match shape {
+        DistributionShape::Spot => 6,   // SpotImBalanced
+        DistributionShape::Curve => 7,  // CurveImBalanced
+        DistributionShape::BidAsk => 8, // BidAskImBalanced
+    }
+}
+
+pub fn read_lb_pair(lb_pair: &AccountInfo) -> Result<LbPair> {
+    read_account(lb_pair, &METEORA_DLMM_PROGRAM_ID, &LB_PAIR_DISCRIMINATOR)
+}
+
+pub fn read_position(position: &AccountInfo) -> Result<MeteoraPosition> {
+    read_account(position, &METEORA_DLMM_PROGRAM_ID, &POSITION_V2_DISCRIMINATOR)
+}
+
+pub fn read_bin_array(bin_array: &AccountInfo) -> Result<BinArrayHeader> {
+    read_account(bin_array, &METEORA_DLMM_PROGRAM_ID, &BIN_ARRAY_DISCRIMINATOR)
+}
+
+/// Deserialize a `T` at `offset` into account data, past the discriminator
+///
+/// For fields past the leading ones the account types deserialize, which
+/// are too large to read whole.
+fn read_at<T: AnchorDeserialize>(data: &[u8], offset: usize) -> Result<T> {
+    data.get(8 + offset..)
+        .and_then(|mut bytes| T::deserialize(&mut bytes).ok())
+        .ok_or(XLiquidityEngineError::InvalidDexAccounts.into())
+}
+
+/// Bin of a pair with `bin_step` whose price is nearest `sqrt_price_x64`
+pub fn bin_id_at(bin_step: u16, sqrt_price_x64: u128) -> i32 {
+    let sqrt_price = sqrt_price_x64 as f64 / (1u128 << 64) as f64;
+    (2.0 * sqrt_price.ln() / (1.0 + bin_step as f64 / MAX_BPS as f64).ln()).round() as i32
+}
+
+/// Token X/Y a position's shares of its bins hold if the pair's active bin
+/// were `active_id`
+///
+/// Bins below the active bin hold only token Y and bins above it only token
+/// X, so every bin but `active_id` is counted whole in one token at its own
+/// price; `active_id` keeps its current mix.
+pub fn position_amounts(
+    lb_pair: &Pubkey,
+    position: &MeteoraPositionAccounts,
+    active_id: i32,
+) -> Result<(u64, u64)> {
+    let bin_arrays = [&position.bin_array_lower, &position.bin_array_upper];
+    let mut indexes = [0i64; 2];
+    for (index, bin_array) in indexes.iter_mut().zip(bin_arrays) {
+        let header = read_bin_array(bin_array)?;
+        require_keys_eq!(header.lb_pair, *lb_pair, XLiquidityEngineError::InvalidDexAccounts);
+        *index = header.index;
+    }
+
+    let data = position.position.try_borrow_data()?;
+    let (lower_bin_id, upper_bin_id): (i32, i32) = read_at(&data, POSITION_BIN_IDS_OFFSET)?;
+    require!(
+        lower_bin_id <= upper_bin_id && upper_bin_id - lower_bin_id < MAX_BINS_PER_POSITION,
+        XLiquidityEngineError::InvalidDexAccounts
+    );
+    let (mut amount_x, mut amount_y) = (0u128, 0u128);
+    for bin_id in lower_bin_id..=upper_bin_id {
+        let offset = POSITION_LIQUIDITY_SHARES_OFFSET + 16 * (bin_id - lower_bin_id) as usize;
+        let shares: u128 = read_at(&data, offset)?;
+        if shares == 0 {
+            continue;
+        }
+        let index = bin_id.div_euclid(MAX_BIN_PER_ARRAY) as i64;
+        let bin_array = indexes
+            .iter()
+            .position(|array_index| *array_index == index)
+            .map(|i| bin_arrays[i])
+            .ok_or(XLiquidityEngineError::InvalidDexAccounts)?;
+        let bin: Bin = read_at(
+            &bin_array.try_borrow_data()?,
+            BIN_ARRAY_BINS_OFFSET + BIN_LEN * bin_id.rem_euclid(MAX_BIN_PER_ARRAY) as usize,
+        )?;
+        require!(
+            bin.price > 0 && shares <= bin.liquidity_supply,
+            XLiquidityEngineError::InvalidDexAccounts
+        );
+
+        let share_of = |amount: u64| mul_div(amount as u128, shares, bin.liquidity_supply);
+        let (x, y) = share_of(bin.amount_x)
+            .zip(share_of(bin.amount_y))
+            .ok_or(XLiquidityEngineError::MathOverflow)?;
+        let (x, y) = match bin_id.cmp(&active_id) {
+            Ordering::Less => (Some(0), mul_div(x, bin.price, Q64).and_then(|x_in_y| y.checked_add(x_in_y))),
+            Ordering::Greater => (mul_div(y, Q64, bin.price).and_then(|y_in_x| x.checked_add(y_in_x)), Some(0)),
+            Ordering::Equal => (Some(x), Some(y)),
+        };
+        amount_x = x
+            .and_then(|x| amount_x.checked_add(x))
+            .ok_or(XLiquidityEngineError::MathOverflow)?;
+        amount_y = y
+            .and_then(|y| amount_y.checked_add(y))
+            .ok_or(XLiquidityEngineError::MathOverflow)?;
+    }
+    Ok((
+        u64::try_from(amount_x).map_err(|_| XLiquidityEngineError::MathOverflow)?,
+        u64::try_from(amount_y).map_err(|_| XLiquidityEngineError::MathOverflow)?,
+    ))
+}
+
+/// Price of the pair's active bin as a Q64.64 sqrt price (token Y per token X)
+pub fn active_sqrt_price(pair: &LbPair) -> u128 {
+    let price = (1.0 + pair.bin_step as f64 / MAX_BPS as f64).powi(pair.active_id);
+    (price.sqrt() * (1u128 << 64) as f64) as u128
+}
+
+/// Check that `accounts` belong to the position's pool and token pair
+///
+/// The position's token A/B must be the pair's token X/Y.
+pub fn validate_pool(
+    accounts: &MeteoraAccounts,
+    token_a: &Pubkey,
+    token_b: &Pubkey,
+) -> Result<LbPair> {
+    let pair = read_lb_pair(&accounts.lb_pair)?;
+    require!(
+        pair.token_x_mint == *token_a && pair.token_y_mint == *token_b,
+        XLiquidityEngineError::InvalidMint
+    );
+    require!(
+        pair.reserve_x == accounts.reserve_x.key() && pair.reserve_y == accounts.reserve_y.key(),
+        XLiquidityEngineError::InvalidDexAccounts
+    );
+    Ok(pair)
+}
+
+/// Account list shared by `add_liquidity_by_strategy` and `remove_all_liquidity`
+fn modify_liquidity_accounts<'info>(
+    cpi: &PositionCpi<'_, 'info>,
+    accounts: &MeteoraAccounts<'info>,
+    position: &MeteoraPositionAccounts<'info>,
+) -> Vec<(AccountMeta, AccountInfo<'info>)> {
+    vec![
+        (meta(&position.position, true), position.position.clone()),
+        (meta(&accounts.lb_pair, true), accounts.lb_pair.clone()),
+        // No bin array bitmap extension: the program id stands in for `None`
+        (meta(&accounts.program, false), accounts.program.clone()),
+        (meta(&cpi.token_a_vault, true), cpi.token_a_vault.clone()),
+        (meta(&cpi.token_b_vault, true), cpi.token_b_vault.clone()),
+        (meta(&accounts.reserve_x, true), accounts.reserve_x.clone()),
+        (meta(&accounts.reserve_y, true), accounts.reserve_y.clone()),
+        (meta(&cpi.token_a_mint, false), cpi.token_a_mint.clone()),
+        (meta(&cpi.token_b_mint, false), cpi.token_b_mint.clone()),
+        (meta(&position.bin_array_lower, true), position.bin_array_lower.clone()),
+        (meta(&position.bin_array_upper, true), position.bin_array_upper.clone()),
+        (signer_meta(&cpi.position, false), cpi.position.clone()),
+        (meta(&cpi.token_program, false), cpi.token_program.clone()),
+        (meta(&cpi.token_program, false), cpi.token_program.clone()),
+        (meta(&accounts.event_authority, false), accounts.event_authority.clone()),
+        (meta(&accounts.program, false), accounts.program.clone()),
+    ]
+}
+
+/// Remove all liquidity of a position; fees stay owed
+pub fn remove_all_liquidity<'info>(
+    cpi: &PositionCpi<'_, 'info>,
+    accounts: &MeteoraAccounts<'info>,
+    position: &MeteoraPositionAccounts<'info>,
+) -> Result<()> {
+    invoke(
+        &accounts.program,
+        REMOVE_ALL_LIQUIDITY.to_vec(),
+        modify_liquidity_accounts(cpi, accounts, position),
+        cpi.signer_seeds,
+    )
+}
+
+/// Deposit `amount_x` / `amount_y` in full over `bin_range` with its shape
+pub fn add_liquidity_by_strategy<'info>(
+    cpi: &PositionCpi<'_, 'info>,
+    accounts: &MeteoraAccounts<'info>,
+    position: &MeteoraPositionAccounts<'info>,
+    active_id: i32,
+    bin_range: &BinRange,
+    amount_x: u64,
+    amount_y: u64,
+) -> Result<()> {
+    let mut data = ADD_LIQUIDITY_BY_STRATEGY.to_vec();
+    LiquidityParameterByStrategy {
+        amount_x,
+        amount_y,
+        active_id,
+        // `active_id` is read in the same transaction
+        max_active_bin_slippage: 0,
+        strategy_parameters: StrategyParameters {
+            min_bin_id: bin_range.lower_bin_id,
+            max_bin_id: bin_range.upper_bin_id,
+            strategy_type: strategy_type(bin_range.shape),
+            parameters: [0; 64],
+        },
+    }
+    .serialize(&mut data)?;
+    invoke(
+        &accounts.program,
+        data,
+        modify_liquidity_accounts(cpi, accounts, position),
+        cpi.signer_seeds,
+    )
+}
+
+/// Pay a position's owed fees into the vaults
+pub fn claim_fee<'info>(
+    cpi: &PositionCpi<'_, 'info>,
+    accounts: &MeteoraAccounts<'info>,
+    position: &MeteoraPositionAccounts<'info>,
+) -> Result<()> {
+    invoke(
+        &accounts.program,
+        CLAIM_FEE.to_vec(),
+        vec![
+            (meta(&accounts.lb_pair, true), accounts.lb_pair.clone()),
+            (meta(&position.position, true), position.position.clone()),
+            (meta(&position.bin_array_lower, true), position.bin_array_lower.clone()),
+            (meta(&position.bin_array_upper, true), position.bin_array_upper.clone()),
+            (signer_meta(&cpi.position, false), cpi.position.clone()),
+            (meta(&accounts.reserve_x, true), accounts.reserve_x.clone()),
+            (meta(&accounts.reserve_y, true), accounts.reserve_y.clone()),
+            (meta(&cpi.token_a_vault, true), cpi.token_a_vault.clone()),
+            (meta(&cpi.token_b_vault, true), cpi.token_b_vault.clone()),
+            (meta(&cpi.token_a_mint, false), cpi.token_a_mint.clone()),
+            (meta(&cpi.token_b_mint, false), cpi.token_b_mint.clone()),
+            (meta(&cpi.token_program, false), cpi.token_program.clone()),
+            (meta(&accounts.event_authority, false), accounts.event_authority.clone()),
+            (meta(&accounts.program, false), accounts.program.clone()),
+        ],
+        cpi.signer_seeds,
+    )
+}
+
+/// Create an empty position over `bin_range` owned by the position PDA
+pub fn initialize_position<'info>(
+    cpi: &PositionCpi<'_, 'info>,
+    accounts: &MeteoraAccounts<'info>,
+    position: &MeteoraPositionAccounts<'info>,
+    bin_range: &BinRange,
+) -> Result<()> {
+    let payer = cpi
+        .payer
+        .as_ref()
+        .ok_or(XLiquidityEngineError::InvalidDexAccounts)?;
+    let mut data = INITIALIZE_POSITION.to_vec();
+    (bin_range.lower_bin_id, bin_range.width()).serialize(&mut data)?;
+    invoke(
+        &accounts.program,
+        data,
+        vec![
+            (meta(payer, true), payer.clone()),
+            (meta(&position.position, true), position.position.clone()),
+            (meta(&accounts.lb_pair, false), accounts.lb_pair.clone()),
+            (signer_meta(&cpi.position, false), cpi.position.clone()),
+            (meta(&cpi.system_program, false), cpi.system_program.clone()),
+            (meta(&accounts.rent, false), accounts.rent.clone()),
+            (meta(&accounts.event_authority, false), accounts.event_authority.clone()),
+            (meta(&accounts.program, false), accounts.program.clone()),
+        ],
+        cpi.signer_seeds,
+    )
+}
+
+/// Close an emptied position; the rent goes to the position PDA
+pub fn close_position<'info>(
+    cpi: &PositionCpi<'_, 'info>,
+    accounts: &MeteoraAccounts<'info>,
+    position: &MeteoraPositionAccounts<'info>,
+) -> Result<()> {
+    invoke(
+        &accounts.program,
+        CLOSE_POSITION.to_vec(),
+        vec![
+            (meta(&position.position, true), position.position.clone()),
+            (meta(&accounts.lb_pair, true), accounts.lb_pair.clone()),
+            (meta(&position.bin_array_lower, true), position.bin_array_lower.clone()),
+            (meta(&position.bin_array_upper, true), position.bin_array_upper.clone()),
+            (signer_meta(&cpi.position, false), cpi.position.clone()),
+            // Receiver of the rent
+            (meta(&cpi.position, true), cpi.position.clone()),
+            (meta(&accounts.event_authority, false), accounts.event_authority.clone()),
+            (meta(&accounts.program, false), accounts.program.clone()),
+        ],
+        cpi.signer_seeds,
+    )
+}
+
+/// [`DexAdapter`] for a Meteora DLMM pair
+pub struct MeteoraAdapter<'info> {
+    accounts: MeteoraAccounts<'info>,
+    /// Address of the current DLMM position
+    position: Option<Pubkey>,
+    /// Position PDA that must own the current DLMM position
+    owner: Pubkey,
+}
+
+impl<'info> MeteoraAdapter<'info> {
+    /// Parse and validate the DLMM accounts of `position`
+    pub fn load(
+        accounts: &[AccountInfo<'info>],
+        position: &LiquidityPosition,
+        owner: Pubkey,
+        opens_new: bool,
+    ) -> Result<Self> {
+        let accounts = MeteoraAccounts::parse(
+            accounts,
+            &position.pool_address,
+            position.position_nft.is_some(),
+            opens_new,
+        )?;
+        validate_pool(&accounts, &position.token_a, &position.token_b)?;
+        Ok(Self {
+            accounts,
+            position: position.position_nft,
+            owner,
+        })
+    }
+
+    /// Accounts of the current position, checked against `position`
+    fn current(&self) -> Result<Option<&MeteoraPositionAccounts<'info>>> {
+        let Some(position) = self.position else {
+            return Ok(None);
+        };
+        let current = self
+            .accounts
+            .current
+            .as_ref()
+            .ok_or(XLiquidityEngineError::InvalidDexAccounts)?;
+        let dlmm_position = read_position(&current.position)?;
+        require!(
+            current.position.key() == position
+                && dlmm_position.lb_pair == self.accounts.lb_pair.key()
+                && dlmm_position.owner == self.owner,
+            XLiquidityEngineError::InvalidDexAccounts
+        );
+        Ok(Some(current))
+    }
+}
+
+impl<'info> DexAdapter<'info> for MeteoraAdapter<'info> {
+    fn collect_fees(&self, cpi: &PositionCpi<'_, 'info>) -> Result<()> {
+        match self.current()? {
+            Some(current) => claim_fee(cpi, &self.accounts, current),
+            None => Ok(()),
+        }
+    }
+
+    /// `remove_all_liquidity` takes no minimums, so the vaults' gains are
+    /// checked against `min_amounts` after it
+    fn remove_liquidity(&self, cpi: &PositionCpi<'_, 'info>, (min_a, min_b): (u64, u64)) -> Result<()> {
+        let Some(current) = self.current()? else {
+            return Ok(());
+        };
+        let before_a = token_balance(&cpi.token_a_vault)?;
+        let before_b = token_balance(&cpi.token_b_vault)?;
+        remove_all_liquidity(cpi, &self.accounts, current)?;
+        require!(
+            token_balance(&cpi.token_a_vault)?.saturating_sub(before_a) >= min_a
+                && token_balance(&cpi.token_b_vault)?.saturating_sub(before_b) >= min_b,
+            XLiquidityEngineError::SlippageExceeded
+        );
+        Ok(())
+    }
+
+    /// Deposits the full vault balances over `range.bin_range` with its
+    /// distribution shape, into a position initialized with the keypair from
+    /// the accounts or, if no new position was passed, into the current one
+    fn add_liquidity(&self, cpi: &PositionCpi<'_, 'info>, range: &TargetRange) -> Result<Option<Pubkey>> {
+        let bin_range = range
+            .bin_range
+            .as_ref()
+            .ok_or(XLiquidityEngineError::InvalidBinRange)?;
+        let available_a = token_balance(&cpi.token_a_vault)?;
+        let available_b = token_balance(&cpi.token_b_vault)?;
+        if available_a == 0 && available_b == 0 {
+            return Ok(if self.accounts.new.is_some() { None } else { self.position });
+        }
+
+        let (position, target) = match (&self.accounts.new, &self.accounts.current) {
+            (Some(new), _) => {
+                initialize_position(cpi, &self.accounts, new, bin_range)?;
+                (Some(new.position.key()), new)
+            }
+            (None, Some(current)) => (self.position, current),
+            (None, None) => return err!(XLiquidityEngineError::InvalidDexAccounts),
+        };
+        add_liquidity_by_strategy(
+            cpi,
+            &self.accounts,
+            target,
+            read_lb_pair(&self.accounts.lb_pair)?.active_id,
+            bin_range,
+            available_a,
+            available_b,
+        )?;
+        Ok(position)
+    }
+
+    fn close_replaced_position(&self, cpi: &PositionCpi<'_, 'info>) -> Result<()> {
+        match (self.current()?, &self.accounts.new) {
+            (Some(current), Some(_)) => close_position(cpi, &self.accounts, current),
+            _ => Ok(()),
+        }
+    }
+
+    /// Price of the active bin, `(1 + bin_step / 10_000) ^ active_id`
+    fn read_pool_price(&self) -> Result<u128> {
+        Ok(active_sqrt_price(&read_lb_pair(&self.accounts.lb_pair)?))
+    }
+
+    /// Values the position's bins as if the active bin were the one nearest
+    /// `sqrt_price_x64`
+    fn position_amounts(&self, sqrt_price_x64: u128) -> Result<(u64, u64)> {
+        let Some(current) = self.current()? else {
+            return Ok((0, 0));
+        };
+        let pair = read_lb_pair(&self.accounts.lb_pair)?;
+        position_amounts(
+            &self.accounts.lb_pair.key(),
+            current,
+            bin_id_at(pair.bin_step, sqrt_price_x64),
+        )
+    }
+
+    /// DLMM liquidity is per bin
+    fn liquidity_of(&self, _dex_position: Pubkey) -> Result<Option<u128>> {
+        Ok(None)
+    }
+}
@@ -0,0 +1,494 @@
+//! Raydium CLMM CPI
+//!
+//! Builds Raydium's `decrease_liquidity_v2`, `increase_liquidity_v2`,
+//! `open_position_v2` and `close_position` instructions and invokes them with
+//! the position PDA as the position NFT owner. Raydium accounts are passed to the calling
+//! instruction as remaining accounts, in the order documented on
+//! [`RaydiumAccounts`].
+
+use anchor_lang::prelude::*;
+
+use super::{invoke, math, meta, read_account, signer_meta, token_balance, DexAdapter, PositionCpi, TargetRange};
+use crate::{LiquidityPosition, XLiquidityEngineError};
+
+pub const RAYDIUM_CLMM_PROGRAM_ID: Pubkey = pubkey!("CAMMCzo5YL8w4VFF8KVHrK22GGUsp5VTaW7grrKgrWqK");
+
+/// Ticks covered by one Raydium tick array (per unit of tick spacing)
+const TICK_ARRAY_SIZE: i32 = 60;
+
+// Anchor discriminators: sha256("global:<name>")[..8] / sha256("account:<name>")[..8]
+const OPEN_POSITION_V2: [u8; 8] = [77, 184, 74, 214, 112, 86, 241, 199];
+const INCREASE_LIQUIDITY_V2: [u8; 8] = [133, 29, 89, 223, 69, 238, 176, 10];
+const DECREASE_LIQUIDITY_V2: [u8; 8] = [58, 127, 188, 62, 79, 82, 196, 96];
+const CLOSE_POSITION: [u8; 8] = [123, 134, 81, 0, 49, 68, 98, 98];
+const POOL_STATE_DISCRIMINATOR: [u8; 8] = [247, 237, 227, 245, 215, 195, 222, 70];
+const PERSONAL_POSITION_DISCRIMINATOR: [u8; 8] = [70, 111, 150, 126, 230, 15, 25, 117];
+
+/// Accounts of one Raydium personal position
+pub struct RaydiumPositionAccounts<'info> {
+    pub nft_mint: AccountInfo<'info>,
+    pub personal_position: AccountInfo<'info>,
+    pub nft_account: AccountInfo<'info>,
+    pub protocol_position: AccountInfo<'info>,
+    pub tick_array_lower: AccountInfo<'info>,
+    pub tick_array_upper: AccountInfo<'info>,
+}
+
+impl<'info> RaydiumPositionAccounts<'info> {
+    const COUNT: usize = 6;
+
+    fn parse(accounts: &[AccountInfo<'info>]) -> Self {
+        Self {
+            nft_mint: accounts[0].clone(),
+            personal_position: accounts[1].clone(),
+            nft_account: accounts[2].clone(),
+            protocol_position: accounts[3].clone(),
+            tick_array_lower: accounts[4].clone(),
+            tick_array_upper: accounts[5].clone(),
+        }
+    }
+}
+
+/// Raydium accounts, parsed from remaining accounts in this order:
+///
+/// 0. Raydium CLMM program
+/// 1. `pool_state` (writable)
+/// 2. `token_vault_0` (writable)
+/// 3. `token_vault_1` (writable)
+/// 4. `token_program_2022`
+/// 5. `memo_program`
+/// 6. `associated_token_program`
+/// 7. `metadata_program`
+/// 8. `rent` sysvar
+///
+/// followed by the current position, if the liquidity position has one:
+/// `position_nft_mint`, `personal_position`, `position_nft_account`,
+/// `protocol_position`, `tick_array_lower`, `tick_array_upper` (all
+/// writable); and, when a new position is opened, the same six accounts for
+/// the new range, with `position_nft_mint` a new keypair signing the
+/// transaction, then its `metadata_account` (writable).
+pub struct RaydiumAccounts<'info> {
+    pub program: AccountInfo<'info>,
+    pub pool_state: AccountInfo<'info>,
+    pub token_vault_0: AccountInfo<'info>,
+    pub token_vault_1: AccountInfo<'info>,
+    pub token_program_2022: AccountInfo<'info>,
+    pub memo_program: AccountInfo<'info>,
+    pub associated_token_program: AccountInfo<'info>,
+    pub metadata_program: AccountInfo<'info>,
+    pub rent: AccountInfo<'info>,
+    pub current: Option<RaydiumPositionAccounts<'info>>,
+    /// New position and its metadata account
+    pub new: Option<(RaydiumPositionAccounts<'info>, AccountInfo<'info>)>,
+}
+
+impl<'info> RaydiumAccounts<'info> {
+    const FIXED_COUNT: usize = 9;
+
+    /// Parse and validate the accounts for `pool` (the position's `pool_address`)
+    pub fn parse(
+        accounts: &[AccountInfo<'info>],
+        pool: &Pubkey,
+        has_current: bool,
+        opens_new: bool,
+    ) -> Result<Self> {
+        let current_count = if has_current { RaydiumPositionAccounts::COUNT } else { 0 };
+        let new_count = if opens_new { RaydiumPositionAccounts::COUNT + 1 } else { 0 };
+        require!(
+            accounts.len() == Self::FIXED_COUNT + current_count + new_count,
+            XLiquidityEngineError::InvalidDexAccounts
+        );
+        require_keys_eq!(
+            accounts[0].key(),
+            RAYDIUM_CLMM_PROGRAM_ID,
+            XLiquidityEngineError::InvalidDexAccounts
+        );
+        require_keys_eq!(accounts[1].key(), *pool, XLiquidityEngineError::InvalidDexAccounts);
+
+        let rest = &accounts[Self::FIXED_COUNT..];
+        let current = has_current.then(|| RaydiumPositionAccounts::parse(&rest[..current_count]));
+        let new = opens_new.then(|| {
+            let new = &rest[current_count..];
+            (
+                RaydiumPositionAccounts::parse(new),
+                new[RaydiumPositionAccounts::COUNT].clone(),
+            )
+        });
+        Ok(Self {
+            program: accounts[0].clone(),
+            pool_state: accounts[1].clone(),
+            token_vault_0: accounts[2].clone(),
+            token_vault_1: accounts[3].clone(),
+            token_program_2022: accounts[4].clone(),
+            memo_program: accounts[5].clone(),
+            associated_token_program: accounts[6].clone(),
+            metadata_program: accounts[7].clone(),
+            rent: accounts[8].clone(),
+            current,
+            new,
+        })
+    }
+}
+
+/// Leading fields of Raydium's `PoolState`
+#[derive(AnchorDeserialize)]
+pub struct RaydiumPool {
+    pub bump: [u8; 1],
+    pub amm_config: Pubkey,
+    pub owner: Pubkey,
+    pub token_mint_0: Pubkey,
+    pub token_mint_1: Pubkey,
+    pub token_vault_0: Pubkey,
+    pub token_vault_1: Pubkey,
+    pub observation_key: Pubkey,
+    pub mint_decimals_0: u8,
+    pub mint_decimals_1: u8,
+    pub tick_spacing: u16,
+    pub liquidity: u128,
+    pub sqrt_price_x64: u128,
+    pub tick_current: i32,
+}
+
+/// Leading fields of Raydium's `PersonalPositionState`
+#[derive(AnchorDeserialize)]
+pub struct RaydiumPersonalPosition {
+    pub bump: u8,
+    pub nft_mint: Pubkey,
+    pub pool_id: Pubkey,
+    pub tick_lower_index: i32,
+    pub tick_upper_index: i32,
+    pub liquidity: u128,
+}
+
+pub fn read_pool(pool_state: &AccountInfo) -> Result<RaydiumPool> {
+    read_account(pool_state, &RAYDIUM_CLMM_PROGRAM_ID, &POOL_STATE_DISCRIMINATOR)
+}
+
+pub fn read_personal_position(personal_position: &AccountInfo) -> Result<RaydiumPersonalPosition> {
+    read_account(
+        personal_position,
+        &RAYDIUM_CLMM_PROGRAM_ID,
+        &PERSONAL_POSITION_DISCRIMINATOR,
+    )
+}
+
+/// Start index of the tick array containing `tick`
+fn tick_array_start_index(tick: i32, tick_spacing: u16) -> i32 {
+    let ticks_in_array = TICK_ARRAY_SIZE * tick_spacing as i32;
+    tick.div_euclid(ticks_in_array) * ticks_in_array
+}
+
+/// Check that `accounts` belong to the position's pool and token pair
+///
+/// The position's token A/B must be the pool's token 0/1.
+pub fn validate_pool(
+    accounts: &RaydiumAccounts,
+    token_a: &Pubkey,
+    token_b: &Pubkey,
+) -> Result<RaydiumPool> {
+    let pool = read_pool(&accounts.pool_state)?;
+    require!(
+        pool.token_mint_0 == *token_a && pool.token_mint_1 == *token_b,
+        XLiquidityEngineError::InvalidMint
+    );
+    require!(
+        pool.token_vault_0 == accounts.token_vault_0.key()
+            && pool.token_vault_1 == accounts.token_vault_1.key(),
+        XLiquidityEngineError::InvalidDexAccounts
+    );
+    Ok(pool)
+}
+
+/// Remove `liquidity` from a position; owed fees are paid out as well
+pub fn decrease_liquidity<'info>(
+    cpi: &PositionCpi<'_, 'info>,
+    accounts: &RaydiumAccounts<'info>,
+    position: &RaydiumPositionAccounts<'info>,
+    liquidity: u128,
+    amount_0_min: u64,
+    amount_1_min: u64,
+) -> Result<()> {
+    let mut data = DECREASE_LIQUIDITY_V2.to_vec();
+    (liquidity, amount_0_min, amount_1_min).serialize(&mut data)?;
+    invoke(
+        &accounts.program,
+        data,
+        vec![
+            (signer_meta(&cpi.position, false), cpi.position.clone()),
+            (meta(&position.nft_account, false), position.nft_account.clone()),
+            (meta(&position.personal_position, true), position.personal_position.clone()),
+            (meta(&accounts.pool_state, true), accounts.pool_state.clone()),
+            (meta(&position.protocol_position, true), position.protocol_position.clone()),
+            (meta(&accounts.token_vault_0, true), accounts.token_vault_0.clone()),
+            (meta(&accounts.token_vault_1, true), accounts.token_vault_1.clone()),
+            (meta(&position.tick_array_lower, true), position.tick_array_lower.clone()),
+            (meta(&position.tick_array_upper, true), position.tick_array_upper.clone()),
+            (meta(&cpi.token_a_vault, true), cpi.token_a_vault.clone()),
+            (meta(&cpi.token_b_vault, true), cpi.token_b_vault.clone()),
+            (meta(&cpi.token_program, false), cpi.token_program.clone()),
+            (meta(&accounts.token_program_2022, false), accounts.token_program_2022.clone()),
+            (meta(&accounts.memo_program, false), accounts.memo_program.clone()),
+            (meta(&cpi.token_a_mint, false), cpi.token_a_mint.clone()),
+            (meta(&cpi.token_b_mint, false), cpi.token_b_mint.clone()),
+        ],
+        cpi.signer_seeds,
+    )
+}
+
+/// Deposit up to `amount_0_max` / `amount_1_max` into an existing position
+pub fn increase_liquidity<'info>(
+    cpi: &PositionCpi<'_, 'info>,
+    accounts: &RaydiumAccounts<'info>,
+    position: &RaydiumPositionAccounts<'info>,
+    amount_0_max: u64,
+    amount_1_max: u64,
+) -> Result<()> {
+    // Zero liquidity with a base flag: Raydium derives liquidity from the amounts
+    let mut data = INCREASE_LIQUIDITY_V2.to_vec();
+    (0u128, amount_0_max, amount_1_max, Some(true)).serialize(&mut data)?;
+    invoke(
+        &accounts.program,
+        data,
+        vec![
+            (signer_meta(&cpi.position, false), cpi.position.clone()),
+            (meta(&position.nft_account, false), position.nft_account.clone()),
+            (meta(&accounts.pool_state, true), accounts.pool_state.clone()),
+            (meta(&position.protocol_position, true), position.protocol_position.clone()),
+            (meta(&position.personal_position, true), position.personal_position.clone()),
+            (meta(&position.tick_array_lower, true), position.tick_array_lower.clone()),
+            (meta(&position.tick_array_upper, true), position.tick_array_upper.clone()),
+            (meta(&cpi.token_a_vault, true), cpi.token_a_vault.clone()),
+            (meta(&cpi.token_b_vault, true), cpi.token_b_vault.clone()),
+            (meta(&accounts.token_vault_0, true), accounts.token_vault_0.clone()),
+            (meta(&accounts.token_vault_1, true), accounts.token_vault_1.clone()),
+            (meta(&cpi.token_program, false), cpi.token_program.clone()),
+            (meta(&accounts.token_program_2022, false), accounts.token_program_2022.clone()),
+            (meta(&cpi.token_a_mint, false), cpi.token_a_mint.clone()),
+            (meta(&cpi.token_b_mint, false), cpi.token_b_mint.clone()),
+        ],
+        cpi.signer_seeds,
+    )
+}
+
+/// Open an empty position over `[tick_lower, tick_upper]` whose NFT goes to
+/// the position PDA
+///
+/// `payer` is Raydium's token authority for `open_position_v2`, so the
+/// position is opened without liquidity and funded by [`increase_liquidity`],
+/// which the position PDA authorizes.
+pub fn open_position<'info>(
+    cpi: &PositionCpi<'_, 'info>,
+    accounts: &RaydiumAccounts<'info>,
+    position: &RaydiumPositionAccounts<'info>,
+    metadata_account: &AccountInfo<'info>,
+    tick_spacing: u16,
+    tick_lower: i32,
+    tick_upper: i32,
+) -> Result<()> {
+    let payer = cpi
+        .payer
+        .as_ref()
+        .ok_or(XLiquidityEngineError::InvalidDexAccounts)?;
+    // Zero liquidity and no base flag: Raydium opens the position empty
+    let mut data = OPEN_POSITION_V2.to_vec();
+    (
+        tick_lower,
+        tick_upper,
+        tick_array_start_index(tick_lower, tick_spacing),
+        tick_array_start_index(tick_upper, tick_spacing),
+        0u128,
+        0u64,
+        0u64,
+        false, // with_metadata
+        None::<bool>,
+    )
+        .serialize(&mut data)?;
+    invoke(
+        &accounts.program,
+        data,
+        vec![
+            (meta(payer, true), payer.clone()),
+            (meta(&cpi.position, false), cpi.position.clone()),
+            (meta(&position.nft_mint, true), position.nft_mint.clone()),
+            (meta(&position.nft_account, true), position.nft_account.clone()),
+            (meta(metadata_account, true), metadata_account.clone()),
+            (meta(&accounts.pool_state, true), accounts.pool_state.clone()),
+            (meta(&position.protocol_position, true), position.protocol_position.clone()),
+            (meta(&position.tick_array_lower, true), position.tick_array_lower.clone()),
+            (meta(&position.tick_array_upper, true), position.tick_array_upper.clone()),
+            (meta(&position.personal_position, true), position.personal_position.clone()),
+            (meta(&cpi.token_a_vault, true), cpi.token_a_vault.clone()),
+            (meta(&cpi.token_b_vault, true), cpi.token_b_vault.clone()),
+            (meta(&accounts.token_vault_0, true), accounts.token_vault_0.clone()),
+            (meta(&accounts.token_vault_1, true), accounts.token_vault_1.clone()),
+            (meta(&accounts.rent, false), accounts.rent.clone()),
+            (meta(&cpi.system_program, false), cpi.system_program.clone()),
+            (meta(&cpi.token_program, false), cpi.token_program.clone()),
+            (meta(&accounts.associated_token_program, false), accounts.associated_token_program.clone()),
+            (meta(&accounts.metadata_program, false), accounts.metadata_program.clone()),
+            (meta(&accounts.token_program_2022, false), accounts.token_program_2022.clone()),
+            (meta(&cpi.token_a_mint, false), cpi.token_a_mint.clone()),
+            (meta(&cpi.token_b_mint, false), cpi.token_b_mint.clone()),
+        ],
+        cpi.signer_seeds,
+    )
+}
+
+/// Close an emptied position and burn its NFT; the rent goes to the position PDA
+pub fn close_position<'info>(
+    cpi: &PositionCpi<'_, 'info>,
+    accounts: &RaydiumAccounts<'info>,
+    position: &RaydiumPositionAccounts<'info>,
+) -> Result<()> {
+    invoke(
+        &accounts.program,
+        CLOSE_POSITION.to_vec(),
+        vec![
+            (signer_meta(&cpi.position, true), cpi.position.clone()),
+            (meta(&position.nft_mint, true), position.nft_mint.clone()),
+            (meta(&position.nft_account, true), position.nft_account.clone()),
+            (meta(&position.personal_position, true), position.personal_position.clone()),
+            (meta(&cpi.system_program, false), cpi.system_program.clone()),
+            (meta(&cpi.token_program, false), cpi.token_program.clone()),
+        ],
+        cpi.signer_seeds,
+    )
+}
+
+/// [`DexAdapter`] for a Raydium CLMM pool
+pub struct RaydiumAdapter<'info> {
+    accounts: RaydiumAccounts<'info>,
+    pool: RaydiumPool,
+    /// NFT mint of the current position
+    position_nft: Option<Pubkey>,
+}
+
+impl<'info> RaydiumAdapter<'info> {
+    /// Parse and validate the Raydium accounts of `position`
+    pub fn load(
+        accounts: &[AccountInfo<'info>],
+        position: &LiquidityPosition,
+        opens_new: bool,
+    ) -> Result<Self> {
+        let accounts = RaydiumAccounts::parse(
+            accounts,
+            &position.pool_address,
+            position.position_nft.is_some(),
+            opens_new,
+        )?;
+        let pool = validate_pool(&accounts, &position.token_a, &position.token_b)?;
+        Ok(Self {
+            accounts,
+            pool,
+            position_nft: position.position_nft,
+        })
+    }
+
+    /// Accounts and state of the current position, checked against `position_nft`
+    fn current(&self) -> Result<Option<(&RaydiumPositionAccounts<'info>, RaydiumPersonalPosition)>> {
+        let Some(position_nft) = self.position_nft else {
+            return Ok(None);
+        };
+        let current = self
+            .accounts
+            .current
+            .as_ref()
+            .ok_or(XLiquidityEngineError::InvalidDexAccounts)?;
+        let personal_position = read_personal_position(&current.personal_position)?;
+        require!(
+            current.nft_mint.key() == position_nft
+                && personal_position.nft_mint == position_nft
+                && personal_position.pool_id == self.accounts.pool_state.key(),
+            XLiquidityEngineError::InvalidDexAccounts
+        );
+        Ok(Some((current, personal_position)))
+    }
+}
+
+impl<'info> DexAdapter<'info> for RaydiumAdapter<'info> {
+    fn collect_fees(&self, cpi: &PositionCpi<'_, 'info>) -> Result<()> {
+        // Decreasing by zero liquidity pays out the owed fees only
+        if let Some((current, _)) = self.current()? {
+            decrease_liquidity(cpi, &self.accounts, current, 0, 0, 0)?;
+        }
+        Ok(())
+    }
+
+    fn remove_liquidity(&self, cpi: &PositionCpi<'_, 'info>, (min_a, min_b): (u64, u64)) -> Result<()> {
+        match self.current()? {
+            Some((current, personal_position)) if personal_position.liquidity > 0 => {
+                decrease_liquidity(cpi, &self.accounts, current, personal_position.liquidity, min_a, min_b)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Increases a position opened with the `position_nft_mint` from the
+    /// accounts, or the current one if no new position was passed
+    fn add_liquidity(&self, cpi: &PositionCpi<'_, 'info>, range: &TargetRange) -> Result<Option<Pubkey>> {
+        let available_a = token_balance(&cpi.token_a_vault)?;
+        let available_b = token_balance(&cpi.token_b_vault)?;
+        if available_a == 0 && available_b == 0 {
+            return Ok(if self.accounts.new.is_some() { None } else { self.position_nft });
+        }
+
+        let (position_nft, position) = match (&self.accounts.new, &self.accounts.current) {
+            (Some((new, metadata_account)), _) => {
+                open_position(
+                    cpi,
+                    &self.accounts,
+                    new,
+                    metadata_account,
+                    self.pool.tick_spacing,
+                    range.tick_lower,
+                    range.tick_upper,
+                )?;
+                (Some(new.nft_mint.key()), new)
+            }
+            (None, Some(current)) => (self.position_nft, current),
+            (None, None) => return err!(XLiquidityEngineError::InvalidDexAccounts),
+        };
+        increase_liquidity(cpi, &self.accounts, position, available_a, available_b)?;
+        Ok(position_nft)
+    }
+
+    fn close_replaced_position(&self, cpi: &PositionCpi<'_, 'info>) -> Result<()> {
+        match (self.current()?, &self.accounts.new) {
+            (Some((current, _)), Some(_)) => close_position(cpi, &self.accounts, current),
+            _ => Ok(()),
+        }
+    }
+
+    fn read_pool_price(&self) -> Result<u128> {
+        Ok(read_pool(&self.accounts.pool_state)?.sqrt_price_x64)
+    }
+
+    fn position_amounts(&self, sqrt_price_x64: u128) -> Result<(u64, u64)> {
+        let Some((_, personal_position)) = self.current()? else {
+            return Ok((0, 0));
+        };
+        math::amounts_for_liquidity(
+            sqrt_price_x64,
+            personal_position.tick_lower_index,
+            personal_position.tick_upper_index,
+            personal_position.liquidity,
+        )
+    }
+
+    fn liquidity_of(&self, dex_position: Pubkey) -> Result<Option<u128>> {
+        let accounts = match &self.accounts.new {
+            Some((new, _)) if new.nft_mint.key() == dex_position => new,
+            _ => {
+                require!(
+                    self.position_nft == Some(dex_position),
+                    XLiquidityEngineError::InvalidDexAccounts
+                );
+                self.accounts
+                    .current
+                    .as_ref()
+                    .ok_or(XLiquidityEngineError::InvalidDexAccounts)?
+            }
+        };
+        Ok(Some(read_personal_position(&accounts.personal_position)?.liquidity))
+    }
+}
@@ -0,0 +1,171 @@
+//! Aggregator swap CPI
+//!
+//! Invokes a Jupiter-compatible aggregator's route instruction with the
+//! position PDA as the user transfer authority, so a rebalance can change the
+//! vaults' token A/B ratio before liquidity is added back. Route data and
+//! accounts are built off chain and passed through unchanged; the aggregator
+//! must be the protocol's `swap_program`, and what the swap spent and received
+//! is measured on the vaults rather than trusted. Both the off-chain quote and
+//! the realized output are held to the rebalance's reference price, so a zero
+//! or stale quote can't lower the floor.
+//!
+//! The PDA's signature reaches whatever the route passes it to, so the route
+//! may not name anything else the PDA controls: no token account of its but
+//! the two vaults, and not the DEX position. The vaults must come back still
+//! owned by the PDA with no delegate or close authority.
+
+use anchor_lang::prelude::*;
+use anchor_spl::token::TokenAccount;
+use anchor_spl::{token, token_2022, token_interface};
+
+use super::{invoke, math, token_balance, PositionCpi};
+use crate::{SwapParams, XLiquidityEngineError, MAX_BPS};
+
+/// A validated swap leg
+pub struct Swap<'a, 'info> {
+    program: AccountInfo<'info>,
+    accounts: &'a [AccountInfo<'info>],
+    params: &'a SwapParams,
+    min_amount_out: u64,
+    reference_sqrt_price_x64: u128,
+    slippage_tolerance_bps: u16,
+}
+
+/// Result of [`Swap::execute`]
+pub struct Swapped {
+    /// Input token taken from the vault
+    pub amount_in: u64,
+    /// Output token received by the other vault
+    pub amount_out: u64,
+}
+
+impl<'a, 'info> Swap<'a, 'info> {
+    /// Check `params` and its accounts (the aggregator program first, then the
+    /// route's accounts) against the protocol and position limits
+    ///
+    /// The quote may be at most `slippage_tolerance_bps` worse than swapping at
+    /// `reference_sqrt_price_x64`. `dex_position` is the position's DEX
+    /// position (`LiquidityPosition::position_nft`), which the route may not
+    /// name.
+    pub fn new(
+        accounts: &'a [AccountInfo<'info>],
+        params: &'a SwapParams,
+        swap_program: Option<Pubkey>,
+        dex_position: Option<Pubkey>,
+        max_single_trade: u64,
+        slippage_tolerance_bps: u16,
+        reference_sqrt_price_x64: u128,
+    ) -> Result<Self> {
+        let (program, accounts) = accounts
+            .split_first()
+            .ok_or(XLiquidityEngineError::InvalidSwap)?;
+        require!(
+            swap_program == Some(program.key()),
+            XLiquidityEngineError::InvalidSwap
+        );
+        require!(
+            accounts.iter().all(|info| Some(info.key()) != dex_position),
+            XLiquidityEngineError::InvalidSwap
+        );
+        require!(params.amount_in > 0, XLiquidityEngineError::InvalidSwap);
+        require!(
+            params.amount_in <= max_single_trade,
+            XLiquidityEngineError::ExceedsMaxTradeSize
+        );
+        let reference_out = math::swap_output(reference_sqrt_price_x64, params.a_to_b, params.amount_in)?;
+        require!(
+            params.quoted_amount_out >= min_amount_out(reference_out, slippage_tolerance_bps)?,
+            XLiquidityEngineError::SwapQuoteBelowReference
+        );
+        Ok(Self {
+            program: program.clone(),
+            accounts,
+            params,
+            min_amount_out: min_amount_out(params.quoted_amount_out, slippage_tolerance_bps)?,
+            reference_sqrt_price_x64,
+            slippage_tolerance_bps,
+        })
+    }
+
+    /// Swap between the vaults and check the balance changes
+    pub fn execute(&self, cpi: &PositionCpi<'_, 'info>) -> Result<Swapped> {
+        let (vault_in, vault_out) = if self.params.a_to_b {
+            (&cpi.token_a_vault, &cpi.token_b_vault)
+        } else {
+            (&cpi.token_b_vault, &cpi.token_a_vault)
+        };
+        let before_in = token_balance(vault_in)?;
+        let before_out = token_balance(vault_out)?;
+
+        // The position PDA can only sign through this program
+        let authority = cpi.position.key();
+        let vaults = [cpi.token_a_vault.key(), cpi.token_b_vault.key()];
+        for info in self.accounts {
+            require!(
+                vaults.contains(&info.key()) || token_owner(info) != Some(authority),
+                XLiquidityEngineError::InvalidSwap
+            );
+        }
+        let accounts = self
+            .accounts
+            .iter()
+            .map(|info| {
+                let meta = AccountMeta {
+                    pubkey: info.key(),
+                    is_signer: info.is_signer || info.key() == authority,
+                    is_writable: info.is_writable,
+                };
+                (meta, info.clone())
+            })
+            .collect();
+        invoke(&self.program, self.params.route_data.clone(), accounts, cpi.signer_seeds)?;
+        for vault in [vault_in, vault_out] {
+            let vault = TokenAccount::try_deserialize(&mut &vault.try_borrow_data()?[..])?;
+            require!(
+                vault.owner == authority
+                    && vault.delegate.is_none()
+                    && vault.close_authority.is_none(),
+                XLiquidityEngineError::InvalidSwap
+            );
+        }
+
+        let amount_in = before_in.saturating_sub(token_balance(vault_in)?);
+        let amount_out = token_balance(vault_out)?
+            .checked_sub(before_out)
+            .ok_or(XLiquidityEngineError::SlippageExceeded)?;
+        require!(
+            amount_in <= self.params.amount_in,
+            XLiquidityEngineError::SwapAmountExceeded
+        );
+        // The route may spend less than `amount_in`; hold what it did spend to
+        // the reference price too
+        let reference_out = math::swap_output(self.reference_sqrt_price_x64, self.params.a_to_b, amount_in)?;
+        require!(
+            amount_out >= self.min_amount_out
+                && amount_out >= min_amount_out(reference_out, self.slippage_tolerance_bps)?,
+            XLiquidityEngineError::SlippageExceeded
+        );
+        Ok(Swapped { amount_in, amount_out })
+    }
+}
+
+/// Owner of `info` if it is a token account of either token program
+fn token_owner(info: &AccountInfo) -> Option<Pubkey> {
+    if info.owner != &token::ID && info.owner != &token_2022::ID {
+        return None;
+    }
+    let data = info.try_borrow_data().ok()?;
+    token_interface::TokenAccount::try_deserialize(&mut &data[..])
+        .ok()
+        .map(|account| account.owner)
+}
+
+/// Least output accepted for a `quoted` output at `slippage_bps`
+pub fn min_amount_out(quoted: u64, slippage_bps: u16) -> Result<u64> {
+    let kept_bps = MAX_BPS.saturating_sub(slippage_bps);
+    let amount = (quoted as u128)
+        .checked_mul(kept_bps as u128)
+        .ok_or(XLiquidityEngineError::MathOverflow)?
+        / MAX_BPS as u128;
+    Ok(amount as u64)
+}
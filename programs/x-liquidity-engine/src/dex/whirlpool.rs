@@ -0,0 +1,501 @@
+//! Orca Whirlpools CPI
+//!
+//! Builds Whirlpool's `open_position`, `increase_liquidity`,
+//! `decrease_liquidity`, `update_fees_and_rewards`, `collect_fees` and
+//! `close_position` instructions and invokes them with the position PDA as the position
+//! authority. Whirlpool accounts are passed to the calling instruction as
+//! remaining accounts, in the order documented on [`WhirlpoolAccounts`].
+
+use anchor_lang::prelude::*;
+
+use super::{invoke, math, meta, read_account, signer_meta, token_balance, DexAdapter, PositionCpi, TargetRange};
+use crate::{LiquidityPosition, XLiquidityEngineError, MAX_BPS};
+
+pub const WHIRLPOOL_PROGRAM_ID: Pubkey = pubkey!("whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc");
+
+/// Liquidity is sized this far below what the vault balances allow, so that
+/// rounding in the Whirlpool's token math never exceeds the token maxima
+const LIQUIDITY_HAIRCUT_BPS: u64 = 10;
+
+// Anchor discriminators: sha256("global:<name>")[..8] / sha256("account:<name>")[..8]
+const OPEN_POSITION: [u8; 8] = [135, 128, 47, 77, 15, 152, 240, 49];
+const INCREASE_LIQUIDITY: [u8; 8] = [46, 156, 243, 118, 13, 205, 251, 178];
+const DECREASE_LIQUIDITY: [u8; 8] = [160, 38, 208, 111, 104, 91, 44, 1];
+const COLLECT_FEES: [u8; 8] = [164, 152, 207, 99, 30, 186, 19, 182];
+const UPDATE_FEES_AND_REWARDS: [u8; 8] = [154, 230, 250, 13, 236, 209, 75, 223];
+const CLOSE_POSITION: [u8; 8] = [123, 134, 81, 0, 49, 68, 98, 98];
+const WHIRLPOOL_DISCRIMINATOR: [u8; 8] = [63, 149, 209, 12, 225, 128, 99, 9];
+const POSITION_DISCRIMINATOR: [u8; 8] = [170, 188, 143, 228, 122, 64, 247, 208];
+
+/// Accounts of one Whirlpool position
+pub struct WhirlpoolPositionAccounts<'info> {
+    pub position_mint: AccountInfo<'info>,
+    pub position: AccountInfo<'info>,
+    pub position_token_account: AccountInfo<'info>,
+    pub tick_array_lower: AccountInfo<'info>,
+    pub tick_array_upper: AccountInfo<'info>,
+}
+
+impl<'info> WhirlpoolPositionAccounts<'info> {
+    const COUNT: usize = 5;
+
+    fn parse(accounts: &[AccountInfo<'info>]) -> Self {
+        Self {
+            position_mint: accounts[0].clone(),
+            position: accounts[1].clone(),
+            position_token_account: accounts[2].clone(),
+            tick_array_lower: accounts[3].clone(),
+            tick_array_upper: accounts[4].clone(),
+        }
+    }
+}
+
+/// Whirlpool accounts, parsed from remaining accounts in this order:
+///
+/// 0. Whirlpool program
+/// 1. `whirlpool` (writable)
+/// 2. `token_vault_a` (writable)
+/// 3. `token_vault_b` (writable)
+/// 4. `associated_token_program`
+/// 5. `rent` sysvar
+///
+/// followed by the current position, if the liquidity position has one:
+/// `position_mint`, `position`, `position_token_account`, `tick_array_lower`,
+/// `tick_array_upper` (all writable); and, when a new position is opened, the
+/// same five accounts for the new range, with `position_mint` a new keypair
+/// signing the transaction.
+pub struct WhirlpoolAccounts<'info> {
+    pub program: AccountInfo<'info>,
+    pub whirlpool: AccountInfo<'info>,
+    pub token_vault_a: AccountInfo<'info>,
+    pub token_vault_b: AccountInfo<'info>,
+    pub associated_token_program: AccountInfo<'info>,
+    pub rent: AccountInfo<'info>,
+    pub current: Option<WhirlpoolPositionAccounts<'info>>,
+    pub new: Option<WhirlpoolPositionAccounts<'info>>,
+}
+
+impl<'info> WhirlpoolAccounts<'info> {
+    const FIXED_COUNT: usize = 6;
+
+    /// Parse and validate the accounts for `pool` (the position's `pool_address`)
+    pub fn parse(
+        accounts: &[AccountInfo<'info>],
+        pool: &Pubkey,
+        has_current: bool,
+        opens_new: bool,
+    ) -> Result<Self> {
+        let current_count = if has_current { WhirlpoolPositionAccounts::COUNT } else { 0 };
+        let new_count = if opens_new { WhirlpoolPositionAccounts::COUNT } else { 0 };
+        require!(
+            accounts.len() == Self::FIXED_COUNT + current_count + new_count,
+            XLiquidityEngineError::InvalidDexAccounts
+        );
+        require_keys_eq!(
+            accounts[0].key(),
+            WHIRLPOOL_PROGRAM_ID,
+            XLiquidityEngineError::InvalidDexAccounts
+        );
+        require_keys_eq!(accounts[1].key(), *pool, XLiquidityEngineError::InvalidDexAccounts);
+
+        let rest = &accounts[Self::FIXED_COUNT..];
+        let current = has_current.then(|| WhirlpoolPositionAccounts::parse(&rest[..current_count]));
+        let new = opens_new.then(|| WhirlpoolPositionAccounts::parse(&rest[current_count..]));
+        Ok(Self {
+            program: accounts[0].clone(),
+            whirlpool: accounts[1].clone(),
+            token_vault_a: accounts[2].clone(),
+            token_vault_b: accounts[3].clone(),
+            associated_token_program: accounts[4].clone(),
+            rent: accounts[5].clone(),
+            current,
+            new,
+        })
+    }
+}
+
+/// Leading fields of Orca's `Whirlpool`
+#[derive(AnchorDeserialize)]
+pub struct Whirlpool {
+    pub whirlpools_config: Pubkey,
+    pub whirlpool_bump: [u8; 1],
+    pub tick_spacing: u16,
+    pub tick_spacing_seed: [u8; 2],
+    pub fee_rate: u16,
+    pub protocol_fee_rate: u16,
+    pub liquidity: u128,
+    pub sqrt_price: u128,
+    pub tick_current_index: i32,
+    pub protocol_fee_owed_a: u64,
+    pub protocol_fee_owed_b: u64,
+    pub token_mint_a: Pubkey,
+    pub token_vault_a: Pubkey,
+    pub fee_growth_global_a: u128,
+    pub token_mint_b: Pubkey,
+    pub token_vault_b: Pubkey,
+}
+
+/// Leading fields of Orca's `Position`
+#[derive(AnchorDeserialize)]
+pub struct WhirlpoolPosition {
+    pub whirlpool: Pubkey,
+    pub position_mint: Pubkey,
+    pub liquidity: u128,
+    pub tick_lower_index: i32,
+    pub tick_upper_index: i32,
+}
+
+pub fn read_whirlpool(whirlpool: &AccountInfo) -> Result<Whirlpool> {
+    read_account(whirlpool, &WHIRLPOOL_PROGRAM_ID, &WHIRLPOOL_DISCRIMINATOR)
+}
+
+pub fn read_position(position: &AccountInfo) -> Result<WhirlpoolPosition> {
+    read_account(position, &WHIRLPOOL_PROGRAM_ID, &POSITION_DISCRIMINATOR)
+}
+
+/// Check that `accounts` belong to the position's pool and token pair
+///
+/// The position's token A/B must be the Whirlpool's token A/B.
+pub fn validate_pool(
+    accounts: &WhirlpoolAccounts,
+    token_a: &Pubkey,
+    token_b: &Pubkey,
+) -> Result<Whirlpool> {
+    let pool = read_whirlpool(&accounts.whirlpool)?;
+    require!(
+        pool.token_mint_a == *token_a && pool.token_mint_b == *token_b,
+        XLiquidityEngineError::InvalidMint
+    );
+    require!(
+        pool.token_vault_a == accounts.token_vault_a.key()
+            && pool.token_vault_b == accounts.token_vault_b.key(),
+        XLiquidityEngineError::InvalidDexAccounts
+    );
+    Ok(pool)
+}
+
+/// Sqrt price at `tick` (not Q64.64 scaled)
+fn sqrt_price_at_tick(tick: i32) -> f64 {
+    1.0001f64.powf(tick as f64 / 2.0)
+}
+
+/// Largest liquidity over `[tick_lower, tick_upper]` that `amount_a` /
+/// `amount_b` can fund at the pool's current sqrt price, less the haircut
+pub fn liquidity_for_amounts(
+    sqrt_price_x64: u128,
+    tick_lower: i32,
+    tick_upper: i32,
+    amount_a: u64,
+    amount_b: u64,
+) -> u128 {
+    let price = sqrt_price_x64 as f64 / (1u128 << 64) as f64;
+    let lower = sqrt_price_at_tick(tick_lower);
+    let upper = sqrt_price_at_tick(tick_upper);
+    let from_a = |from: f64| amount_a as f64 * from * upper / (upper - from);
+    let from_b = |to: f64| amount_b as f64 / (to - lower);
+
+    let liquidity = if price <= lower {
+        from_a(lower)
+    } else if price >= upper {
+        from_b(upper)
+    } else {
+        from_a(price).min(from_b(price))
+    };
+    let haircut = (MAX_BPS as u64 - LIQUIDITY_HAIRCUT_BPS) as f64 / MAX_BPS as f64;
+    (liquidity * haircut) as u128
+}
+
+/// Remove `liquidity` from a position
+pub fn decrease_liquidity<'info>(
+    cpi: &PositionCpi<'_, 'info>,
+    accounts: &WhirlpoolAccounts<'info>,
+    position: &WhirlpoolPositionAccounts<'info>,
+    liquidity: u128,
+    token_min_a: u64,
+    token_min_b: u64,
+) -> Result<()> {
+    let mut data = DECREASE_LIQUIDITY.to_vec();
+    (liquidity, token_min_a, token_min_b).serialize(&mut data)?;
+    invoke(&accounts.program, data, modify_liquidity_accounts(cpi, accounts, position), cpi.signer_seeds)
+}
+
+/// Add `liquidity` to a position, spending at most `token_max_a` / `token_max_b`
+pub fn increase_liquidity<'info>(
+    cpi: &PositionCpi<'_, 'info>,
+    accounts: &WhirlpoolAccounts<'info>,
+    position: &WhirlpoolPositionAccounts<'info>,
+    liquidity: u128,
+    token_max_a: u64,
+    token_max_b: u64,
+) -> Result<()> {
+    let mut data = INCREASE_LIQUIDITY.to_vec();
+    (liquidity, token_max_a, token_max_b).serialize(&mut data)?;
+    invoke(&accounts.program, data, modify_liquidity_accounts(cpi, accounts, position), cpi.signer_seeds)
+}
+
+/// Account list shared by `increase_liquidity` and `decrease_liquidity`
+fn modify_liquidity_accounts<'info>(
+    cpi: &PositionCpi<'_, 'info>,
+    accounts: &WhirlpoolAccounts<'info>,
+    position: &WhirlpoolPositionAccounts<'info>,
+) -> Vec<(AccountMeta, AccountInfo<'info>)> {
+    vec![
+        (meta(&accounts.whirlpool, true), accounts.whirlpool.clone()),
+        (meta(&cpi.token_program, false), cpi.token_program.clone()),
+        (signer_meta(&cpi.position, false), cpi.position.clone()),
+        (meta(&position.position, true), position.position.clone()),
+        (meta(&position.position_token_account, false), position.position_token_account.clone()),
+        (meta(&cpi.token_a_vault, true), cpi.token_a_vault.clone()),
+        (meta(&cpi.token_b_vault, true), cpi.token_b_vault.clone()),
+        (meta(&accounts.token_vault_a, true), accounts.token_vault_a.clone()),
+        (meta(&accounts.token_vault_b, true), accounts.token_vault_b.clone()),
+        (meta(&position.tick_array_lower, true), position.tick_array_lower.clone()),
+        (meta(&position.tick_array_upper, true), position.tick_array_upper.clone()),
+    ]
+}
+
+/// Bring a position's owed fees up to date
+pub fn update_fees_and_rewards<'info>(
+    accounts: &WhirlpoolAccounts<'info>,
+    position: &WhirlpoolPositionAccounts<'info>,
+) -> Result<()> {
+    invoke(
+        &accounts.program,
+        UPDATE_FEES_AND_REWARDS.to_vec(),
+        vec![
+            (meta(&accounts.whirlpool, true), accounts.whirlpool.clone()),
+            (meta(&position.position, true), position.position.clone()),
+            (meta(&position.tick_array_lower, false), position.tick_array_lower.clone()),
+            (meta(&position.tick_array_upper, false), position.tick_array_upper.clone()),
+        ],
+        &[],
+    )
+}
+
+/// Pay a position's owed fees into the vaults
+pub fn collect_fees<'info>(
+    cpi: &PositionCpi<'_, 'info>,
+    accounts: &WhirlpoolAccounts<'info>,
+    position: &WhirlpoolPositionAccounts<'info>,
+) -> Result<()> {
+    invoke(
+        &accounts.program,
+        COLLECT_FEES.to_vec(),
+        vec![
+            (meta(&accounts.whirlpool, false), accounts.whirlpool.clone()),
+            (signer_meta(&cpi.position, false), cpi.position.clone()),
+            (meta(&position.position, true), position.position.clone()),
+            (meta(&position.position_token_account, false), position.position_token_account.clone()),
+            (meta(&cpi.token_a_vault, true), cpi.token_a_vault.clone()),
+            (meta(&accounts.token_vault_a, true), accounts.token_vault_a.clone()),
+            (meta(&cpi.token_b_vault, true), cpi.token_b_vault.clone()),
+            (meta(&accounts.token_vault_b, true), accounts.token_vault_b.clone()),
+            (meta(&cpi.token_program, false), cpi.token_program.clone()),
+        ],
+        cpi.signer_seeds,
+    )
+}
+
+/// Open an empty position over `[tick_lower, tick_upper]` owned by the position PDA
+pub fn open_position<'info>(
+    cpi: &PositionCpi<'_, 'info>,
+    accounts: &WhirlpoolAccounts<'info>,
+    position: &WhirlpoolPositionAccounts<'info>,
+    tick_lower: i32,
+    tick_upper: i32,
+) -> Result<()> {
+    let payer = cpi
+        .payer
+        .as_ref()
+        .ok_or(XLiquidityEngineError::InvalidDexAccounts)?;
+    let (position_address, position_bump) = Pubkey::find_program_address(
+        &[b"position", position.position_mint.key().as_ref()],
+        &WHIRLPOOL_PROGRAM_ID,
+    );
+    require_keys_eq!(
+        position.position.key(),
+        position_address,
+        XLiquidityEngineError::InvalidDexAccounts
+    );
+
+    let mut data = OPEN_POSITION.to_vec();
+    (position_bump, tick_lower, tick_upper).serialize(&mut data)?;
+    invoke(
+        &accounts.program,
+        data,
+        vec![
+            (meta(payer, true), payer.clone()),
+            (meta(&cpi.position, false), cpi.position.clone()),
+            (meta(&position.position, true), position.position.clone()),
+            (meta(&position.position_mint, true), position.position_mint.clone()),
+            (meta(&position.position_token_account, true), position.position_token_account.clone()),
+            (meta(&accounts.whirlpool, false), accounts.whirlpool.clone()),
+            (meta(&cpi.token_program, false), cpi.token_program.clone()),
+            (meta(&cpi.system_program, false), cpi.system_program.clone()),
+            (meta(&accounts.rent, false), accounts.rent.clone()),
+            (meta(&accounts.associated_token_program, false), accounts.associated_token_program.clone()),
+        ],
+        cpi.signer_seeds,
+    )
+}
+
+/// Close an emptied position and burn its NFT; the rent goes to the position PDA
+pub fn close_position<'info>(
+    cpi: &PositionCpi<'_, 'info>,
+    accounts: &WhirlpoolAccounts<'info>,
+    position: &WhirlpoolPositionAccounts<'info>,
+) -> Result<()> {
+    invoke(
+        &accounts.program,
+        CLOSE_POSITION.to_vec(),
+        vec![
+            (signer_meta(&cpi.position, false), cpi.position.clone()),
+            // Receiver of the rent
+            (meta(&cpi.position, true), cpi.position.clone()),
+            (meta(&position.position, true), position.position.clone()),
+            (meta(&position.position_mint, true), position.position_mint.clone()),
+            (meta(&position.position_token_account, true), position.position_token_account.clone()),
+            (meta(&cpi.token_program, false), cpi.token_program.clone()),
+        ],
+        cpi.signer_seeds,
+    )
+}
+
+/// [`DexAdapter`] for an Orca Whirlpool
+pub struct WhirlpoolAdapter<'info> {
+    accounts: WhirlpoolAccounts<'info>,
+    /// Mint of the current position
+    position_mint: Option<Pubkey>,
+}
+
+impl<'info> WhirlpoolAdapter<'info> {
+    /// Parse and validate the Whirlpool accounts of `position`
+    pub fn load(
+        accounts: &[AccountInfo<'info>],
+        position: &LiquidityPosition,
+        opens_new: bool,
+    ) -> Result<Self> {
+        let accounts = WhirlpoolAccounts::parse(
+            accounts,
+            &position.pool_address,
+            position.position_nft.is_some(),
+            opens_new,
+        )?;
+        validate_pool(&accounts, &position.token_a, &position.token_b)?;
+        Ok(Self {
+            accounts,
+            position_mint: position.position_nft,
+        })
+    }
+
+    /// Accounts and state of the current position, checked against `position_mint`
+    fn current(&self) -> Result<Option<(&WhirlpoolPositionAccounts<'info>, WhirlpoolPosition)>> {
+        let Some(position_mint) = self.position_mint else {
+            return Ok(None);
+        };
+        let current = self
+            .accounts
+            .current
+            .as_ref()
+            .ok_or(XLiquidityEngineError::InvalidDexAccounts)?;
+        let position = read_position(&current.position)?;
+        require!(
+            current.position_mint.key() == position_mint
+                && position.position_mint == position_mint
+                && position.whirlpool == self.accounts.whirlpool.key(),
+            XLiquidityEngineError::InvalidDexAccounts
+        );
+        Ok(Some((current, position)))
+    }
+}
+
+impl<'info> DexAdapter<'info> for WhirlpoolAdapter<'info> {
+    fn collect_fees(&self, cpi: &PositionCpi<'_, 'info>) -> Result<()> {
+        let Some((current, position)) = self.current()? else {
+            return Ok(());
+        };
+        // Whirlpool only credits owed fees on a liquidity change or an explicit update
+        if position.liquidity > 0 {
+            update_fees_and_rewards(&self.accounts, current)?;
+        }
+        collect_fees(cpi, &self.accounts, current)
+    }
+
+    fn remove_liquidity(&self, cpi: &PositionCpi<'_, 'info>, (min_a, min_b): (u64, u64)) -> Result<()> {
+        match self.current()? {
+            Some((current, position)) if position.liquidity > 0 => {
+                decrease_liquidity(cpi, &self.accounts, current, position.liquidity, min_a, min_b)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Adds the largest liquidity the vault balances fund at the pool price,
+    /// to a position opened with the `position_mint` from the accounts or, if
+    /// no new position was passed, to the current one
+    fn add_liquidity(&self, cpi: &PositionCpi<'_, 'info>, range: &TargetRange) -> Result<Option<Pubkey>> {
+        let available_a = token_balance(&cpi.token_a_vault)?;
+        let available_b = token_balance(&cpi.token_b_vault)?;
+        let liquidity = liquidity_for_amounts(
+            self.read_pool_price()?,
+            range.tick_lower,
+            range.tick_upper,
+            available_a,
+            available_b,
+        );
+        if liquidity == 0 {
+            return Ok(if self.accounts.new.is_some() { None } else { self.position_mint });
+        }
+
+        let (position_mint, position) = match (&self.accounts.new, &self.accounts.current) {
+            (Some(new), _) => {
+                open_position(cpi, &self.accounts, new, range.tick_lower, range.tick_upper)?;
+                (Some(new.position_mint.key()), new)
+            }
+            (None, Some(current)) => (self.position_mint, current),
+            (None, None) => return err!(XLiquidityEngineError::InvalidDexAccounts),
+        };
+        increase_liquidity(cpi, &self.accounts, position, liquidity, available_a, available_b)?;
+        Ok(position_mint)
+    }
+
+    fn close_replaced_position(&self, cpi: &PositionCpi<'_, 'info>) -> Result<()> {
+        match (self.current()?, &self.accounts.new) {
+            (Some((current, _)), Some(_)) => close_position(cpi, &self.accounts, current),
+            _ => Ok(()),
+        }
+    }
+
+    fn read_pool_price(&self) -> Result<u128> {
+        Ok(read_whirlpool(&self.accounts.whirlpool)?.sqrt_price)
+    }
+
+    fn position_amounts(&self, sqrt_price_x64: u128) -> Result<(u64, u64)> {
+        let Some((_, position)) = self.current()? else {
+            return Ok((0, 0));
+        };
+        math::amounts_for_liquidity(
+            sqrt_price_x64,
+            position.tick_lower_index,
+            position.tick_upper_index,
+            position.liquidity,
+        )
+    }
+
+    fn liquidity_of(&self, dex_position: Pubkey) -> Result<Option<u128>> {
+        let accounts = match &self.accounts.new {
+            Some(new) if new.position_mint.key() == dex_position => new,
+            _ => {
+                require!(
+                    self.position_mint == Some(dex_position),
+                    XLiquidityEngineError::InvalidDexAccounts
+                );
+                self.accounts
+                    .current
+                    .as_ref()
+                    .ok_or(XLiquidityEngineError::InvalidDexAccounts)?
+            }
+        };
+        Ok(Some(read_position(&accounts.position)?.liquidity))
+    }
+}
@@ -1,7 +1,16 @@
 use anchor_lang::prelude::*;
+use anchor_lang::system_program;
+use anchor_spl::token::{self, CloseAccount, Mint, Token, TokenAccount, TransferChecked};
+use solana_instructions_sysvar::{load_current_index_checked, load_instruction_at_checked};
+use solana_sha256_hasher::hashv;
 
 declare_id!("5eKPz3P7vBT1RhMUoYadmHB4KaNwjSoaUPaNvEzjcuKx");
 
+#[cfg(not(target_os = "solana"))]
+pub mod client;
+pub mod dex;
+pub mod migration;
+
 #[program]
 pub mod x_liquidity_engine {
     use super::*;
@@ -21,25 +30,495 @@ pub mod x_liquidity_engine {
         config.performance_fee_bps = performance_fee_bps;
         config.protocol_fee_bps = protocol_fee_bps;
         config.fee_recipient = ctx.accounts.fee_recipient.key();
+        config.x402_facilitator = None;
+        config.x402_facilitators = vec![];
         config.x402_min_payment = x402_min_payment;
+        config.x402_usdc_mint = None;
+        config.x402_usdt_mint = None;
         config.x402_api_base_url = "https://api.x-liquidity-engine.com".to_string();
+        config.revenue_split = RevenueSplit {
+            treasury_bps: MAX_BPS,
+            providers_bps: 0,
+            lp_pool_bps: 0,
+        };
         config.min_rebalance_interval = 3600; // 1 hour default
         config.max_rebalance_frequency = 24; // Max 24 per day
         config.default_slippage_tolerance_bps = 50; // 0.5% default
+        config.swap_program = None;
+        config.rebalance_keeper = None;
+        config.x402_token_vaults = [0; PAYMENT_CURRENCY_COUNT];
         config.max_position_size = 1_000_000_000_000; // $1M default (scaled)
         config.max_single_trade_size = 100_000_000_000; // $100K default (scaled)
         config.require_human_approval_threshold = 500_000_000_000; // $500K threshold
         config.default_ai_model_version = "v1.0.0".to_string();
         config.audit_log_enabled = true;
+        config.pending_authority = None;
         config.compliance_mode = ComplianceMode::Enhanced;
+        config.paused = ProtocolPauseFlags::default();
         config.created_at = clock.unix_timestamp;
         config.updated_at = clock.unix_timestamp;
+        config.layout_version = LAYOUT_VERSION;
 
         msg!("Protocol config initialized by: {}", ctx.accounts.authority.key());
         Ok(())
     }
 
+    /// Update protocol configuration parameters (authority only)
+    ///
+    /// Only fields set in `params` are changed. The audit event records the
+    /// borsh-encoded old and new values of every changed field.
+    pub fn update_protocol_config(
+        ctx: Context<UpdateProtocolConfig>,
+        params: UpdateProtocolConfigParams,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        let clock = Clock::get()?;
+
+        params.validate(config)?;
+
+        let mut old = UpdateProtocolConfigParams::default();
+        if let Some(value) = params.performance_fee_bps {
+            old.performance_fee_bps = Some(config.performance_fee_bps);
+            config.performance_fee_bps = value;
+        }
+        if let Some(value) = params.protocol_fee_bps {
+            old.protocol_fee_bps = Some(config.protocol_fee_bps);
+            config.protocol_fee_bps = value;
+        }
+        if let Some(value) = params.fee_recipient {
+            old.fee_recipient = Some(config.fee_recipient);
+            config.fee_recipient = value;
+        }
+        if let Some(value) = params.x402_min_payment {
+            old.x402_min_payment = Some(config.x402_min_payment);
+            config.x402_min_payment = value;
+        }
+        if let Some(value) = params.min_rebalance_interval {
+            old.min_rebalance_interval = Some(config.min_rebalance_interval);
+            config.min_rebalance_interval = value;
+        }
+        if let Some(value) = params.max_rebalance_frequency {
+            old.max_rebalance_frequency = Some(config.max_rebalance_frequency);
+            config.max_rebalance_frequency = value;
+        }
+        if let Some(value) = params.default_slippage_tolerance_bps {
+            old.default_slippage_tolerance_bps = Some(config.default_slippage_tolerance_bps);
+            config.default_slippage_tolerance_bps = value;
+        }
+        if let Some(value) = params.max_position_size {
+            old.max_position_size = Some(config.max_position_size);
+            config.max_position_size = value;
+        }
+        if let Some(value) = params.max_single_trade_size {
+            old.max_single_trade_size = Some(config.max_single_trade_size);
+            config.max_single_trade_size = value;
+        }
+        if let Some(value) = params.require_human_approval_threshold {
+            old.require_human_approval_threshold = Some(config.require_human_approval_threshold);
+            config.require_human_approval_threshold = value;
+        }
+        if let Some(value) = params.compliance_mode {
+            old.compliance_mode = Some(config.compliance_mode);
+            config.compliance_mode = value;
+        }
+        if params.x402_usdc_mint != PubkeyUpdate::Keep {
+            old.x402_usdc_mint = config.x402_usdc_mint.into();
+            config.x402_usdc_mint = params.x402_usdc_mint.apply(config.x402_usdc_mint);
+        }
+        if params.x402_usdt_mint != PubkeyUpdate::Keep {
+            old.x402_usdt_mint = config.x402_usdt_mint.into();
+            config.x402_usdt_mint = params.x402_usdt_mint.apply(config.x402_usdt_mint);
+        }
+        if let Some(value) = params.revenue_split {
+            old.revenue_split = Some(config.revenue_split);
+            config.revenue_split = value;
+        }
+        if params.swap_program != PubkeyUpdate::Keep {
+            old.swap_program = config.swap_program.into();
+            config.swap_program = params.swap_program.apply(config.swap_program);
+        }
+        if params.rebalance_keeper != PubkeyUpdate::Keep {
+            old.rebalance_keeper = config.rebalance_keeper.into();
+            config.rebalance_keeper = params.rebalance_keeper.apply(config.rebalance_keeper);
+        }
+        config.updated_at = clock.unix_timestamp;
+
+        // Create audit log
+        let event_data = (old, params).try_to_vec()?;
+        create_audit_log_internal(
+            &mut ctx.accounts.audit_stream,
+            ctx.bumps.audit_stream,
+            &mut ctx.accounts.audit_log,
+            ctx.bumps.audit_log,
+            ctx.accounts.config.key(),
+            AuditEventType::ConfigUpdated,
+            None,
+            ctx.accounts.authority.key(),
+            &event_data,
+            &clock,
+        )?;
+
+        msg!("Protocol config updated by: {}", ctx.accounts.authority.key());
+        Ok(())
+    }
+
+    /// Replace the x402 revenue providers in `ai_model_registry` (authority
+    /// only)
+    pub fn set_ai_model_registry(
+        ctx: Context<SetAiModelRegistry>,
+        registry: Vec<Pubkey>,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        let clock = Clock::get()?;
+
+        require!(
+            registry.len() <= MAX_AI_MODEL_REGISTRY,
+            XLiquidityEngineError::InvalidConfigValue
+        );
+        for (i, provider) in registry.iter().enumerate() {
+            require!(
+                !registry[..i].contains(provider),
+                XLiquidityEngineError::InvalidConfigValue
+            );
+        }
+
+        let old = std::mem::replace(&mut config.ai_model_registry, registry);
+        config.updated_at = clock.unix_timestamp;
+
+        // Create audit log
+        let event_data = (old, &config.ai_model_registry).try_to_vec()?;
+        create_audit_log_internal(
+            &mut ctx.accounts.audit_stream,
+            ctx.bumps.audit_stream,
+            &mut ctx.accounts.audit_log,
+            ctx.bumps.audit_log,
+            ctx.accounts.config.key(),
+            AuditEventType::AiModelRegistryUpdated,
+            None,
+            ctx.accounts.authority.key(),
+            &event_data,
+            &clock,
+        )?;
+
+        msg!("AI model registry set: {} providers", ctx.accounts.config.ai_model_registry.len());
+        Ok(())
+    }
+
+    /// Set the protocol-wide pause flags (authority only)
+    ///
+    /// Incident-response lever: each flag halts one area of the protocol across
+    /// every position and payment. Withdrawals, position closes, refunds and
+    /// revenue claims are never paused.
+    pub fn set_protocol_paused(
+        ctx: Context<SetProtocolPaused>,
+        paused: ProtocolPauseFlags,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        let clock = Clock::get()?;
+
+        let old = config.paused;
+        config.paused = paused;
+        config.updated_at = clock.unix_timestamp;
+
+        // Create audit log
+        let event_data = (old, paused).try_to_vec()?;
+        create_audit_log_internal(
+            &mut ctx.accounts.audit_stream,
+            ctx.bumps.audit_stream,
+            &mut ctx.accounts.audit_log,
+            ctx.bumps.audit_log,
+            ctx.accounts.config.key(),
+            AuditEventType::ProtocolPauseUpdated,
+            None,
+            ctx.accounts.authority.key(),
+            &event_data,
+            &clock,
+        )?;
+
+        msg!(
+            "Protocol pause flags set: rebalancing {}, new positions {}, x402 payments {}",
+            paused.rebalancing,
+            paused.new_positions,
+            paused.x402_payments
+        );
+        Ok(())
+    }
+
+    /// Propose a new protocol authority (step 1 of 2)
+    pub fn propose_authority(
+        ctx: Context<ProposeAuthority>,
+        new_authority: Pubkey,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        let clock = Clock::get()?;
+
+        require!(
+            new_authority != Pubkey::default() && new_authority != config.authority,
+            XLiquidityEngineError::InvalidAuthority
+        );
+
+        config.pending_authority = Some(new_authority);
+        config.updated_at = clock.unix_timestamp;
+
+        // Create audit log
+        create_audit_log_internal(
+            &mut ctx.accounts.audit_stream,
+            ctx.bumps.audit_stream,
+            &mut ctx.accounts.audit_log,
+            ctx.bumps.audit_log,
+            ctx.accounts.config.key(),
+            AuditEventType::AuthorityProposed,
+            None,
+            ctx.accounts.authority.key(),
+            new_authority.as_ref(),
+            &clock,
+        )?;
+
+        msg!("Protocol authority transfer proposed to: {}", new_authority);
+        Ok(())
+    }
+
+    /// Accept a pending protocol authority transfer (step 2 of 2)
+    pub fn accept_authority(ctx: Context<AcceptAuthority>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        let clock = Clock::get()?;
+
+        let previous_authority = config.authority;
+        config.authority = ctx.accounts.new_authority.key();
+        config.pending_authority = None;
+        config.updated_at = clock.unix_timestamp;
+
+        // Create audit log
+        create_audit_log_internal(
+            &mut ctx.accounts.audit_stream,
+            ctx.bumps.audit_stream,
+            &mut ctx.accounts.audit_log,
+            ctx.bumps.audit_log,
+            ctx.accounts.config.key(),
+            AuditEventType::AuthorityTransferred,
+            None,
+            ctx.accounts.new_authority.key(),
+            previous_authority.as_ref(),
+            &clock,
+        )?;
+
+        msg!(
+            "Protocol authority transferred from {} to {}",
+            previous_authority,
+            ctx.accounts.new_authority.key()
+        );
+        Ok(())
+    }
+
+    /// Cancel a pending protocol authority transfer
+    pub fn cancel_authority_transfer(ctx: Context<CancelAuthorityTransfer>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        let clock = Clock::get()?;
+
+        let pending_authority = config
+            .pending_authority
+            .take()
+            .ok_or(XLiquidityEngineError::NoPendingAuthority)?;
+        config.updated_at = clock.unix_timestamp;
+
+        // Create audit log
+        create_audit_log_internal(
+            &mut ctx.accounts.audit_stream,
+            ctx.bumps.audit_stream,
+            &mut ctx.accounts.audit_log,
+            ctx.bumps.audit_log,
+            ctx.accounts.config.key(),
+            AuditEventType::AuthorityTransferCancelled,
+            None,
+            ctx.accounts.authority.key(),
+            pending_authority.as_ref(),
+            &clock,
+        )?;
+
+        msg!("Protocol authority transfer to {} cancelled", pending_authority);
+        Ok(())
+    }
+
+    /// Register or update an x402 facilitator in the registry (authority only)
+    ///
+    /// An existing entry keeps its `expires_at`, e.g. the end of a rotation
+    /// overlap window, unless `params.expires_at` changes it.
+    pub fn set_x402_facilitator(
+        ctx: Context<SetX402Facilitator>,
+        params: X402FacilitatorParams,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        let clock = Clock::get()?;
+
+        params.validate(clock.unix_timestamp)?;
+        config.prune_expired_facilitators(clock.unix_timestamp);
+
+        let mut entry = X402FacilitatorEntry {
+            facilitator: params.facilitator,
+            enabled: params.enabled,
+            accepted_currencies: params.accepted_currencies.clone(),
+            min_payment: params.min_payment,
+            expires_at: None,
+        };
+        match config
+            .x402_facilitators
+            .iter_mut()
+            .find(|existing| existing.facilitator == params.facilitator)
+        {
+            Some(existing) => {
+                entry.expires_at = params.expires_at.apply(existing.expires_at);
+                *existing = entry;
+            }
+            None => {
+                entry.expires_at = params.expires_at.apply(None);
+                require!(
+                    config.x402_facilitators.len() < MAX_X402_FACILITATORS,
+                    XLiquidityEngineError::FacilitatorRegistryFull
+                );
+                config.x402_facilitators.push(entry);
+            }
+        }
+        config.updated_at = clock.unix_timestamp;
+
+        // Create audit log
+        let mut event_data = Vec::new();
+        params.serialize(&mut event_data)?;
+        create_audit_log_internal(
+            &mut ctx.accounts.audit_stream,
+            ctx.bumps.audit_stream,
+            &mut ctx.accounts.audit_log,
+            ctx.bumps.audit_log,
+            ctx.accounts.config.key(),
+            AuditEventType::FacilitatorUpdated,
+            None,
+            ctx.accounts.authority.key(),
+            &event_data,
+            &clock,
+        )?;
+
+        msg!("x402 facilitator set: {}", params.facilitator);
+        Ok(())
+    }
+
+    /// Rotate an x402 facilitator to a new key (authority only)
+    ///
+    /// The incoming facilitator inherits the outgoing entry's settings. With a
+    /// non-zero `overlap_seconds`, payments signed by the outgoing facilitator
+    /// keep verifying until the overlap window ends.
+    pub fn rotate_x402_facilitator(
+        ctx: Context<SetX402Facilitator>,
+        outgoing: Pubkey,
+        incoming: Pubkey,
+        overlap_seconds: u32,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        let clock = Clock::get()?;
+
+        require!(
+            incoming != Pubkey::default() && incoming != outgoing,
+            XLiquidityEngineError::InvalidFacilitator
+        );
+        require!(
+            overlap_seconds <= MAX_FACILITATOR_OVERLAP,
+            XLiquidityEngineError::InvalidConfigValue
+        );
+        config.prune_expired_facilitators(clock.unix_timestamp);
+        require!(
+            config.find_facilitator(&incoming).is_none(),
+            XLiquidityEngineError::InvalidFacilitator
+        );
+
+        let index = config
+            .x402_facilitators
+            .iter()
+            .position(|entry| entry.facilitator == outgoing)
+            .ok_or(XLiquidityEngineError::InvalidFacilitator)?;
+        let mut entry = config.x402_facilitators[index].clone();
+        entry.facilitator = incoming;
+        entry.expires_at = None;
+
+        if overlap_seconds > 0 {
+            require!(
+                config.x402_facilitators.len() < MAX_X402_FACILITATORS,
+                XLiquidityEngineError::FacilitatorRegistryFull
+            );
+            config.x402_facilitators[index].expires_at = Some(
+                clock
+                    .unix_timestamp
+                    .checked_add(overlap_seconds as i64)
+                    .ok_or(XLiquidityEngineError::MathOverflow)?,
+            );
+            config.x402_facilitators.push(entry);
+        } else {
+            config.x402_facilitators[index] = entry;
+        }
+        config.updated_at = clock.unix_timestamp;
+
+        // Create audit log (outgoing facilitator followed by the incoming one)
+        let mut event_data = outgoing.to_bytes().to_vec();
+        event_data.extend_from_slice(incoming.as_ref());
+        create_audit_log_internal(
+            &mut ctx.accounts.audit_stream,
+            ctx.bumps.audit_stream,
+            &mut ctx.accounts.audit_log,
+            ctx.bumps.audit_log,
+            ctx.accounts.config.key(),
+            AuditEventType::FacilitatorRotated,
+            None,
+            ctx.accounts.authority.key(),
+            &event_data,
+            &clock,
+        )?;
+
+        msg!("x402 facilitator rotated from {} to {}", outgoing, incoming);
+        Ok(())
+    }
+
+    /// Remove an x402 facilitator from the registry (authority only)
+    pub fn revoke_x402_facilitator(
+        ctx: Context<SetX402Facilitator>,
+        facilitator: Pubkey,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        let clock = Clock::get()?;
+
+        let index = config
+            .x402_facilitators
+            .iter()
+            .position(|entry| entry.facilitator == facilitator)
+            .ok_or(XLiquidityEngineError::InvalidFacilitator)?;
+        config.x402_facilitators.remove(index);
+        config.prune_expired_facilitators(clock.unix_timestamp);
+        config.updated_at = clock.unix_timestamp;
+
+        // Create audit log
+        create_audit_log_internal(
+            &mut ctx.accounts.audit_stream,
+            ctx.bumps.audit_stream,
+            &mut ctx.accounts.audit_log,
+            ctx.bumps.audit_log,
+            ctx.accounts.config.key(),
+            AuditEventType::FacilitatorRevoked,
+            None,
+            ctx.accounts.authority.key(),
+            facilitator.as_ref(),
+            &clock,
+        )?;
+
+        msg!("x402 facilitator revoked: {}", facilitator);
+        Ok(())
+    }
+
     /// Create a new liquidity position
+    ///
+    /// Also creates the position's token A/B vaults: PDA token accounts of the
+    /// `token_a` / `token_b` mints whose authority is the position PDA.
+    /// `pool` is the `dex` pool (Raydium CLMM pool, Orca Whirlpool or Meteora
+    /// DLMM pair) the position's liquidity is deployed to; Meteora positions
+    /// also take the `bin_range` their liquidity is spread over.
+    /// `max_amount_a` / `max_amount_b` cap the owner's net deposits of each
+    /// token, in raw units.
+    #[allow(clippy::too_many_arguments)]
     pub fn create_liquidity_position(
         ctx: Context<CreateLiquidityPosition>,
         _position_index: u8,
@@ -51,13 +530,24 @@ pub mod x_liquidity_engine {
         price_upper: u128,
         max_position_size: u64,
         max_single_trade: u64,
+        dex: DexType,
+        bin_range: Option<BinRange>,
+        max_amount_a: u64,
+        max_amount_b: u64,
     ) -> Result<()> {
         let position = &mut ctx.accounts.position;
         let clock = Clock::get()?;
 
-        // Validate price range
+        // Validate price range, token pair and DEX
         require!(tick_lower < tick_upper, XLiquidityEngineError::InvalidPriceRange);
         require!(price_lower < price_upper, XLiquidityEngineError::InvalidPriceRange);
+        require_keys_neq!(token_a, token_b, XLiquidityEngineError::InvalidMint);
+        require!(dex != DexType::Unknown, XLiquidityEngineError::UnsupportedDex);
+        BinRange::validate_for(dex, bin_range.as_ref())?;
+        require!(
+            max_amount_a > 0 && max_amount_b > 0,
+            XLiquidityEngineError::InvalidLiquidityAmount
+        );
 
         // Validate against protocol limits
         let config = &ctx.accounts.config;
@@ -76,22 +566,27 @@ pub mod x_liquidity_engine {
         position.token_b = token_b;
         position.token_a_vault = ctx.accounts.token_a_vault.key();
         position.token_b_vault = ctx.accounts.token_b_vault.key();
-        position.dex = DexType::Raydium; // Default to Raydium
+        position.dex = dex;
         position.pool_address = ctx.accounts.pool.key();
         position.current_tick_lower = tick_lower;
         position.current_tick_upper = tick_upper;
         position.current_price_lower = price_lower;
         position.current_price_upper = price_upper;
+        position.bin_range = bin_range;
         position.liquidity_amount = 0;
         position.total_fees_earned_a = 0;
         position.total_fees_earned_b = 0;
         position.total_value_locked = 0;
+        position.deployed_amount_a = 0;
+        position.deployed_amount_b = 0;
         position.last_rebalance_slot = 0;
         position.last_rebalance_timestamp = 0;
         position.rebalance_count = 0;
+        position.pending_decisions = 0;
         position.total_return_percentage = 0;
         position.apy_estimate = 0;
         position.status = PositionStatus::Active;
+        position.emergency_paused = false;
         position.auto_rebalance_enabled = true;
         position.min_rebalance_interval = config.min_rebalance_interval;
         position.max_position_size = max_position_size;
@@ -99,829 +594,5412 @@ pub mod x_liquidity_engine {
         position.allowed_dex_programs = vec![ctx.accounts.pool.key()];
         position.created_at = clock.unix_timestamp;
         position.updated_at = clock.unix_timestamp;
+        position.layout_version = LAYOUT_VERSION;
+        position.max_amount_a = max_amount_a;
+        position.max_amount_b = max_amount_b;
+        position.net_deposited_a = 0;
+        position.net_deposited_b = 0;
+        position.value_sqrt_price_x64 = 0;
 
         // Create audit log
         create_audit_log_internal(
-            &ctx.accounts.audit_log,
+            &mut ctx.accounts.audit_stream,
+            ctx.bumps.audit_stream,
+            &mut ctx.accounts.audit_log,
+            ctx.bumps.audit_log,
+            position.key(),
             AuditEventType::PositionCreated,
             Some(position.key()),
             ctx.accounts.owner.key(),
             &[],
-            clock,
+            &clock,
         )?;
 
         msg!("Liquidity position created: {}", position.key());
         Ok(())
     }
 
-    /// Create a rebalancing decision based on AI prediction
-    pub fn create_rebalance_decision(
-        ctx: Context<CreateRebalanceDecision>,
+    /// Deposit token A/B from the owner into the position vaults (owner only)
+    ///
+    /// The owner's net deposits of each token (deposits less withdrawals) may
+    /// not exceed `max_amount_a` / `max_amount_b`, and valued in token B at
+    /// the pool price they may not exceed the position's or the protocol's
+    /// `max_position_size`; tokens sent to the vaults directly, fees and
+    /// swaps don't count against the caps.
+    pub fn deposit_liquidity(
+        ctx: Context<DepositLiquidity>,
         _position_index: u8,
-        _decision_index: u32,
-        new_tick_lower: i32,
-        new_tick_upper: i32,
-        new_price_lower: u128,
-        new_price_upper: u128,
-        ai_model_version: String,
-        ai_model_hash: [u8; 32],
-        prediction_confidence: u16,
-        market_sentiment_score: i16,
-        volatility_metric: u16,
-        whale_activity_score: u16,
-        decision_reason: String,
+        amount_a: u64,
+        amount_b: u64,
     ) -> Result<()> {
-        let decision = &mut ctx.accounts.decision;
         let position = &ctx.accounts.position;
-        let config = &ctx.accounts.config;
         let clock = Clock::get()?;
 
-        // Validate position is active
         require!(
             position.status == PositionStatus::Active,
             XLiquidityEngineError::PositionNotActive
         );
-
-        // Validate rebalance interval
         require!(
-            clock.unix_timestamp - position.last_rebalance_timestamp >= position.min_rebalance_interval as i64,
-            XLiquidityEngineError::RebalanceTooFrequent
+            amount_a > 0 || amount_b > 0,
+            XLiquidityEngineError::InvalidLiquidityAmount
         );
 
-        // Validate price range
-        require!(new_tick_lower < new_tick_upper, XLiquidityEngineError::InvalidPriceRange);
-        require!(new_price_lower < new_price_upper, XLiquidityEngineError::InvalidPriceRange);
+        for (amount, from, to, mint) in [
+            (
+                amount_a,
+                &ctx.accounts.owner_token_a,
+                &ctx.accounts.token_a_vault,
+                &ctx.accounts.token_a_mint,
+            ),
+            (
+                amount_b,
+                &ctx.accounts.owner_token_b,
+                &ctx.accounts.token_b_vault,
+                &ctx.accounts.token_b_mint,
+            ),
+        ] {
+            if amount == 0 {
+                continue;
+            }
+            token::transfer_checked(
+                CpiContext::new(
+                    ctx.accounts.token_program.to_account_info(),
+                    TransferChecked {
+                        from: from.to_account_info(),
+                        mint: mint.to_account_info(),
+                        to: to.to_account_info(),
+                        authority: ctx.accounts.owner.to_account_info(),
+                    },
+                ),
+                amount,
+                mint.decimals,
+            )?;
+        }
 
-        // Determine risk level and if human approval is needed
-        let risk_assessment = assess_risk(
-            prediction_confidence,
-            market_sentiment_score,
-            volatility_metric,
+        ctx.accounts.token_a_vault.reload()?;
+        ctx.accounts.token_b_vault.reload()?;
+        let sqrt_price_x64 = dex::pool_price(ctx.accounts.position.dex, &ctx.accounts.pool)?;
+        let position = &mut ctx.accounts.position;
+        position.value_sqrt_price_x64 = sqrt_price_x64;
+        position.refresh_value_locked(&ctx.accounts.token_a_vault, &ctx.accounts.token_b_vault)?;
+        position.net_deposited_a = position
+            .net_deposited_a
+            .checked_add(amount_a)
+            .ok_or(XLiquidityEngineError::MathOverflow)?;
+        position.net_deposited_b = position
+            .net_deposited_b
+            .checked_add(amount_b)
+            .ok_or(XLiquidityEngineError::MathOverflow)?;
+        require!(
+            position.net_deposited_a <= position.max_amount_a
+                && position.net_deposited_b <= position.max_amount_b,
+            XLiquidityEngineError::ExceedsMaxPositionSize
         );
-        let requires_human_approval = risk_assessment == RiskLevel::Critical
-            || risk_assessment == RiskLevel::High
-            || position.total_value_locked >= config.require_human_approval_threshold;
+        let net_deposited_value = dex::math::value_in_b(
+            sqrt_price_x64,
+            (position.net_deposited_a, position.net_deposited_b),
+        )?;
+        require!(
+            net_deposited_value <= position.max_position_size.min(ctx.accounts.config.max_position_size) as u128,
+            XLiquidityEngineError::ExceedsMaxPositionSize
+        );
+        position.updated_at = clock.unix_timestamp;
 
-        decision.position = position.key();
-        decision.decision_bump = ctx.bumps.decision;
-        decision.new_tick_lower = new_tick_lower;
-        decision.new_tick_upper = new_tick_upper;
-        decision.new_price_lower = new_price_lower;
-        decision.new_price_upper = new_price_upper;
-        decision.ai_model_version = ai_model_version;
-        decision.ai_model_hash = ai_model_hash;
-        decision.prediction_confidence = prediction_confidence;
-        decision.market_sentiment_score = market_sentiment_score;
-        decision.volatility_metric = volatility_metric;
-        decision.whale_activity_score = whale_activity_score;
-        decision.on_chain_indicators = vec![];
-        decision.decision_reason = decision_reason;
-        decision.risk_assessment = risk_assessment;
-        decision.execution_status = if requires_human_approval {
-            ExecutionStatus::Pending
-        } else {
-            ExecutionStatus::Pending
-        };
-        decision.execution_tx_signature = None;
-        decision.execution_slippage = None;
-        decision.requires_human_approval = requires_human_approval;
-        decision.human_approver = None;
-        decision.approval_timestamp = None;
-        decision.created_at = clock.unix_timestamp;
-        decision.executed_at = None;
+        // Create audit log
+        let event_data = (amount_a, amount_b, position.total_value_locked).try_to_vec()?;
+        create_audit_log_internal(
+            &mut ctx.accounts.audit_stream,
+            ctx.bumps.audit_stream,
+            &mut ctx.accounts.audit_log,
+            ctx.bumps.audit_log,
+            position.key(),
+            AuditEventType::LiquidityDeposited,
+            Some(position.key()),
+            position.owner,
+            &event_data,
+            &clock,
+        )?;
 
         msg!(
-            "Rebalance decision created for position: {}, requires approval: {}",
+            "Liquidity deposited to position {}: {} token A, {} token B",
             position.key(),
-            requires_human_approval
+            amount_a,
+            amount_b
         );
         Ok(())
     }
 
-    /// Execute a rebalancing decision
-    pub fn execute_rebalance(
-        ctx: Context<ExecuteRebalance>,
-        _position_index: u8,
-        _decision_index: u32,
-        slippage_tolerance_bps: u16,
+    /// Withdraw token A/B from the position vaults to the owner (owner only)
+    pub fn withdraw_liquidity(
+        ctx: Context<WithdrawLiquidity>,
+        position_index: u8,
+        amount_a: u64,
+        amount_b: u64,
     ) -> Result<()> {
-        let decision = &mut ctx.accounts.decision;
-        let position = &mut ctx.accounts.position;
+        let position = &ctx.accounts.position;
         let clock = Clock::get()?;
 
-        // Validate decision status
         require!(
-            decision.execution_status == ExecutionStatus::Pending,
-            XLiquidityEngineError::InvalidExecutionStatus
+            position.status != PositionStatus::Closed,
+            XLiquidityEngineError::PositionNotActive
+        );
+        require!(
+            amount_a > 0 || amount_b > 0,
+            XLiquidityEngineError::InvalidLiquidityAmount
+        );
+        require!(
+            amount_a <= ctx.accounts.token_a_vault.amount
+                && amount_b <= ctx.accounts.token_b_vault.amount,
+            XLiquidityEngineError::InsufficientLiquidity
         );
 
-        // Check if human approval is required
-        if decision.requires_human_approval {
-            require!(
-                decision.human_approver.is_some(),
-                XLiquidityEngineError::HumanApprovalRequired
-            );
-            if let Some(approver) = &ctx.accounts.approver {
-                require!(
-                    decision.human_approver.unwrap() == approver.key(),
-                    XLiquidityEngineError::InvalidApprover
-                );
-            } else {
-                return Err(XLiquidityEngineError::HumanApprovalRequired.into());
+        let owner = position.owner;
+        let bump_seed = [position.position_bump];
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"liquidity_position",
+            owner.as_ref(),
+            &[position_index],
+            &bump_seed,
+        ]];
+        for (amount, from, to, mint) in [
+            (
+                amount_a,
+                &ctx.accounts.token_a_vault,
+                &ctx.accounts.owner_token_a,
+                &ctx.accounts.token_a_mint,
+            ),
+            (
+                amount_b,
+                &ctx.accounts.token_b_vault,
+                &ctx.accounts.owner_token_b,
+                &ctx.accounts.token_b_mint,
+            ),
+        ] {
+            if amount == 0 {
+                continue;
             }
+            token::transfer_checked(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    TransferChecked {
+                        from: from.to_account_info(),
+                        mint: mint.to_account_info(),
+                        to: to.to_account_info(),
+                        authority: position.to_account_info(),
+                    },
+                    signer_seeds,
+                ),
+                amount,
+                mint.decimals,
+            )?;
         }
 
-        // Validate slippage tolerance
-        let config = &ctx.accounts.config;
-        require!(
-            slippage_tolerance_bps <= config.default_slippage_tolerance_bps * 2,
-            XLiquidityEngineError::SlippageTooHigh
-        );
-
-        // Update position with new range
-        position.current_tick_lower = decision.new_tick_lower;
-        position.current_tick_upper = decision.new_tick_upper;
-        position.current_price_lower = decision.new_price_lower;
-        position.current_price_upper = decision.new_price_upper;
-        position.last_rebalance_slot = clock.slot;
-        position.last_rebalance_timestamp = clock.unix_timestamp;
-        position.rebalance_count = position.rebalance_count.checked_add(1).unwrap();
+        ctx.accounts.token_a_vault.reload()?;
+        ctx.accounts.token_b_vault.reload()?;
+        let position = &mut ctx.accounts.position;
+        position.refresh_value_locked(&ctx.accounts.token_a_vault, &ctx.accounts.token_b_vault)?;
+        position.net_deposited_a = position.net_deposited_a.saturating_sub(amount_a);
+        position.net_deposited_b = position.net_deposited_b.saturating_sub(amount_b);
         position.updated_at = clock.unix_timestamp;
 
-        // Update decision status
-        decision.execution_status = ExecutionStatus::Executed;
-        decision.executed_at = Some(clock.unix_timestamp);
-        // Note: execution_tx_signature and execution_slippage would be set by off-chain service
-
         // Create audit log
-        let event_data = format!(
-            "Rebalanced position {}: ticks [{}, {}], prices [{}, {}]",
-            position.key(),
-            decision.new_tick_lower,
-            decision.new_tick_upper,
-            decision.new_price_lower,
-            decision.new_price_upper
-        );
+        let event_data = (amount_a, amount_b, position.total_value_locked).try_to_vec()?;
         create_audit_log_internal(
-            &ctx.accounts.audit_log,
-            AuditEventType::Rebalanced,
+            &mut ctx.accounts.audit_stream,
+            ctx.bumps.audit_stream,
+            &mut ctx.accounts.audit_log,
+            ctx.bumps.audit_log,
+            position.key(),
+            AuditEventType::LiquidityWithdrawn,
             Some(position.key()),
             position.owner,
-            event_data.as_bytes(),
-            clock,
+            &event_data,
+            &clock,
         )?;
 
-        msg!("Rebalance executed for position: {}", position.key());
+        msg!(
+            "Liquidity withdrawn from position {}: {} token A, {} token B",
+            position.key(),
+            amount_a,
+            amount_b
+        );
         Ok(())
     }
 
-    /// Verify x402 payment and grant API access
-    pub fn verify_x402_payment(
-        ctx: Context<VerifyX402Payment>,
-        payment_id: [u8; 32],
-        amount: u64,
-        currency: PaymentCurrency,
-        api_endpoint: String,
-        api_version: String,
+    /// Close a liquidity position (owner only)
+    ///
+    /// Removes any liquidity from the DEX (DEX accounts as remaining accounts,
+    /// as for `execute_rebalance`), withdraws all remaining liquidity and
+    /// uncollected fees from the vaults to the owner and marks the position
+    /// closed. Fails while a rebalance decision is still pending. With
+    /// `close_account` the vaults and the position account are closed as well
+    /// and their rent is returned to the owner.
+    pub fn close_liquidity_position<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CloseLiquidityPosition<'info>>,
+        position_index: u8,
+        close_account: bool,
     ) -> Result<()> {
-        let payment = &mut ctx.accounts.payment;
-        let config = &ctx.accounts.config;
+        let position = &ctx.accounts.position;
         let clock = Clock::get()?;
 
-        // Validate minimum payment
         require!(
-            amount >= config.x402_min_payment,
-            XLiquidityEngineError::PaymentTooSmall
+            position.status != PositionStatus::Closed,
+            XLiquidityEngineError::PositionNotActive
         );
-
-        // Validate facilitator
         require!(
-            ctx.accounts.facilitator.key() == config.x402_facilitator.unwrap_or(Pubkey::default()),
-            XLiquidityEngineError::InvalidFacilitator
+            position.pending_decisions == 0,
+            XLiquidityEngineError::RebalancePending
         );
 
-        payment.payment_id = payment_id;
-        payment.payment_bump = ctx.bumps.payment;
-        payment.payer = ctx.accounts.payer.key();
-        payment.payer_wallet = ctx.accounts.payer_wallet.key();
-        payment.amount = amount;
-        payment.currency = currency;
-        payment.payment_status = PaymentStatus::Verified;
-        payment.facilitator = ctx.accounts.facilitator.key();
-        payment.facilitator_signature = None; // Would be set by facilitator verification
-        payment.payment_tx_signature = None; // Would be set after on-chain settlement
-        payment.api_endpoint = api_endpoint;
-        payment.api_version = api_version;
-        payment.access_granted = true;
-        payment.access_expires_at = Some(clock.unix_timestamp + 3600); // 1 hour access
-        payment.requested_at = clock.unix_timestamp;
-        payment.verified_at = Some(clock.unix_timestamp);
-        payment.settled_at = None; // Set after settlement
+        let owner = position.owner;
+        let bump_seed = [position.position_bump];
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"liquidity_position",
+            owner.as_ref(),
+            &[position_index],
+            &bump_seed,
+        ]];
+
+        // Pull liquidity and fees out of the DEX into the vaults
+        let mut fees_a = position.total_fees_earned_a;
+        let mut fees_b = position.total_fees_earned_b;
+        if position.position_nft.is_some() {
+            let cpi = dex::PositionCpi {
+                position: position.to_account_info(),
+                token_a_vault: ctx.accounts.token_a_vault.to_account_info(),
+                token_b_vault: ctx.accounts.token_b_vault.to_account_info(),
+                token_a_mint: ctx.accounts.token_a_mint.to_account_info(),
+                token_b_mint: ctx.accounts.token_b_mint.to_account_info(),
+                token_program: ctx.accounts.token_program.to_account_info(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+                payer: None,
+                signer_seeds,
+            };
+            let adapter = dex::load(ctx.remaining_accounts, position, false)?;
+            let (collected_a, collected_b) = dex::withdraw_all(adapter.as_ref(), &cpi)?;
+            fees_a = fees_a
+                .checked_add(collected_a)
+                .ok_or(XLiquidityEngineError::MathOverflow)?;
+            fees_b = fees_b
+                .checked_add(collected_b)
+                .ok_or(XLiquidityEngineError::MathOverflow)?;
+            ctx.accounts.token_a_vault.reload()?;
+            ctx.accounts.token_b_vault.reload()?;
+        }
+
+        let amount_a = ctx.accounts.token_a_vault.amount;
+        let amount_b = ctx.accounts.token_b_vault.amount;
+        for (amount, from, to, mint) in [
+            (
+                amount_a,
+                &ctx.accounts.token_a_vault,
+                &ctx.accounts.owner_token_a,
+                &ctx.accounts.token_a_mint,
+            ),
+            (
+                amount_b,
+                &ctx.accounts.token_b_vault,
+                &ctx.accounts.owner_token_b,
+                &ctx.accounts.token_b_mint,
+            ),
+        ] {
+            if amount > 0 {
+                token::transfer_checked(
+                    CpiContext::new_with_signer(
+                        ctx.accounts.token_program.to_account_info(),
+                        TransferChecked {
+                            from: from.to_account_info(),
+                            mint: mint.to_account_info(),
+                            to: to.to_account_info(),
+                            authority: position.to_account_info(),
+                        },
+                        signer_seeds,
+                    ),
+                    amount,
+                    mint.decimals,
+                )?;
+            }
+            if close_account {
+                token::close_account(CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    CloseAccount {
+                        account: from.to_account_info(),
+                        destination: ctx.accounts.owner.to_account_info(),
+                        authority: position.to_account_info(),
+                    },
+                    signer_seeds,
+                ))?;
+            }
+        }
+
+        let position = &mut ctx.accounts.position;
+        position.liquidity_amount = 0;
+        position.total_fees_earned_a = 0;
+        position.total_fees_earned_b = 0;
+        position.total_value_locked = 0;
+        position.deployed_amount_a = 0;
+        position.deployed_amount_b = 0;
+        position.net_deposited_a = 0;
+        position.net_deposited_b = 0;
+        position.position_nft = None;
+        position.auto_rebalance_enabled = false;
+        position.status = PositionStatus::Closed;
+        position.updated_at = clock.unix_timestamp;
 
         // Create audit log
+        let event_data = (amount_a, amount_b, fees_a, fees_b, close_account).try_to_vec()?;
         create_audit_log_internal(
-            &ctx.accounts.audit_log,
-            AuditEventType::PaymentReceived,
-            None,
-            ctx.accounts.payer.key(),
-            &[],
-            clock,
+            &mut ctx.accounts.audit_stream,
+            ctx.bumps.audit_stream,
+            &mut ctx.accounts.audit_log,
+            ctx.bumps.audit_log,
+            position.key(),
+            AuditEventType::PositionClosed,
+            Some(position.key()),
+            owner,
+            &event_data,
+            &clock,
         )?;
 
-        msg!("x402 payment verified: {} for endpoint: {}", amount, payment.api_endpoint);
+        msg!(
+            "Liquidity position closed: {}, returned {} token A, {} token B",
+            position.key(),
+            amount_a,
+            amount_b
+        );
+
+        if close_account {
+            ctx.accounts
+                .position
+                .close(ctx.accounts.owner.to_account_info())?;
+        }
         Ok(())
     }
 
-    /// Collect fees from a liquidity position
-    pub fn collect_fees(
-        ctx: Context<CollectFees>,
-        _position_index: u8,
-    ) -> Result<()> {
+    /// Pause a liquidity position (owner only)
+    ///
+    /// While paused, rebalance decisions, execution, fee collection and
+    /// deposits are rejected; withdrawals still work.
+    pub fn pause_position(ctx: Context<PausePosition>, _position_index: u8) -> Result<()> {
         let position = &mut ctx.accounts.position;
         let clock = Clock::get()?;
 
-        // Validate position is active
         require!(
             position.status == PositionStatus::Active,
             XLiquidityEngineError::PositionNotActive
         );
 
-        // Check if there are fees to collect
-        require!(
-            position.total_fees_earned_a > 0 || position.total_fees_earned_b > 0,
-            XLiquidityEngineError::NoFeesToCollect
-        );
+        position.status = PositionStatus::Paused;
+        position.updated_at = clock.unix_timestamp;
 
-        // Calculate protocol fees
-        let config = &ctx.accounts.config;
-        let _protocol_fee_a = (position.total_fees_earned_a as u128)
-            .checked_mul(config.protocol_fee_bps as u128)
-            .unwrap()
-            .checked_div(10000)
-            .unwrap() as u64;
-        let _protocol_fee_b = (position.total_fees_earned_b as u128)
-            .checked_mul(config.protocol_fee_bps as u128)
-            .unwrap()
-            .checked_div(10000)
-            .unwrap() as u64;
+        // Create audit log
+        let event_data = false.try_to_vec()?;
+        create_audit_log_internal(
+            &mut ctx.accounts.audit_stream,
+            ctx.bumps.audit_stream,
+            &mut ctx.accounts.audit_log,
+            ctx.bumps.audit_log,
+            position.key(),
+            AuditEventType::PositionPaused,
+            Some(position.key()),
+            ctx.accounts.owner.key(),
+            &event_data,
+            &clock,
+        )?;
 
-        // Note: Actual token transfer would happen via CPI to token program
-        // This is just updating the accounting
-        // Protocol fees would be sent to config.fee_recipient in production
+        msg!("Liquidity position paused: {}", position.key());
+        Ok(())
+    }
 
-        // Reset fee counters (fees would be transferred off-chain)
-        let fees_collected_a = position.total_fees_earned_a;
-        let fees_collected_b = position.total_fees_earned_b;
-        position.total_fees_earned_a = 0;
-        position.total_fees_earned_b = 0;
+    /// Resume a paused liquidity position (owner only)
+    ///
+    /// Fails while an emergency pause by the authority is in effect.
+    pub fn resume_position(ctx: Context<PausePosition>, _position_index: u8) -> Result<()> {
+        let position = &mut ctx.accounts.position;
+        let clock = Clock::get()?;
+
+        require!(
+            position.status == PositionStatus::Paused,
+            XLiquidityEngineError::PositionNotPaused
+        );
+        require!(
+            !position.emergency_paused,
+            XLiquidityEngineError::EmergencyPauseActive
+        );
+
+        position.status = PositionStatus::Active;
         position.updated_at = clock.unix_timestamp;
 
         // Create audit log
-        let event_data = format!(
-            "Fees collected: {} token A, {} token B",
-            fees_collected_a, fees_collected_b
-        );
+        let event_data = false.try_to_vec()?;
         create_audit_log_internal(
-            &ctx.accounts.audit_log,
-            AuditEventType::FeesCollected,
+            &mut ctx.accounts.audit_stream,
+            ctx.bumps.audit_stream,
+            &mut ctx.accounts.audit_log,
+            ctx.bumps.audit_log,
+            position.key(),
+            AuditEventType::PositionResumed,
             Some(position.key()),
-            position.owner,
-            event_data.as_bytes(),
-            clock,
+            ctx.accounts.owner.key(),
+            &event_data,
+            &clock,
         )?;
 
-        msg!(
-            "Fees collected from position {}: {} token A, {} token B",
-            position.key(),
-            fees_collected_a,
-            fees_collected_b
-        );
+        msg!("Liquidity position resumed: {}", position.key());
         Ok(())
     }
 
-    /// Approve a rebalancing decision (human oversight)
-    pub fn approve_rebalance(
-        ctx: Context<ApproveRebalance>,
-        _decision_index: u32,
+    /// Emergency-pause any liquidity position (authority only)
+    ///
+    /// Also applies to positions the owner already paused. Only the authority
+    /// can lift an emergency pause.
+    pub fn emergency_pause_position(
+        ctx: Context<EmergencyPausePosition>,
+        _position_index: u8,
     ) -> Result<()> {
-        let decision = &mut ctx.accounts.decision;
+        let position = &mut ctx.accounts.position;
         let clock = Clock::get()?;
 
         require!(
-            decision.requires_human_approval,
-            XLiquidityEngineError::ApprovalNotRequired
+            position.status == PositionStatus::Active || position.status == PositionStatus::Paused,
+            XLiquidityEngineError::PositionNotActive
         );
         require!(
-            decision.execution_status == ExecutionStatus::Pending,
-            XLiquidityEngineError::InvalidExecutionStatus
+            !position.emergency_paused,
+            XLiquidityEngineError::EmergencyPauseActive
         );
 
-        decision.human_approver = Some(ctx.accounts.approver.key());
-        decision.approval_timestamp = Some(clock.unix_timestamp);
+        position.status = PositionStatus::Paused;
+        position.emergency_paused = true;
+        position.updated_at = clock.unix_timestamp;
 
         // Create audit log
+        let event_data = true.try_to_vec()?;
         create_audit_log_internal(
-            &ctx.accounts.audit_log,
-            AuditEventType::HumanApprovalGranted,
-            Some(decision.position),
-            ctx.accounts.approver.key(),
-            &[],
-            clock,
+            &mut ctx.accounts.audit_stream,
+            ctx.bumps.audit_stream,
+            &mut ctx.accounts.audit_log,
+            ctx.bumps.audit_log,
+            position.key(),
+            AuditEventType::PositionPaused,
+            Some(position.key()),
+            ctx.accounts.authority.key(),
+            &event_data,
+            &clock,
         )?;
 
-        msg!("Rebalance decision approved by: {}", ctx.accounts.approver.key());
+        msg!("Liquidity position emergency-paused: {}", position.key());
         Ok(())
     }
-}
 
-// ============================================================================
-// HELPER FUNCTIONS
-// ============================================================================
+    /// Lift an emergency pause and resume the position (authority only)
+    pub fn emergency_resume_position(
+        ctx: Context<EmergencyPausePosition>,
+        _position_index: u8,
+    ) -> Result<()> {
+        let position = &mut ctx.accounts.position;
+        let clock = Clock::get()?;
 
-/// Assess risk level based on prediction metrics
-fn assess_risk(
-    confidence: u16,
-    sentiment: i16,
-    volatility: u16,
-) -> RiskLevel {
-    // Simple risk assessment logic
-    // In production, this would be more sophisticated
-    if confidence < 5000 || volatility > 8000 {
-        RiskLevel::Critical
-    } else if confidence < 7000 || volatility > 6000 || sentiment < -5000 {
-        RiskLevel::High
-    } else if confidence < 8500 || volatility > 4000 {
-        RiskLevel::Medium
-    } else {
-        RiskLevel::Low
-    }
-}
+        require!(
+            position.status == PositionStatus::Paused && position.emergency_paused,
+            XLiquidityEngineError::PositionNotPaused
+        );
 
-/// Create an audit log entry (internal helper)
-fn create_audit_log_internal(
-    _audit_log_account: &AccountInfo,
-    event_type: AuditEventType,
-    position: Option<Pubkey>,
-    user: Pubkey,
-    _event_data: &[u8],
-    _clock: Clock,
-) -> Result<()> {
-    // Note: This is a simplified version
-    // In a real implementation, you would initialize the audit log account here
-    // For now, we'll just log the event
-    msg!(
-        "Audit log: {:?} for user: {}, position: {:?}",
-        event_type,
-        user,
-        position
-    );
-    Ok(())
-}
+        position.status = PositionStatus::Active;
+        position.emergency_paused = false;
+        position.updated_at = clock.unix_timestamp;
 
-// ============================================================================
-// ENUMS AND TYPES
-// ============================================================================
+        // Create audit log
+        let event_data = true.try_to_vec()?;
+        create_audit_log_internal(
+            &mut ctx.accounts.audit_stream,
+            ctx.bumps.audit_stream,
+            &mut ctx.accounts.audit_log,
+            ctx.bumps.audit_log,
+            position.key(),
+            AuditEventType::PositionResumed,
+            Some(position.key()),
+            ctx.accounts.authority.key(),
+            &event_data,
+            &clock,
+        )?;
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
-pub enum DexType {
-    Raydium,
-    Orca,
-    Meteora,
+        msg!("Liquidity position emergency pause lifted: {}", position.key());
+        Ok(())
+    }
+
+    /// Create a rebalancing decision based on AI prediction (position owner or
+    /// rebalance keeper)
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_rebalance_decision(
+        ctx: Context<CreateRebalanceDecision>,
+        _position_index: u8,
+        _decision_index: u32,
+        new_tick_lower: i32,
+        new_tick_upper: i32,
+        new_price_lower: u128,
+        new_price_upper: u128,
+        ai_model_version: String,
+        ai_model_hash: [u8; 32],
+        prediction_confidence: u16,
+        market_sentiment_score: i16,
+        volatility_metric: u16,
+        whale_activity_score: u16,
+        decision_reason: String,
+        new_bin_range: Option<BinRange>,
+        reference_sqrt_price_x64: u128,
+    ) -> Result<()> {
+        let decision = &mut ctx.accounts.decision;
+        let position = &mut ctx.accounts.position;
+        let config = &ctx.accounts.config;
+        let clock = Clock::get()?;
+
+        // Validate position is active
+        require!(
+            position.status == PositionStatus::Active,
+            XLiquidityEngineError::PositionNotActive
+        );
+
+        // Validate rebalance interval
+        require!(
+            clock.unix_timestamp - position.last_rebalance_timestamp >= position.min_rebalance_interval as i64,
+            XLiquidityEngineError::RebalanceTooFrequent
+        );
+
+        // Validate price range
+        require!(new_tick_lower < new_tick_upper, XLiquidityEngineError::InvalidPriceRange);
+        require!(new_price_lower < new_price_upper, XLiquidityEngineError::InvalidPriceRange);
+        BinRange::validate_for(position.dex, new_bin_range.as_ref())?;
+        require!(reference_sqrt_price_x64 > 0, XLiquidityEngineError::InvalidReferencePrice);
+
+        // Determine risk level and if human approval is needed
+        let risk_assessment = assess_risk(
+            prediction_confidence,
+            market_sentiment_score,
+            volatility_metric,
+        );
+        let requires_human_approval = risk_assessment == RiskLevel::Critical
+            || risk_assessment == RiskLevel::High
+            || position.total_value_locked >= config.require_human_approval_threshold;
+
+        decision.position = position.key();
+        decision.decision_bump = ctx.bumps.decision;
+        decision.new_tick_lower = new_tick_lower;
+        decision.new_tick_upper = new_tick_upper;
+        decision.new_price_lower = new_price_lower;
+        decision.new_price_upper = new_price_upper;
+        decision.new_bin_range = new_bin_range;
+        decision.ai_model_version = ai_model_version;
+        decision.ai_model_hash = ai_model_hash;
+        decision.prediction_confidence = prediction_confidence;
+        decision.market_sentiment_score = market_sentiment_score;
+        decision.volatility_metric = volatility_metric;
+        decision.whale_activity_score = whale_activity_score;
+        decision.on_chain_indicators = vec![];
+        decision.decision_reason = decision_reason;
+        decision.risk_assessment = risk_assessment;
+        decision.execution_status = ExecutionStatus::Pending;
+        decision.execution_tx_signature = None;
+        decision.execution_slippage = None;
+        decision.requires_human_approval = requires_human_approval;
+        decision.human_approver = None;
+        decision.approval_timestamp = None;
+        decision.created_at = clock.unix_timestamp;
+        decision.executed_at = None;
+        decision.layout_version = LAYOUT_VERSION;
+        decision.expires_at = clock
+            .unix_timestamp
+            .checked_add(REBALANCE_DECISION_TTL)
+            .ok_or(XLiquidityEngineError::MathOverflow)?;
+        decision.reference_sqrt_price_x64 = reference_sqrt_price_x64;
+
+        position.pending_decisions = position
+            .pending_decisions
+            .checked_add(1)
+            .ok_or(XLiquidityEngineError::MathOverflow)?;
+
+        msg!(
+            "Rebalance decision created for position: {}, requires approval: {}",
+            position.key(),
+            requires_human_approval
+        );
+        Ok(())
+    }
+
+    /// Execute a rebalancing decision (position owner or rebalance keeper)
+    ///
+    /// If the position holds liquidity, its owed fees are collected and the
+    /// liquidity is moved to the new range on the DEX via CPI with the position
+    /// PDA signing; collected fees are added to the fee counters. DEX accounts
+    /// are passed as remaining accounts (see `dex::raydium::RaydiumAccounts`,
+    /// `dex::whirlpool::WhirlpoolAccounts` and `dex::meteora::MeteoraAccounts`).
+    ///
+    /// With `swap`, the vaults are swapped between through the protocol's
+    /// `swap_program` after the liquidity is removed; the aggregator program
+    /// and route accounts follow the DEX accounts.
+    ///
+    /// Everything is priced at the decision's `reference_sqrt_price_x64`: the
+    /// pool price must be within `slippage_tolerance_bps` of it, the removal
+    /// and the swap may pay out at most that much less than it implies, and
+    /// the value lost on the way, stored as the decision's
+    /// `execution_slippage`, may not exceed it.
+    pub fn execute_rebalance<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ExecuteRebalance<'info>>,
+        position_index: u8,
+        _decision_index: u32,
+        slippage_tolerance_bps: u16,
+        swap: Option<SwapParams>,
+    ) -> Result<()> {
+        let decision = &mut ctx.accounts.decision;
+        let position = &mut ctx.accounts.position;
+        let clock = Clock::get()?;
+
+        // Validate position is active
+        require!(
+            position.status == PositionStatus::Active,
+            XLiquidityEngineError::PositionNotActive
+        );
+
+        // Validate decision status
+        require!(
+            decision.execution_status == ExecutionStatus::Pending,
+            XLiquidityEngineError::InvalidExecutionStatus
+        );
+        require!(
+            clock.unix_timestamp < decision.expires_at,
+            XLiquidityEngineError::DecisionExpired
+        );
+
+        // Check if human approval is required
+        if decision.requires_human_approval {
+            require!(
+                decision.human_approver.is_some(),
+                XLiquidityEngineError::HumanApprovalRequired
+            );
+            if let Some(approver) = &ctx.accounts.approver {
+                require!(
+                    decision.human_approver.unwrap() == approver.key(),
+                    XLiquidityEngineError::InvalidApprover
+                );
+            } else {
+                return Err(XLiquidityEngineError::HumanApprovalRequired.into());
+            }
+        }
+
+        // Validate slippage tolerance
+        let config = &ctx.accounts.config;
+        require!(
+            slippage_tolerance_bps <= config.default_slippage_tolerance_bps * 2,
+            XLiquidityEngineError::SlippageTooHigh
+        );
+
+        // Move liquidity on the DEX; an empty position only records the new range
+        let has_liquidity = position.position_nft.is_some()
+            || ctx.accounts.token_a_vault.amount > 0
+            || ctx.accounts.token_b_vault.amount > 0;
+        require!(
+            has_liquidity || swap.is_none(),
+            XLiquidityEngineError::InvalidSwap
+        );
+        if has_liquidity {
+            // Decisions migrated from layout v0 carry no reference price
+            require!(
+                decision.reference_sqrt_price_x64 > 0,
+                XLiquidityEngineError::InvalidReferencePrice
+            );
+            let owner = position.owner;
+            let bump_seed = [position.position_bump];
+            let signer_seeds: &[&[&[u8]]] = &[&[
+                b"liquidity_position",
+                owner.as_ref(),
+                &[position_index],
+                &bump_seed,
+            ]];
+            let cpi = dex::PositionCpi {
+                position: position.to_account_info(),
+                token_a_vault: ctx.accounts.token_a_vault.to_account_info(),
+                token_b_vault: ctx.accounts.token_b_vault.to_account_info(),
+                token_a_mint: ctx.accounts.token_a_mint.to_account_info(),
+                token_b_mint: ctx.accounts.token_b_mint.to_account_info(),
+                token_program: ctx.accounts.token_program.to_account_info(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+                payer: Some(ctx.accounts.payer.to_account_info()),
+                signer_seeds,
+            };
+            let range_changed = decision.new_tick_lower != position.current_tick_lower
+                || decision.new_tick_upper != position.current_tick_upper
+                || decision.new_bin_range != position.bin_range;
+            let opens_new = position.position_nft.is_none() || range_changed;
+            let swap_accounts_len = swap.as_ref().map_or(0, |swap| swap.accounts_len as usize);
+            let dex_accounts_len = ctx
+                .remaining_accounts
+                .len()
+                .checked_sub(swap_accounts_len)
+                .ok_or(XLiquidityEngineError::InvalidSwap)?;
+            let (dex_accounts, swap_accounts) = ctx.remaining_accounts.split_at(dex_accounts_len);
+            let swap = swap
+                .as_ref()
+                .map(|params| {
+                    dex::swap::Swap::new(
+                        swap_accounts,
+                        params,
+                        config.swap_program,
+                        position.position_nft,
+                        position.max_single_trade,
+                        slippage_tolerance_bps,
+                        decision.reference_sqrt_price_x64,
+                    )
+                })
+                .transpose()?;
+            let adapter = dex::load(dex_accounts, position, opens_new)?;
+            let result = dex::rebalance(
+                adapter.as_ref(),
+                &cpi,
+                &dex::TargetRange {
+                    tick_lower: decision.new_tick_lower,
+                    tick_upper: decision.new_tick_upper,
+                    bin_range: decision.new_bin_range,
+                },
+                swap.as_ref(),
+                decision.reference_sqrt_price_x64,
+                slippage_tolerance_bps,
+            )?;
+            if let Some(swapped) = &result.swapped {
+                msg!("Swapped {} in for {} out", swapped.amount_in, swapped.amount_out);
+            }
+            require!(
+                result.slippage_bps <= slippage_tolerance_bps,
+                XLiquidityEngineError::SlippageExceeded
+            );
+            decision.execution_slippage = Some(result.slippage_bps);
+            position.position_nft = result.position;
+            position.deployed_amount_a = result.deployed_a;
+            position.deployed_amount_b = result.deployed_b;
+            position.liquidity_amount = result.liquidity.unwrap_or(0);
+            position.total_fees_earned_a = position
+                .total_fees_earned_a
+                .checked_add(result.fees_a)
+                .ok_or(XLiquidityEngineError::MathOverflow)?;
+            position.total_fees_earned_b = position
+                .total_fees_earned_b
+                .checked_add(result.fees_b)
+                .ok_or(XLiquidityEngineError::MathOverflow)?;
+
+            ctx.accounts.token_a_vault.reload()?;
+            ctx.accounts.token_b_vault.reload()?;
+            position.value_sqrt_price_x64 = decision.reference_sqrt_price_x64;
+            position.refresh_value_locked(&ctx.accounts.token_a_vault, &ctx.accounts.token_b_vault)?;
+        }
+
+        // Update position with new range
+        position.current_tick_lower = decision.new_tick_lower;
+        position.current_tick_upper = decision.new_tick_upper;
+        position.current_price_lower = decision.new_price_lower;
+        position.current_price_upper = decision.new_price_upper;
+        position.bin_range = decision.new_bin_range;
+        position.last_rebalance_slot = clock.slot;
+        position.last_rebalance_timestamp = clock.unix_timestamp;
+        position.rebalance_count = position.rebalance_count.checked_add(1).unwrap();
+        position.pending_decisions = position.pending_decisions.saturating_sub(1);
+        position.updated_at = clock.unix_timestamp;
+
+        // Update decision status
+        decision.execution_status = ExecutionStatus::Executed;
+        decision.executed_at = Some(clock.unix_timestamp);
+        // Note: execution_tx_signature would be set by off-chain service
+
+        // Create audit log
+        let event_data = format!(
+            "Rebalanced position {}: ticks [{}, {}], prices [{}, {}]",
+            position.key(),
+            decision.new_tick_lower,
+            decision.new_tick_upper,
+            decision.new_price_lower,
+            decision.new_price_upper
+        );
+        create_audit_log_internal(
+            &mut ctx.accounts.audit_stream,
+            ctx.bumps.audit_stream,
+            &mut ctx.accounts.audit_log,
+            ctx.bumps.audit_log,
+            position.key(),
+            AuditEventType::Rebalanced,
+            Some(position.key()),
+            position.owner,
+            event_data.as_bytes(),
+            &clock,
+        )?;
+
+        msg!("Rebalance executed for position: {}", position.key());
+        Ok(())
+    }
+
+    /// Price an x402 API endpoint/version (authority only)
+    pub fn create_x402_endpoint_pricing(
+        ctx: Context<CreateX402EndpointPricing>,
+        api_endpoint: String,
+        api_version: String,
+        params: X402PricingParams,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+
+        require!(
+            !api_endpoint.is_empty() && api_endpoint.len() <= X402EndpointPricing::MAX_ENDPOINT_LEN,
+            XLiquidityEngineError::InvalidConfigValue
+        );
+        require!(
+            api_version.len() <= X402EndpointPricing::MAX_VERSION_LEN,
+            XLiquidityEngineError::InvalidConfigValue
+        );
+        params.validate()?;
+
+        let pricing = &mut ctx.accounts.pricing;
+        pricing.endpoint_hash = x402_endpoint_hash(&api_endpoint, &api_version);
+        pricing.pricing_bump = ctx.bumps.pricing;
+        pricing.api_endpoint = api_endpoint;
+        pricing.api_version = api_version;
+        pricing.apply(&params);
+        pricing.created_at = clock.unix_timestamp;
+        pricing.updated_at = clock.unix_timestamp;
+
+        // Create audit log
+        let mut event_data = pricing.endpoint_hash.to_vec();
+        params.serialize(&mut event_data)?;
+        create_audit_log_internal(
+            &mut ctx.accounts.audit_stream,
+            ctx.bumps.audit_stream,
+            &mut ctx.accounts.audit_log,
+            ctx.bumps.audit_log,
+            ctx.accounts.pricing.key(),
+            AuditEventType::EndpointPricingUpdated,
+            None,
+            ctx.accounts.authority.key(),
+            &event_data,
+            &clock,
+        )?;
+
+        msg!("x402 endpoint priced: {}", ctx.accounts.pricing.api_endpoint);
+        Ok(())
+    }
+
+    /// Update the price and access duration of an x402 endpoint (authority only)
+    pub fn update_x402_endpoint_pricing(
+        ctx: Context<UpdateX402EndpointPricing>,
+        params: X402PricingParams,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+
+        params.validate()?;
+
+        let pricing = &mut ctx.accounts.pricing;
+        pricing.apply(&params);
+        pricing.updated_at = clock.unix_timestamp;
+
+        // Create audit log
+        let mut event_data = pricing.endpoint_hash.to_vec();
+        params.serialize(&mut event_data)?;
+        create_audit_log_internal(
+            &mut ctx.accounts.audit_stream,
+            ctx.bumps.audit_stream,
+            &mut ctx.accounts.audit_log,
+            ctx.bumps.audit_log,
+            ctx.accounts.pricing.key(),
+            AuditEventType::EndpointPricingUpdated,
+            None,
+            ctx.accounts.authority.key(),
+            &event_data,
+            &clock,
+        )?;
+
+        msg!("x402 endpoint pricing updated: {}", ctx.accounts.pricing.api_endpoint);
+        Ok(())
+    }
+
+    /// Verify x402 payment and grant API access
+    ///
+    /// The transaction must include, immediately before this instruction, an
+    /// ed25519 program instruction in which the facilitator signs the message
+    /// built by `x402_payment_message`.
+    pub fn verify_x402_payment(
+        ctx: Context<VerifyX402Payment>,
+        payment_id: [u8; 32],
+        amount: u64,
+        currency: PaymentCurrency,
+        api_endpoint: String,
+        api_version: String,
+        signature_expires_at: i64,
+    ) -> Result<()> {
+        let payment = &mut ctx.accounts.payment;
+        let config = &ctx.accounts.config;
+        let clock = Clock::get()?;
+
+        // Validate minimum payment and the endpoint's price for this currency
+        require!(
+            amount >= config.x402_min_payment,
+            XLiquidityEngineError::PaymentTooSmall
+        );
+        let pricing = &ctx.accounts.pricing;
+        require!(pricing.enabled, XLiquidityEngineError::EndpointNotPriced);
+        let price = pricing
+            .price_for(currency)
+            .ok_or(XLiquidityEngineError::UnsupportedCurrency)?;
+        require!(amount >= price, XLiquidityEngineError::PaymentTooSmall);
+
+        // Validate facilitator against the registry
+        let facilitator = config
+            .find_active_facilitator(&ctx.accounts.facilitator.key(), clock.unix_timestamp)
+            .ok_or(XLiquidityEngineError::InvalidFacilitator)?;
+        require!(
+            facilitator.accepted_currencies.contains(&currency),
+            XLiquidityEngineError::UnsupportedCurrency
+        );
+        if let Some(min_payment) = facilitator.min_payment {
+            require!(amount >= min_payment, XLiquidityEngineError::PaymentTooSmall);
+        }
+
+        // Validate the facilitator's signature over the canonical payment message
+        require!(
+            clock.unix_timestamp <= signature_expires_at,
+            XLiquidityEngineError::PaymentAuthorizationExpired
+        );
+        let message = x402_payment_message(
+            &payment_id,
+            &ctx.accounts.payer.key(),
+            amount,
+            currency,
+            &api_endpoint,
+            &api_version,
+            signature_expires_at,
+        );
+        let facilitator_signature = verify_ed25519_signature(
+            &ctx.accounts.instructions_sysvar,
+            &ctx.accounts.facilitator.key(),
+            &message,
+        )?;
+
+        payment.payment_id = payment_id;
+        payment.payment_bump = ctx.bumps.payment;
+        payment.payer = ctx.accounts.payer.key();
+        payment.payer_wallet = ctx.accounts.payer_wallet.key();
+        payment.amount = amount;
+        payment.currency = currency;
+        payment.payment_status = PaymentStatus::Verified;
+        payment.facilitator = ctx.accounts.facilitator.key();
+        payment.facilitator_signature = Some(facilitator_signature);
+        payment.payment_tx_signature = None; // Would be set after on-chain settlement
+        payment.api_endpoint = api_endpoint;
+        payment.api_version = api_version;
+        payment.access_granted = true;
+        payment.access_expires_at = Some(
+            clock
+                .unix_timestamp
+                .checked_add(pricing.access_duration as i64)
+                .ok_or(XLiquidityEngineError::MathOverflow)?,
+        );
+        payment.requested_at = clock.unix_timestamp;
+        payment.verified_at = Some(clock.unix_timestamp);
+        payment.settled_at = None; // Set after settlement
+
+        // Create audit log
+        create_audit_log_internal(
+            &mut ctx.accounts.audit_stream,
+            ctx.bumps.audit_stream,
+            &mut ctx.accounts.audit_log,
+            ctx.bumps.audit_log,
+            payment.key(),
+            AuditEventType::PaymentReceived,
+            None,
+            ctx.accounts.payer.key(),
+            &payment_id,
+            &clock,
+        )?;
+
+        msg!("x402 payment verified: {} for endpoint: {}", amount, payment.api_endpoint);
+        Ok(())
+    }
+
+    /// Settle a verified x402 payment into the currency's revenue vault
+    ///
+    /// SOL is transferred through the system program; USDC/USDT through SPL Token
+    /// using the mints configured in `ProtocolConfig`.
+    pub fn settle_x402_payment(ctx: Context<SettleX402Payment>) -> Result<()> {
+        let payment = &ctx.accounts.payment;
+        let config = &ctx.accounts.config;
+        let clock = Clock::get()?;
+
+        require!(
+            payment.payment_status == PaymentStatus::Verified,
+            XLiquidityEngineError::InvalidPaymentStatus
+        );
+        check_token_vault(
+            ctx.accounts.revenue_vault.token_vault,
+            ctx.accounts.revenue_token_vault.as_ref(),
+        )?;
+
+        let amount = payment.amount;
+        transfer_x402_funds(
+            config,
+            payment.currency,
+            amount,
+            &ctx.accounts.payer_wallet.to_account_info(),
+            &ctx.accounts.revenue_vault.to_account_info(),
+            X402TokenAccounts {
+                mint: ctx.accounts.mint.as_ref(),
+                from: ctx.accounts.payer_token_account.as_ref(),
+                to: ctx.accounts.revenue_token_vault.as_ref(),
+                token_program: ctx.accounts.token_program.as_ref(),
+            },
+            &ctx.accounts.system_program,
+            &[],
+        )?;
+
+        let revenue_vault = &mut ctx.accounts.revenue_vault;
+        revenue_vault.accrue(amount, &ctx.accounts.config)?;
+        revenue_vault.updated_at = clock.unix_timestamp;
+
+        let payment = &mut ctx.accounts.payment;
+        payment.payment_status = PaymentStatus::Settled;
+        payment.settled_at = Some(clock.unix_timestamp);
+        let payment_id = payment.payment_id;
+
+        // Create audit log
+        create_audit_log_internal(
+            &mut ctx.accounts.audit_stream,
+            ctx.bumps.audit_stream,
+            &mut ctx.accounts.audit_log,
+            ctx.bumps.audit_log,
+            ctx.accounts.payment.key(),
+            AuditEventType::PaymentSettled,
+            None,
+            ctx.accounts.payer_wallet.key(),
+            &payment_id,
+            &clock,
+        )?;
+
+        msg!("x402 payment settled: {}", amount);
+        Ok(())
+    }
+
+    /// Refund an x402 payment (the payment's facilitator while it is still
+    /// registered and active, or the protocol authority)
+    ///
+    /// Settled funds are returned to the payer wallet out of the treasury's
+    /// share: from the revenue vault as far as the treasury's accrued balance
+    /// covers it, and the rest from the co-signing fee recipient once the
+    /// treasury has already claimed. Verified-but-unsettled payments only have
+    /// their access revoked.
+    pub fn refund_x402_payment(ctx: Context<RefundX402Payment>) -> Result<()> {
+        let payment = &ctx.accounts.payment;
+        let config = &ctx.accounts.config;
+        let clock = Clock::get()?;
+
+        let refund_authority = ctx.accounts.refund_authority.key();
+        let active_facilitator = refund_authority == payment.facilitator
+            && config
+                .find_active_facilitator(&refund_authority, clock.unix_timestamp)
+                .is_some();
+        require!(
+            refund_authority == config.authority || active_facilitator,
+            XLiquidityEngineError::Unauthorized
+        );
+
+        let amount = payment.amount;
+        match payment.payment_status {
+            PaymentStatus::Verified => {}
+            PaymentStatus::Settled => {
+                let revenue_vault = ctx
+                    .accounts
+                    .revenue_vault
+                    .as_mut()
+                    .ok_or(XLiquidityEngineError::MissingRefundSource)?;
+                require!(
+                    revenue_vault.currency == payment.currency,
+                    XLiquidityEngineError::MissingRefundSource
+                );
+                check_token_vault(
+                    revenue_vault.token_vault,
+                    ctx.accounts.revenue_token_vault.as_ref(),
+                )?;
+                let from_vault = amount.min(revenue_vault.treasury_accrued);
+                let shortfall = amount - from_vault;
+                revenue_vault.treasury_accrued -= from_vault;
+                revenue_vault.total_refunded = revenue_vault
+                    .total_refunded
+                    .checked_add(amount)
+                    .ok_or(XLiquidityEngineError::MathOverflow)?;
+                revenue_vault.updated_at = clock.unix_timestamp;
+
+                let currency_seed = [revenue_vault.currency as u8];
+                let bump_seed = [revenue_vault.vault_bump];
+                let signer_seeds: &[&[&[u8]]] = &[&[b"x402_revenue", &currency_seed, &bump_seed]];
+                if from_vault > 0 {
+                    withdraw_x402_funds(
+                        config,
+                        payment.currency,
+                        from_vault,
+                        &revenue_vault.to_account_info(),
+                        &ctx.accounts.payer_wallet.to_account_info(),
+                        X402TokenAccounts {
+                            mint: ctx.accounts.mint.as_ref(),
+                            from: ctx.accounts.revenue_token_vault.as_ref(),
+                            to: ctx.accounts.payer_token_account.as_ref(),
+                            token_program: ctx.accounts.token_program.as_ref(),
+                        },
+                        &ctx.accounts.system_program,
+                        signer_seeds,
+                    )?;
+                }
+
+                // The treasury already claimed the rest; the fee recipient pays it back
+                if shortfall > 0 {
+                    let treasury = ctx
+                        .accounts
+                        .treasury
+                        .as_ref()
+                        .ok_or(XLiquidityEngineError::InsufficientRevenue)?;
+                    transfer_x402_funds(
+                        config,
+                        payment.currency,
+                        shortfall,
+                        &treasury.to_account_info(),
+                        &ctx.accounts.payer_wallet.to_account_info(),
+                        X402TokenAccounts {
+                            mint: ctx.accounts.mint.as_ref(),
+                            from: ctx.accounts.treasury_token_account.as_ref(),
+                            to: ctx.accounts.payer_token_account.as_ref(),
+                            token_program: ctx.accounts.token_program.as_ref(),
+                        },
+                        &ctx.accounts.system_program,
+                        &[],
+                    )?;
+                }
+            }
+            _ => return Err(XLiquidityEngineError::InvalidPaymentStatus.into()),
+        }
+
+        let payment = &mut ctx.accounts.payment;
+        payment.payment_status = PaymentStatus::Refunded;
+        payment.access_granted = false;
+        payment.access_expires_at = Some(clock.unix_timestamp);
+        let payment_id = payment.payment_id;
+
+        // Create audit log
+        create_audit_log_internal(
+            &mut ctx.accounts.audit_stream,
+            ctx.bumps.audit_stream,
+            &mut ctx.accounts.audit_log,
+            ctx.bumps.audit_log,
+            ctx.accounts.payment.key(),
+            AuditEventType::PaymentRefunded,
+            None,
+            refund_authority,
+            &payment_id,
+            &clock,
+        )?;
+
+        msg!("x402 payment refunded: {}", amount);
+        Ok(())
+    }
+
+    /// Open a prepaid x402 credit account for one payment currency
+    ///
+    /// SOL credit is held as lamports on the credit PDA itself; USDC/USDT credit
+    /// is held in `credit_vault`, a token account of the configured mint owned
+    /// by the credit PDA.
+    pub fn open_x402_credit_account(
+        ctx: Context<OpenX402CreditAccount>,
+        currency: PaymentCurrency,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        let clock = Clock::get()?;
+
+        let vault = x402_token_vault(
+            config,
+            currency,
+            ctx.accounts.credit_vault.as_ref(),
+            ctx.accounts.credit.key(),
+        )?;
+        if vault.is_some() {
+            config.track_token_vault(currency, true)?;
+        }
+
+        let credit = &mut ctx.accounts.credit;
+        credit.owner = ctx.accounts.owner.key();
+        credit.currency = currency;
+        credit.credit_bump = ctx.bumps.credit;
+        credit.vault = vault;
+        credit.balance = 0;
+        credit.total_deposited = 0;
+        credit.total_debited = 0;
+        credit.calls_debited = 0;
+        credit.last_debit_at = None;
+        credit.created_at = clock.unix_timestamp;
+        credit.updated_at = clock.unix_timestamp;
+
+        msg!("x402 credit account opened: {}", credit.key());
+        Ok(())
+    }
+
+    /// Top up a prepaid x402 credit account (owner only)
+    pub fn deposit_x402_credit(ctx: Context<DepositX402Credit>, amount: u64) -> Result<()> {
+        let credit = &ctx.accounts.credit;
+        let clock = Clock::get()?;
+
+        require!(amount > 0, XLiquidityEngineError::InvalidCreditAmount);
+        if let Some(vault) = credit.vault {
+            let credit_vault = ctx
+                .accounts
+                .credit_vault
+                .as_ref()
+                .ok_or(XLiquidityEngineError::MissingTokenAccounts)?;
+            require_keys_eq!(
+                credit_vault.key(),
+                vault,
+                XLiquidityEngineError::InvalidTokenAccount
+            );
+        }
+
+        transfer_x402_funds(
+            &ctx.accounts.config,
+            credit.currency,
+            amount,
+            &ctx.accounts.owner.to_account_info(),
+            &credit.to_account_info(),
+            X402TokenAccounts {
+                mint: ctx.accounts.mint.as_ref(),
+                from: ctx.accounts.owner_token_account.as_ref(),
+                to: ctx.accounts.credit_vault.as_ref(),
+                token_program: ctx.accounts.token_program.as_ref(),
+            },
+            &ctx.accounts.system_program,
+            &[],
+        )?;
+
+        let credit = &mut ctx.accounts.credit;
+        credit.balance = credit
+            .balance
+            .checked_add(amount)
+            .ok_or(XLiquidityEngineError::MathOverflow)?;
+        credit.total_deposited = credit
+            .total_deposited
+            .checked_add(amount)
+            .ok_or(XLiquidityEngineError::MathOverflow)?;
+        credit.updated_at = clock.unix_timestamp;
+
+        // Create audit log
+        let event_data = (credit.key(), amount, credit.balance).try_to_vec()?;
+        create_audit_log_internal(
+            &mut ctx.accounts.audit_stream,
+            ctx.bumps.audit_stream,
+            &mut ctx.accounts.audit_log,
+            ctx.bumps.audit_log,
+            ctx.accounts.credit.key(),
+            AuditEventType::CreditDeposited,
+            None,
+            ctx.accounts.owner.key(),
+            &event_data,
+            &clock,
+        )?;
+
+        msg!("x402 credit deposited: {}", amount);
+        Ok(())
+    }
+
+    /// Debit a batch of metered x402 API calls from a prepaid credit account
+    ///
+    /// Signed by a registered facilitator. The charge is `call_count` times the
+    /// endpoint's price in the credit currency and is paid into the currency's
+    /// revenue vault.
+    pub fn debit_x402_credit(
+        ctx: Context<DebitX402Credit>,
+        call_count: u32,
+        api_endpoint: String,
+        api_version: String,
+    ) -> Result<()> {
+        let config = &ctx.accounts.config;
+        let credit = &ctx.accounts.credit;
+        let pricing = &ctx.accounts.pricing;
+        let clock = Clock::get()?;
+
+        require!(call_count > 0, XLiquidityEngineError::InvalidCreditAmount);
+
+        // Validate facilitator against the registry
+        let facilitator = config
+            .find_active_facilitator(&ctx.accounts.facilitator.key(), clock.unix_timestamp)
+            .ok_or(XLiquidityEngineError::InvalidFacilitator)?;
+        require!(
+            facilitator.accepted_currencies.contains(&credit.currency),
+            XLiquidityEngineError::UnsupportedCurrency
+        );
+
+        // Price the batch
+        require!(pricing.enabled, XLiquidityEngineError::EndpointNotPriced);
+        let price = pricing
+            .price_for(credit.currency)
+            .ok_or(XLiquidityEngineError::UnsupportedCurrency)?;
+        let amount = price
+            .checked_mul(call_count as u64)
+            .ok_or(XLiquidityEngineError::MathOverflow)?;
+        require!(amount <= credit.balance, XLiquidityEngineError::InsufficientCredit);
+
+        // Pay the revenue vault out of the credit balance
+        check_token_vault(credit.vault, ctx.accounts.credit_vault.as_ref())?;
+        check_token_vault(
+            ctx.accounts.revenue_vault.token_vault,
+            ctx.accounts.revenue_token_vault.as_ref(),
+        )?;
+        let owner = credit.owner;
+        let currency_seed = [credit.currency as u8];
+        let bump_seed = [credit.credit_bump];
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"x402_credit",
+            owner.as_ref(),
+            &currency_seed,
+            &bump_seed,
+        ]];
+        withdraw_x402_funds(
+            config,
+            credit.currency,
+            amount,
+            &credit.to_account_info(),
+            &ctx.accounts.revenue_vault.to_account_info(),
+            X402TokenAccounts {
+                mint: ctx.accounts.mint.as_ref(),
+                from: ctx.accounts.credit_vault.as_ref(),
+                to: ctx.accounts.revenue_token_vault.as_ref(),
+                token_program: ctx.accounts.token_program.as_ref(),
+            },
+            &ctx.accounts.system_program,
+            signer_seeds,
+        )?;
+
+        let revenue_vault = &mut ctx.accounts.revenue_vault;
+        revenue_vault.accrue(amount, &ctx.accounts.config)?;
+        revenue_vault.updated_at = clock.unix_timestamp;
+
+        let credit = &mut ctx.accounts.credit;
+        credit.balance -= amount;
+        credit.total_debited = credit
+            .total_debited
+            .checked_add(amount)
+            .ok_or(XLiquidityEngineError::MathOverflow)?;
+        credit.calls_debited = credit
+            .calls_debited
+            .checked_add(call_count as u64)
+            .ok_or(XLiquidityEngineError::MathOverflow)?;
+        credit.last_debit_at = Some(clock.unix_timestamp);
+        credit.updated_at = clock.unix_timestamp;
+
+        // Create audit log
+        let event_data = (
+            credit.key(),
+            ctx.accounts.pricing.endpoint_hash,
+            call_count,
+            amount,
+            credit.balance,
+        )
+            .try_to_vec()?;
+        let owner = credit.owner;
+        create_audit_log_internal(
+            &mut ctx.accounts.audit_stream,
+            ctx.bumps.audit_stream,
+            &mut ctx.accounts.audit_log,
+            ctx.bumps.audit_log,
+            ctx.accounts.credit.key(),
+            AuditEventType::CreditDebited,
+            None,
+            owner,
+            &event_data,
+            &clock,
+        )?;
+
+        msg!(
+            "x402 credit debited: {} calls to {} {} for {}",
+            call_count,
+            api_endpoint,
+            api_version,
+            amount
+        );
+        Ok(())
+    }
+
+    /// Withdraw unused prepaid balance from an x402 credit account (owner only)
+    ///
+    /// Not gated by the x402 pause so owners can always reclaim their funds.
+    pub fn withdraw_x402_credit(ctx: Context<WithdrawX402Credit>, amount: u64) -> Result<()> {
+        let credit = &ctx.accounts.credit;
+        let clock = Clock::get()?;
+
+        require!(amount > 0, XLiquidityEngineError::InvalidCreditAmount);
+        require!(amount <= credit.balance, XLiquidityEngineError::InsufficientCredit);
+        check_token_vault(credit.vault, ctx.accounts.credit_vault.as_ref())?;
+
+        let owner = credit.owner;
+        let currency_seed = [credit.currency as u8];
+        let bump_seed = [credit.credit_bump];
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"x402_credit",
+            owner.as_ref(),
+            &currency_seed,
+            &bump_seed,
+        ]];
+        withdraw_x402_funds(
+            &ctx.accounts.config,
+            credit.currency,
+            amount,
+            &credit.to_account_info(),
+            &ctx.accounts.owner.to_account_info(),
+            X402TokenAccounts {
+                mint: ctx.accounts.mint.as_ref(),
+                from: ctx.accounts.credit_vault.as_ref(),
+                to: ctx.accounts.owner_token_account.as_ref(),
+                token_program: ctx.accounts.token_program.as_ref(),
+            },
+            &ctx.accounts.system_program,
+            signer_seeds,
+        )?;
+
+        let credit = &mut ctx.accounts.credit;
+        credit.balance -= amount;
+        credit.updated_at = clock.unix_timestamp;
+
+        // Create audit log
+        let event_data = (credit.key(), amount, credit.balance).try_to_vec()?;
+        create_audit_log_internal(
+            &mut ctx.accounts.audit_stream,
+            ctx.bumps.audit_stream,
+            &mut ctx.accounts.audit_log,
+            ctx.bumps.audit_log,
+            ctx.accounts.credit.key(),
+            AuditEventType::CreditWithdrawn,
+            None,
+            owner,
+            &event_data,
+            &clock,
+        )?;
+
+        msg!("x402 credit withdrawn: {}", amount);
+        Ok(())
+    }
+
+    /// Close an x402 credit account and return everything it holds to the owner
+    ///
+    /// For USDC/USDT the whole token vault balance is paid out and the vault is
+    /// closed; the credit account's lamports, including any SOL balance, go back
+    /// to the owner with the account rent.
+    pub fn close_x402_credit_account(ctx: Context<CloseX402CreditAccount>) -> Result<()> {
+        let credit = &ctx.accounts.credit;
+        let clock = Clock::get()?;
+        let refunded = credit.balance;
+
+        if let Some(vault) = credit.vault {
+            let credit_vault = ctx
+                .accounts
+                .credit_vault
+                .as_ref()
+                .ok_or(XLiquidityEngineError::MissingTokenAccounts)?;
+            require_keys_eq!(
+                credit_vault.key(),
+                vault,
+                XLiquidityEngineError::InvalidTokenAccount
+            );
+            let token_program = ctx
+                .accounts
+                .token_program
+                .as_ref()
+                .ok_or(XLiquidityEngineError::MissingTokenAccounts)?;
+
+            let owner = credit.owner;
+            let currency_seed = [credit.currency as u8];
+            let bump_seed = [credit.credit_bump];
+            let signer_seeds: &[&[&[u8]]] = &[&[
+                b"x402_credit",
+                owner.as_ref(),
+                &currency_seed,
+                &bump_seed,
+            ]];
+            if credit_vault.amount > 0 {
+                withdraw_x402_funds(
+                    &ctx.accounts.config,
+                    credit.currency,
+                    credit_vault.amount,
+                    &credit.to_account_info(),
+                    &ctx.accounts.owner.to_account_info(),
+                    X402TokenAccounts {
+                        mint: ctx.accounts.mint.as_ref(),
+                        from: Some(credit_vault),
+                        to: ctx.accounts.owner_token_account.as_ref(),
+                        token_program: Some(token_program),
+                    },
+                    &ctx.accounts.system_program,
+                    signer_seeds,
+                )?;
+            }
+            token::close_account(CpiContext::new_with_signer(
+                token_program.to_account_info(),
+                CloseAccount {
+                    account: credit_vault.to_account_info(),
+                    destination: ctx.accounts.owner.to_account_info(),
+                    authority: credit.to_account_info(),
+                },
+                signer_seeds,
+            ))?;
+            ctx.accounts.config.track_token_vault(credit.currency, false)?;
+        }
+
+        // Create audit log
+        let event_data = (credit.key(), credit.currency, refunded).try_to_vec()?;
+        let owner = credit.owner;
+        create_audit_log_internal(
+            &mut ctx.accounts.audit_stream,
+            ctx.bumps.audit_stream,
+            &mut ctx.accounts.audit_log,
+            ctx.bumps.audit_log,
+            ctx.accounts.credit.key(),
+            AuditEventType::CreditAccountClosed,
+            None,
+            owner,
+            &event_data,
+            &clock,
+        )?;
+
+        msg!("x402 credit account closed: {}", ctx.accounts.credit.key());
+        Ok(())
+    }
+
+    /// Check whether `payer` currently has x402 access to an endpoint
+    ///
+    /// Read-only, intended for simulation by the API gateway. Access comes from
+    /// a settled, unexpired `payment` for the endpoint, or from a `credit`
+    /// account whose balance covers one call at the endpoint price. Fails with
+    /// `X402AccessExpired` when the payment's access window has passed and no
+    /// credit covers the call.
+    pub fn check_x402_access(
+        ctx: Context<CheckX402Access>,
+        payer: Pubkey,
+        api_endpoint: String,
+        api_version: String,
+    ) -> Result<X402AccessStatus> {
+        let pricing = &ctx.accounts.pricing;
+        let clock = Clock::get()?;
+
+        let mut payment_expired = false;
+        if let Some(payment) = &ctx.accounts.payment {
+            require_keys_eq!(payment.payer, payer, XLiquidityEngineError::InvalidAccessAccount);
+            require!(
+                payment.api_endpoint == api_endpoint && payment.api_version == api_version,
+                XLiquidityEngineError::InvalidAccessAccount
+            );
+            if payment.payment_status == PaymentStatus::Settled && payment.access_granted {
+                match payment.access_expires_at {
+                    Some(expires_at) if clock.unix_timestamp >= expires_at => {
+                        payment_expired = true;
+                    }
+                    access_expires_at => {
+                        return Ok(X402AccessStatus {
+                            has_access: true,
+                            access_expires_at,
+                            remaining_credit: None,
+                        });
+                    }
+                }
+            }
+        }
+
+        if let Some(credit) = &ctx.accounts.credit {
+            require_keys_eq!(credit.owner, payer, XLiquidityEngineError::InvalidAccessAccount);
+            let price = pricing
+                .price_for(credit.currency)
+                .filter(|_| pricing.enabled);
+            if price.is_some_and(|price| credit.balance >= price) {
+                return Ok(X402AccessStatus {
+                    has_access: true,
+                    access_expires_at: None,
+                    remaining_credit: Some(credit.balance),
+                });
+            }
+        }
+
+        require!(!payment_expired, XLiquidityEngineError::X402AccessExpired);
+        Ok(X402AccessStatus {
+            has_access: false,
+            access_expires_at: None,
+            remaining_credit: None,
+        })
+    }
+
+    /// Close an expired x402 payment account (permissionless)
+    ///
+    /// Only payments in a terminal status (settled, failed or refunded) whose
+    /// access window has passed can be closed. The closer is paid
+    /// `CLOSE_PAYMENT_REWARD_BPS` of the rent, the rest goes back to the
+    /// original payer, and a compact `PaymentClosed` audit event is emitted.
+    pub fn close_expired_payment(ctx: Context<CloseExpiredPayment>) -> Result<()> {
+        let payment = &ctx.accounts.payment;
+        let clock = Clock::get()?;
+
+        require!(
+            matches!(
+                payment.payment_status,
+                PaymentStatus::Settled | PaymentStatus::Failed | PaymentStatus::Refunded
+            ),
+            XLiquidityEngineError::InvalidPaymentStatus
+        );
+        require!(
+            payment
+                .access_expires_at
+                .is_some_and(|expires_at| clock.unix_timestamp >= expires_at),
+            XLiquidityEngineError::PaymentNotExpired
+        );
+
+        // Create audit log
+        let event_data = (
+            payment.payment_id,
+            payment.amount,
+            payment.currency,
+            payment.payment_status,
+        )
+            .try_to_vec()?;
+        let payer = payment.payer;
+        create_audit_log_internal(
+            &mut ctx.accounts.audit_stream,
+            ctx.bumps.audit_stream,
+            &mut ctx.accounts.audit_log,
+            ctx.bumps.audit_log,
+            ctx.accounts.payment.key(),
+            AuditEventType::PaymentClosed,
+            None,
+            payer,
+            &event_data,
+            &clock,
+        )?;
+
+        // Pay the closer's share here; `close = payer` sends the rest
+        let payment_info = ctx.accounts.payment.to_account_info();
+        let reward = bps_share(payment_info.lamports(), CLOSE_PAYMENT_REWARD_BPS)?;
+        payment_info.sub_lamports(reward)?;
+        ctx.accounts.closer.add_lamports(reward)?;
+
+        msg!("x402 payment closed: {}, closer reward: {}", payment_info.key(), reward);
+        Ok(())
+    }
+
+    /// Create the x402 revenue vault for one payment currency (authority only)
+    ///
+    /// SOL revenue is held as lamports on the vault PDA; USDC/USDT revenue is
+    /// held in `token_vault`, a token account of the configured mint owned by
+    /// the vault PDA.
+    pub fn initialize_x402_revenue_vault(
+        ctx: Context<InitializeX402RevenueVault>,
+        currency: PaymentCurrency,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+
+        let token_vault = x402_token_vault(
+            &ctx.accounts.config,
+            currency,
+            ctx.accounts.token_vault.as_ref(),
+            ctx.accounts.revenue_vault.key(),
+        )?;
+        if token_vault.is_some() {
+            ctx.accounts.config.track_token_vault(currency, true)?;
+        }
+
+        let revenue_vault = &mut ctx.accounts.revenue_vault;
+        revenue_vault.currency = currency;
+        revenue_vault.vault_bump = ctx.bumps.revenue_vault;
+        revenue_vault.token_vault = token_vault;
+        revenue_vault.treasury_accrued = 0;
+        revenue_vault.lp_pool_accrued = 0;
+        revenue_vault.provider_accruals = vec![];
+        revenue_vault.total_revenue = 0;
+        revenue_vault.total_claimed = 0;
+        revenue_vault.total_refunded = 0;
+        revenue_vault.created_at = clock.unix_timestamp;
+        revenue_vault.updated_at = clock.unix_timestamp;
+
+        msg!("x402 revenue vault initialized: {}", revenue_vault.key());
+        Ok(())
+    }
+
+    /// Claim accrued x402 revenue from a currency's revenue vault
+    ///
+    /// Pays the signer everything it is owed as protocol treasury (the fee
+    /// recipient) and/or registered provider. The LP share is moved to the LP
+    /// rewards pool by `distribute_x402_lp_rewards` instead.
+    pub fn claim_x402_revenue(ctx: Context<ClaimX402Revenue>) -> Result<()> {
+        let clock = Clock::get()?;
+        let claimant = ctx.accounts.claimant.key();
+
+        let amount = ctx
+            .accounts
+            .revenue_vault
+            .take_claim(&claimant, &ctx.accounts.config)?;
+        require!(amount > 0, XLiquidityEngineError::NothingToClaim);
+
+        let revenue_vault = &ctx.accounts.revenue_vault;
+        check_token_vault(
+            revenue_vault.token_vault,
+            ctx.accounts.revenue_token_vault.as_ref(),
+        )?;
+        let currency = revenue_vault.currency;
+        let currency_seed = [currency as u8];
+        let bump_seed = [revenue_vault.vault_bump];
+        let signer_seeds: &[&[&[u8]]] = &[&[b"x402_revenue", &currency_seed, &bump_seed]];
+        withdraw_x402_funds(
+            &ctx.accounts.config,
+            currency,
+            amount,
+            &revenue_vault.to_account_info(),
+            &ctx.accounts.claimant.to_account_info(),
+            X402TokenAccounts {
+                mint: ctx.accounts.mint.as_ref(),
+                from: ctx.accounts.revenue_token_vault.as_ref(),
+                to: ctx.accounts.claimant_token_account.as_ref(),
+                token_program: ctx.accounts.token_program.as_ref(),
+            },
+            &ctx.accounts.system_program,
+            signer_seeds,
+        )?;
+
+        let revenue_vault = &mut ctx.accounts.revenue_vault;
+        revenue_vault.total_claimed = revenue_vault
+            .total_claimed
+            .checked_add(amount)
+            .ok_or(XLiquidityEngineError::MathOverflow)?;
+        revenue_vault.updated_at = clock.unix_timestamp;
+
+        // Create audit log
+        let event_data = (currency, amount).try_to_vec()?;
+        create_audit_log_internal(
+            &mut ctx.accounts.audit_stream,
+            ctx.bumps.audit_stream,
+            &mut ctx.accounts.audit_log,
+            ctx.bumps.audit_log,
+            ctx.accounts.revenue_vault.key(),
+            AuditEventType::RevenueClaimed,
+            None,
+            claimant,
+            &event_data,
+            &clock,
+        )?;
+
+        msg!("x402 revenue claimed: {} by {}", amount, claimant);
+        Ok(())
+    }
+
+    /// Create the LP rewards pool for one payment currency (authority only)
+    ///
+    /// The pool PDA receives the LP share of x402 revenue. SOL is held as
+    /// lamports on the pool; USDC/USDT in `token_vault`, a token account of the
+    /// configured mint owned by the pool PDA.
+    pub fn initialize_x402_lp_rewards_pool(
+        ctx: Context<InitializeX402LpRewardsPool>,
+        currency: PaymentCurrency,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+
+        let token_vault = x402_token_vault(
+            &ctx.accounts.config,
+            currency,
+            ctx.accounts.token_vault.as_ref(),
+            ctx.accounts.lp_rewards_pool.key(),
+        )?;
+        if token_vault.is_some() {
+            ctx.accounts.config.track_token_vault(currency, true)?;
+        }
+
+        let lp_rewards_pool = &mut ctx.accounts.lp_rewards_pool;
+        lp_rewards_pool.currency = currency;
+        lp_rewards_pool.pool_bump = ctx.bumps.lp_rewards_pool;
+        lp_rewards_pool.token_vault = token_vault;
+        lp_rewards_pool.total_received = 0;
+        lp_rewards_pool.total_paid = 0;
+        lp_rewards_pool.created_at = clock.unix_timestamp;
+        lp_rewards_pool.updated_at = clock.unix_timestamp;
+
+        msg!("x402 LP rewards pool initialized: {}", lp_rewards_pool.key());
+        Ok(())
+    }
+
+    /// Move the LP share accrued in a revenue vault to the LP rewards pool
+    /// of the same currency (permissionless)
+    pub fn distribute_x402_lp_rewards(ctx: Context<DistributeX402LpRewards>) -> Result<()> {
+        let clock = Clock::get()?;
+
+        let revenue_vault = &ctx.accounts.revenue_vault;
+        let amount = revenue_vault.lp_pool_accrued;
+        require!(amount > 0, XLiquidityEngineError::NothingToClaim);
+        check_token_vault(
+            revenue_vault.token_vault,
+            ctx.accounts.revenue_token_vault.as_ref(),
+        )?;
+        check_token_vault(
+            ctx.accounts.lp_rewards_pool.token_vault,
+            ctx.accounts.pool_token_vault.as_ref(),
+        )?;
+        let currency = revenue_vault.currency;
+        let currency_seed = [currency as u8];
+        let bump_seed = [revenue_vault.vault_bump];
+        let signer_seeds: &[&[&[u8]]] = &[&[b"x402_revenue", &currency_seed, &bump_seed]];
+        withdraw_x402_funds(
+            &ctx.accounts.config,
+            currency,
+            amount,
+            &revenue_vault.to_account_info(),
+            &ctx.accounts.lp_rewards_pool.to_account_info(),
+            X402TokenAccounts {
+                mint: ctx.accounts.mint.as_ref(),
+                from: ctx.accounts.revenue_token_vault.as_ref(),
+                to: ctx.accounts.pool_token_vault.as_ref(),
+                token_program: ctx.accounts.token_program.as_ref(),
+            },
+            &ctx.accounts.system_program,
+            signer_seeds,
+        )?;
+
+        let revenue_vault = &mut ctx.accounts.revenue_vault;
+        revenue_vault.lp_pool_accrued = 0;
+        revenue_vault.total_claimed = revenue_vault
+            .total_claimed
+            .checked_add(amount)
+            .ok_or(XLiquidityEngineError::MathOverflow)?;
+        revenue_vault.updated_at = clock.unix_timestamp;
+
+        let lp_rewards_pool = &mut ctx.accounts.lp_rewards_pool;
+        lp_rewards_pool.total_received = lp_rewards_pool
+            .total_received
+            .checked_add(amount)
+            .ok_or(XLiquidityEngineError::MathOverflow)?;
+        lp_rewards_pool.updated_at = clock.unix_timestamp;
+
+        // Create audit log
+        let event_data = (currency, amount).try_to_vec()?;
+        create_audit_log_internal(
+            &mut ctx.accounts.audit_stream,
+            ctx.bumps.audit_stream,
+            &mut ctx.accounts.audit_log,
+            ctx.bumps.audit_log,
+            ctx.accounts.lp_rewards_pool.key(),
+            AuditEventType::LpRewardsDistributed,
+            None,
+            ctx.accounts.caller.key(),
+            &event_data,
+            &clock,
+        )?;
+
+        msg!("x402 LP rewards distributed: {}", amount);
+        Ok(())
+    }
+
+    /// Pay LP rewards out of an LP rewards pool to `recipient` (authority
+    /// only)
+    ///
+    /// Pays at most what the pool has received and not yet paid out, so a SOL
+    /// pool never dips into its rent.
+    pub fn pay_x402_lp_rewards(ctx: Context<PayX402LpRewards>, amount: u64) -> Result<()> {
+        let clock = Clock::get()?;
+
+        let lp_rewards_pool = &ctx.accounts.lp_rewards_pool;
+        require!(amount > 0, XLiquidityEngineError::NothingToClaim);
+        require!(
+            amount <= lp_rewards_pool.total_received - lp_rewards_pool.total_paid,
+            XLiquidityEngineError::InsufficientLpRewards
+        );
+        check_token_vault(
+            lp_rewards_pool.token_vault,
+            ctx.accounts.pool_token_vault.as_ref(),
+        )?;
+        let currency = lp_rewards_pool.currency;
+        let currency_seed = [currency as u8];
+        let bump_seed = [lp_rewards_pool.pool_bump];
+        let signer_seeds: &[&[&[u8]]] = &[&[b"x402_lp_rewards", &currency_seed, &bump_seed]];
+        withdraw_x402_funds(
+            &ctx.accounts.config,
+            currency,
+            amount,
+            &lp_rewards_pool.to_account_info(),
+            &ctx.accounts.recipient.to_account_info(),
+            X402TokenAccounts {
+                mint: ctx.accounts.mint.as_ref(),
+                from: ctx.accounts.pool_token_vault.as_ref(),
+                to: ctx.accounts.recipient_token_account.as_ref(),
+                token_program: ctx.accounts.token_program.as_ref(),
+            },
+            &ctx.accounts.system_program,
+            signer_seeds,
+        )?;
+
+        let lp_rewards_pool = &mut ctx.accounts.lp_rewards_pool;
+        lp_rewards_pool.total_paid += amount;
+        lp_rewards_pool.updated_at = clock.unix_timestamp;
+
+        // Create audit log
+        let recipient = ctx.accounts.recipient.key();
+        let event_data = (currency, recipient, amount).try_to_vec()?;
+        create_audit_log_internal(
+            &mut ctx.accounts.audit_stream,
+            ctx.bumps.audit_stream,
+            &mut ctx.accounts.audit_log,
+            ctx.bumps.audit_log,
+            ctx.accounts.lp_rewards_pool.key(),
+            AuditEventType::LpRewardsPaid,
+            None,
+            ctx.accounts.authority.key(),
+            &event_data,
+            &clock,
+        )?;
+
+        msg!("x402 LP rewards paid: {} to {}", amount, recipient);
+        Ok(())
+    }
+
+    /// Collect fees from a liquidity position
+    ///
+    /// If the position holds liquidity, the fees it is owed on the DEX are
+    /// first collected into the vaults via CPI, with the current DEX
+    /// position's accounts passed as remaining accounts. Calls into the DEX,
+    /// so it stops while rebalancing is paused.
+    pub fn collect_fees<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CollectFees<'info>>,
+        position_index: u8,
+    ) -> Result<()> {
+        let position = &mut ctx.accounts.position;
+        let clock = Clock::get()?;
+
+        // Validate position is active
+        require!(
+            position.status == PositionStatus::Active,
+            XLiquidityEngineError::PositionNotActive
+        );
+
+        if position.position_nft.is_some() {
+            let owner = position.owner;
+            let bump_seed = [position.position_bump];
+            let signer_seeds: &[&[&[u8]]] = &[&[
+                b"liquidity_position",
+                owner.as_ref(),
+                &[position_index],
+                &bump_seed,
+            ]];
+            let cpi = dex::PositionCpi {
+                position: position.to_account_info(),
+                token_a_vault: ctx.accounts.token_a_vault.to_account_info(),
+                token_b_vault: ctx.accounts.token_b_vault.to_account_info(),
+                token_a_mint: ctx.accounts.token_a_mint.to_account_info(),
+                token_b_mint: ctx.accounts.token_b_mint.to_account_info(),
+                token_program: ctx.accounts.token_program.to_account_info(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+                payer: None,
+                signer_seeds,
+            };
+            let adapter = dex::load(ctx.remaining_accounts, position, false)?;
+            let (collected_a, collected_b) = dex::collect_fees(adapter.as_ref(), &cpi)?;
+            position.total_fees_earned_a = position
+                .total_fees_earned_a
+                .checked_add(collected_a)
+                .ok_or(XLiquidityEngineError::MathOverflow)?;
+            position.total_fees_earned_b = position
+                .total_fees_earned_b
+                .checked_add(collected_b)
+                .ok_or(XLiquidityEngineError::MathOverflow)?;
+
+            ctx.accounts.token_a_vault.reload()?;
+            ctx.accounts.token_b_vault.reload()?;
+            position.refresh_value_locked(&ctx.accounts.token_a_vault, &ctx.accounts.token_b_vault)?;
+        }
+
+        // Check if there are fees to collect
+        require!(
+            position.total_fees_earned_a > 0 || position.total_fees_earned_b > 0,
+            XLiquidityEngineError::NoFeesToCollect
+        );
+
+        // Calculate protocol fees
+        let config = &ctx.accounts.config;
+        let _protocol_fee_a = (position.total_fees_earned_a as u128)
+            .checked_mul(config.protocol_fee_bps as u128)
+            .unwrap()
+            .checked_div(10000)
+            .unwrap() as u64;
+        let _protocol_fee_b = (position.total_fees_earned_b as u128)
+            .checked_mul(config.protocol_fee_bps as u128)
+            .unwrap()
+            .checked_div(10000)
+            .unwrap() as u64;
+
+        // Note: Actual token transfer would happen via CPI to token program
+        // This is just updating the accounting
+        // Protocol fees would be sent to config.fee_recipient in production
+
+        // Reset fee counters (fees would be transferred off-chain)
+        let fees_collected_a = position.total_fees_earned_a;
+        let fees_collected_b = position.total_fees_earned_b;
+        position.total_fees_earned_a = 0;
+        position.total_fees_earned_b = 0;
+        position.updated_at = clock.unix_timestamp;
+
+        // Create audit log
+        let event_data = format!(
+            "Fees collected: {} token A, {} token B",
+            fees_collected_a, fees_collected_b
+        );
+        create_audit_log_internal(
+            &mut ctx.accounts.audit_stream,
+            ctx.bumps.audit_stream,
+            &mut ctx.accounts.audit_log,
+            ctx.bumps.audit_log,
+            position.key(),
+            AuditEventType::FeesCollected,
+            Some(position.key()),
+            position.owner,
+            event_data.as_bytes(),
+            &clock,
+        )?;
+
+        msg!(
+            "Fees collected from position {}: {} token A, {} token B",
+            position.key(),
+            fees_collected_a,
+            fees_collected_b
+        );
+        Ok(())
+    }
+
+    /// Re-read what the position's DEX liquidity holds (position owner or
+    /// rebalance keeper)
+    ///
+    /// `deployed_amount_a` / `deployed_amount_b` are set when liquidity is
+    /// deposited; as the pool price moves the DEX position's token mix
+    /// changes, so this recomputes them from its liquidity and range at the
+    /// current pool price, and refreshes `liquidity_amount` and
+    /// `total_value_locked` at that price. The current DEX
+    /// position's accounts are passed as remaining accounts.
+    pub fn refresh_deployed_amounts<'info>(
+        ctx: Context<'_, '_, 'info, 'info, RefreshDeployedAmounts<'info>>,
+        _position_index: u8,
+    ) -> Result<()> {
+        let position = &mut ctx.accounts.position;
+        let clock = Clock::get()?;
+
+        let (deployed_a, deployed_b, liquidity) = if let Some(dex_position) = position.position_nft {
+            let adapter = dex::load(ctx.remaining_accounts, position, false)?;
+            let sqrt_price_x64 = adapter.read_pool_price()?;
+            let (deployed_a, deployed_b) = adapter.position_amounts(sqrt_price_x64)?;
+            position.value_sqrt_price_x64 = sqrt_price_x64;
+            (deployed_a, deployed_b, adapter.liquidity_of(dex_position)?.unwrap_or(0))
+        } else {
+            (0, 0, 0)
+        };
+        position.liquidity_amount = liquidity;
+        position.deployed_amount_a = deployed_a;
+        position.deployed_amount_b = deployed_b;
+        position.refresh_value_locked(&ctx.accounts.token_a_vault, &ctx.accounts.token_b_vault)?;
+        position.updated_at = clock.unix_timestamp;
+
+        msg!(
+            "Deployed amounts of position {}: {} token A, {} token B",
+            position.key(),
+            deployed_a,
+            deployed_b
+        );
+        Ok(())
+    }
+
+    /// Cancel a pending rebalancing decision (owner only)
+    ///
+    /// Frees the decision's slot in `pending_decisions`, so the position can be
+    /// closed without waiting for the decision to expire.
+    pub fn cancel_rebalance_decision(
+        ctx: Context<CancelRebalanceDecision>,
+        _position_index: u8,
+        _decision_index: u32,
+    ) -> Result<()> {
+        let decision = &mut ctx.accounts.decision;
+        let position = &mut ctx.accounts.position;
+        let clock = Clock::get()?;
+
+        retire_pending_decision(decision, position, ExecutionStatus::Cancelled, &clock)?;
+
+        // Create audit log
+        create_audit_log_internal(
+            &mut ctx.accounts.audit_stream,
+            ctx.bumps.audit_stream,
+            &mut ctx.accounts.audit_log,
+            ctx.bumps.audit_log,
+            position.key(),
+            AuditEventType::RebalanceCancelled,
+            Some(position.key()),
+            ctx.accounts.owner.key(),
+            decision.key().as_ref(),
+            &clock,
+        )?;
+
+        msg!("Rebalance decision cancelled: {}", decision.key());
+        Ok(())
+    }
+
+    /// Expire a pending rebalancing decision past its `expires_at`
+    /// (permissionless)
+    ///
+    /// Stale decisions can't be executed; this frees their slot in
+    /// `pending_decisions`.
+    pub fn expire_rebalance_decision(
+        ctx: Context<ExpireRebalanceDecision>,
+        _position_index: u8,
+        _decision_index: u32,
+    ) -> Result<()> {
+        let decision = &mut ctx.accounts.decision;
+        let position = &mut ctx.accounts.position;
+        let clock = Clock::get()?;
+
+        require!(
+            clock.unix_timestamp >= decision.expires_at,
+            XLiquidityEngineError::DecisionNotExpired
+        );
+        retire_pending_decision(decision, position, ExecutionStatus::Expired, &clock)?;
+
+        // Create audit log
+        create_audit_log_internal(
+            &mut ctx.accounts.audit_stream,
+            ctx.bumps.audit_stream,
+            &mut ctx.accounts.audit_log,
+            ctx.bumps.audit_log,
+            position.key(),
+            AuditEventType::RebalanceExpired,
+            Some(position.key()),
+            ctx.accounts.caller.key(),
+            decision.key().as_ref(),
+            &clock,
+        )?;
+
+        msg!("Rebalance decision expired: {}", decision.key());
+        Ok(())
+    }
+
+    /// Approve a rebalancing decision (human oversight; position owner or
+    /// protocol authority)
+    pub fn approve_rebalance(
+        ctx: Context<ApproveRebalance>,
+        _decision_index: u32,
+    ) -> Result<()> {
+        let decision = &mut ctx.accounts.decision;
+        let clock = Clock::get()?;
+
+        require!(
+            decision.requires_human_approval,
+            XLiquidityEngineError::ApprovalNotRequired
+        );
+        require!(
+            decision.execution_status == ExecutionStatus::Pending,
+            XLiquidityEngineError::InvalidExecutionStatus
+        );
+
+        decision.human_approver = Some(ctx.accounts.approver.key());
+        decision.approval_timestamp = Some(clock.unix_timestamp);
+
+        // Create audit log
+        create_audit_log_internal(
+            &mut ctx.accounts.audit_stream,
+            ctx.bumps.audit_stream,
+            &mut ctx.accounts.audit_log,
+            ctx.bumps.audit_log,
+            ctx.accounts.position.key(),
+            AuditEventType::HumanApprovalGranted,
+            Some(decision.position),
+            ctx.accounts.approver.key(),
+            &[],
+            &clock,
+        )?;
+
+        msg!("Rebalance decision approved by: {}", ctx.accounts.approver.key());
+        Ok(())
+    }
+
+    /// Verify a contiguous range of a subject's audit log entries
+    ///
+    /// The `AuditLog` PDAs are passed as remaining accounts in sequence order,
+    /// starting at `start_sequence`. Any stored range can be checked; one that
+    /// ends at the stream's latest entry must also match the stream head.
+    /// Read-only: fails with `AuditChainBroken` on the first entry that was
+    /// altered, removed or reordered.
+    pub fn verify_audit_chain<'info>(
+        ctx: Context<'_, '_, 'info, 'info, VerifyAuditChain<'info>>,
+        start_sequence: u64,
+    ) -> Result<()> {
+        let stream = &ctx.accounts.audit_stream;
+        require!(
+            !ctx.remaining_accounts.is_empty(),
+            XLiquidityEngineError::AuditChainBroken
+        );
+
+        let mut expected_sequence = start_sequence;
+        let mut expected_prev_hash: Option<[u8; 32]> = if start_sequence == 0 {
+            Some([0u8; 32])
+        } else {
+            None // The entry before the range isn't part of it
+        };
+
+        for info in ctx.remaining_accounts.iter() {
+            let entry = Account::<AuditLog>::try_from(info)?;
+            require!(
+                entry.subject == stream.subject && entry.sequence == expected_sequence,
+                XLiquidityEngineError::AuditChainBroken
+            );
+            if let Some(prev_hash) = expected_prev_hash {
+                require!(
+                    entry.prev_event_hash == prev_hash,
+                    XLiquidityEngineError::AuditChainBroken
+                );
+            }
+            require!(
+                entry.event_hash == compute_audit_event_hash(&entry),
+                XLiquidityEngineError::AuditChainBroken
+            );
+
+            expected_prev_hash = Some(entry.event_hash);
+            expected_sequence = expected_sequence
+                .checked_add(1)
+                .ok_or(XLiquidityEngineError::MathOverflow)?;
+        }
+
+        // A range reaching the head must end at the stream's latest hash
+        if expected_sequence == stream.log_count {
+            require!(
+                expected_prev_hash == Some(stream.last_hash),
+                XLiquidityEngineError::AuditChainBroken
+            );
+        }
+
+        msg!(
+            "Audit chain of {} verified for entries {}..{}",
+            stream.subject,
+            start_sequence,
+            expected_sequence
+        );
+        Ok(())
+    }
+
+    /// Upgrade a layout-v0 protocol config to the current layout
+    ///
+    /// Permissionless; the payer covers the extra rent. Moves the legacy
+    /// single facilitator into the registry and sets the same defaults as
+    /// `initialize_protocol_config` for the appended fields.
+    pub fn migrate_protocol_config(ctx: Context<MigrateProtocolConfig>) -> Result<()> {
+        let info = ctx.accounts.config.to_account_info();
+        migration::upgrade::<ProtocolConfig, migration::ProtocolConfigV0>(
+            &info,
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            8 + ProtocolConfig::LEN,
+        )?;
+
+        let mut config = ProtocolConfig::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+        if let Some(facilitator) = config.x402_facilitator.take() {
+            config.x402_facilitators.push(X402FacilitatorEntry {
+                facilitator,
+                enabled: true,
+                accepted_currencies: vec![PaymentCurrency::SOL],
+                min_payment: None,
+                expires_at: None,
+            });
+        }
+        config.revenue_split = RevenueSplit {
+            treasury_bps: MAX_BPS,
+            providers_bps: 0,
+            lp_pool_bps: 0,
+        };
+        config.layout_version = LAYOUT_VERSION;
+        config.updated_at = Clock::get()?.unix_timestamp;
+        config.try_serialize(&mut &mut info.try_borrow_mut_data()?[..])?;
+
+        msg!("Protocol config migrated to layout v{}", LAYOUT_VERSION);
+        Ok(())
+    }
+
+    /// Upgrade a layout-v0 liquidity position to the current layout
+    ///
+    /// Permissionless; the payer covers the extra rent. v0 positions never
+    /// deployed liquidity, so the appended fields start out empty, and their
+    /// token deposits are left uncapped.
+    pub fn migrate_liquidity_position(ctx: Context<MigrateLiquidityPosition>) -> Result<()> {
+        let info = ctx.accounts.position.to_account_info();
+        migration::upgrade::<LiquidityPosition, migration::LiquidityPositionV0>(
+            &info,
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            8 + LiquidityPosition::LEN,
+        )?;
+
+        let mut position = LiquidityPosition::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+        position.layout_version = LAYOUT_VERSION;
+        position.max_amount_a = u64::MAX;
+        position.max_amount_b = u64::MAX;
+        position.try_serialize(&mut &mut info.try_borrow_mut_data()?[..])?;
+
+        msg!("Position {} migrated to layout v{}", info.key(), LAYOUT_VERSION);
+        Ok(())
+    }
+
+    /// Upgrade a layout-v0 rebalance decision to the current layout
+    ///
+    /// Permissionless; the payer covers the extra rent. A v0 decision has no
+    /// reference price, so it can only be executed on a position without
+    /// liquidity; otherwise it is left to expire.
+    pub fn migrate_rebalance_decision(ctx: Context<MigrateRebalanceDecision>) -> Result<()> {
+        let info = ctx.accounts.decision.to_account_info();
+        migration::upgrade::<RebalanceDecision, migration::RebalanceDecisionV0>(
+            &info,
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            8 + RebalanceDecision::LEN,
+        )?;
+
+        let mut decision = RebalanceDecision::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+        decision.layout_version = LAYOUT_VERSION;
+        decision.expires_at = decision
+            .created_at
+            .checked_add(REBALANCE_DECISION_TTL)
+            .ok_or(XLiquidityEngineError::MathOverflow)?;
+        decision.try_serialize(&mut &mut info.try_borrow_mut_data()?[..])?;
+
+        msg!("Decision {} migrated to layout v{}", info.key(), LAYOUT_VERSION);
+        Ok(())
+    }
+}
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+/// Assess risk level based on prediction metrics
+fn assess_risk(
+    confidence: u16,
+    sentiment: i16,
+    volatility: u16,
+) -> RiskLevel {
+    // Simple risk assessment logic
+    // In production, this would be more sophisticated
+    if confidence < 5000 || volatility > 8000 {
+        RiskLevel::Critical
+    } else if confidence < 7000 || volatility > 6000 || sentiment < -5000 {
+        RiskLevel::High
+    } else if confidence < 8500 || volatility > 4000 {
+        RiskLevel::Medium
+    } else {
+        RiskLevel::Low
+    }
+}
+
+/// Seed identifying an x402 endpoint/version pricing account
+pub fn x402_endpoint_hash(api_endpoint: &str, api_version: &str) -> [u8; 32] {
+    hashv(&[
+        &(api_endpoint.len() as u32).to_le_bytes(),
+        api_endpoint.as_bytes(),
+        api_version.as_bytes(),
+    ])
+    .to_bytes()
+}
+
+/// Build the canonical message a facilitator signs to authorize an x402 payment
+///
+/// Layout: domain tag, payment_id, payer, amount (LE), currency, endpoint
+/// length (u32 LE) and bytes, version length (u32 LE) and bytes, expiry
+/// (i64 LE).
+pub fn x402_payment_message(
+    payment_id: &[u8; 32],
+    payer: &Pubkey,
+    amount: u64,
+    currency: PaymentCurrency,
+    api_endpoint: &str,
+    api_version: &str,
+    expires_at: i64,
+) -> Vec<u8> {
+    let mut message = Vec::with_capacity(
+        X402_MESSAGE_DOMAIN.len() + 89 + api_endpoint.len() + api_version.len(),
+    );
+    message.extend_from_slice(X402_MESSAGE_DOMAIN);
+    message.extend_from_slice(payment_id);
+    message.extend_from_slice(payer.as_ref());
+    message.extend_from_slice(&amount.to_le_bytes());
+    message.push(currency as u8);
+    message.extend_from_slice(&(api_endpoint.len() as u32).to_le_bytes());
+    message.extend_from_slice(api_endpoint.as_bytes());
+    message.extend_from_slice(&(api_version.len() as u32).to_le_bytes());
+    message.extend_from_slice(api_version.as_bytes());
+    message.extend_from_slice(&expires_at.to_le_bytes());
+    message
+}
+
+/// Check that the previous instruction is an ed25519 program instruction in
+/// which `signer` signed exactly `message`, and return the signature
+fn verify_ed25519_signature(
+    instructions_sysvar: &AccountInfo,
+    signer: &Pubkey,
+    message: &[u8],
+) -> Result<[u8; 64]> {
+    let current_index = load_current_index_checked(instructions_sysvar)?;
+    require!(
+        current_index > 0,
+        XLiquidityEngineError::InvalidFacilitatorSignature
+    );
+    let ix = load_instruction_at_checked(current_index as usize - 1, instructions_sysvar)?;
+    require_keys_eq!(
+        ix.program_id,
+        solana_sdk_ids::ed25519_program::ID,
+        XLiquidityEngineError::InvalidFacilitatorSignature
+    );
+
+    // Header: num_signatures (u8), padding (u8), then one 14-byte offsets struct
+    let data = &ix.data;
+    require!(
+        data.len() >= 16 && data[0] == 1,
+        XLiquidityEngineError::InvalidFacilitatorSignature
+    );
+    let read_u16 = |at: usize| u16::from_le_bytes([data[at], data[at + 1]]);
+    let signature_offset = read_u16(2) as usize;
+    let signature_ix_index = read_u16(4);
+    let public_key_offset = read_u16(6) as usize;
+    let public_key_ix_index = read_u16(8);
+    let message_offset = read_u16(10) as usize;
+    let message_size = read_u16(12) as usize;
+    let message_ix_index = read_u16(14);
+
+    // All data must live in the ed25519 instruction itself
+    require!(
+        signature_ix_index == u16::MAX
+            && public_key_ix_index == u16::MAX
+            && message_ix_index == u16::MAX,
+        XLiquidityEngineError::InvalidFacilitatorSignature
+    );
+
+    let slice = |offset: usize, len: usize| -> Result<&[u8]> {
+        data.get(offset..offset + len)
+            .ok_or_else(|| XLiquidityEngineError::InvalidFacilitatorSignature.into())
+    };
+    require!(
+        slice(public_key_offset, 32)? == signer.as_ref(),
+        XLiquidityEngineError::InvalidFacilitatorSignature
+    );
+    require!(
+        slice(message_offset, message_size)? == message,
+        XLiquidityEngineError::InvalidFacilitatorSignature
+    );
+
+    let mut signature = [0u8; 64];
+    signature.copy_from_slice(slice(signature_offset, 64)?);
+    Ok(signature)
+}
+
+/// Optional SPL token accounts used to move USDC/USDT x402 funds
+struct X402TokenAccounts<'a, 'info> {
+    mint: Option<&'a Account<'info, Mint>>,
+    from: Option<&'a Account<'info, TokenAccount>>,
+    to: Option<&'a Account<'info, TokenAccount>>,
+    token_program: Option<&'a Program<'info, Token>>,
+}
+
+/// Move x402 funds from `from_wallet` (signer, or PDA signing with
+/// `signer_seeds`) to `to_wallet`
+///
+/// SOL goes through the system program. USDC/USDT go through SPL Token
+/// `transfer_checked` between token accounts of the configured mint, where the
+/// destination must be owned by `to_wallet`.
+#[allow(clippy::too_many_arguments)]
+fn transfer_x402_funds<'info>(
+    config: &ProtocolConfig,
+    currency: PaymentCurrency,
+    amount: u64,
+    from_wallet: &AccountInfo<'info>,
+    to_wallet: &AccountInfo<'info>,
+    token_accounts: X402TokenAccounts<'_, 'info>,
+    system_program: &Program<'info, System>,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    if currency == PaymentCurrency::SOL {
+        return system_program::transfer(
+            CpiContext::new_with_signer(
+                system_program.to_account_info(),
+                system_program::Transfer {
+                    from: from_wallet.clone(),
+                    to: to_wallet.clone(),
+                },
+                signer_seeds,
+            ),
+            amount,
+        );
+    }
+
+    let expected_mint = config
+        .currency_mint(currency)
+        .ok_or(XLiquidityEngineError::CurrencyMintNotConfigured)?;
+    let X402TokenAccounts {
+        mint: Some(mint),
+        from: Some(from),
+        to: Some(to),
+        token_program: Some(token_program),
+    } = token_accounts
+    else {
+        return Err(XLiquidityEngineError::MissingTokenAccounts.into());
+    };
+    require_keys_eq!(mint.key(), expected_mint, XLiquidityEngineError::InvalidMint);
+    require_keys_eq!(from.mint, expected_mint, XLiquidityEngineError::InvalidMint);
+    require_keys_eq!(to.mint, expected_mint, XLiquidityEngineError::InvalidMint);
+    require_keys_eq!(
+        to.owner,
+        to_wallet.key(),
+        XLiquidityEngineError::InvalidTokenAccount
+    );
+
+    token::transfer_checked(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            TransferChecked {
+                from: from.to_account_info(),
+                mint: mint.to_account_info(),
+                to: to.to_account_info(),
+                authority: from_wallet.clone(),
+            },
+            signer_seeds,
+        ),
+        amount,
+        mint.decimals,
+    )
+}
+
+/// Pay x402 funds out of a program-owned PDA (credit account or revenue vault)
+///
+/// SOL is held as lamports on the PDA itself and moved directly; USDC/USDT are
+/// moved out of the PDA's token account with the PDA signing.
+#[allow(clippy::too_many_arguments)]
+fn withdraw_x402_funds<'info>(
+    config: &ProtocolConfig,
+    currency: PaymentCurrency,
+    amount: u64,
+    pda: &AccountInfo<'info>,
+    to_wallet: &AccountInfo<'info>,
+    token_accounts: X402TokenAccounts<'_, 'info>,
+    system_program: &Program<'info, System>,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    if currency == PaymentCurrency::SOL {
+        pda.sub_lamports(amount)?;
+        to_wallet.add_lamports(amount)?;
+        return Ok(());
+    }
+    transfer_x402_funds(
+        config,
+        currency,
+        amount,
+        pda,
+        to_wallet,
+        token_accounts,
+        system_program,
+        signer_seeds,
+    )
+}
+
+/// Validate the token account a PDA will hold `currency` in
+///
+/// Returns `None` for SOL (held as lamports on the PDA). For USDC/USDT the
+/// token account must be of the configured mint and owned by `pda`.
+fn x402_token_vault(
+    config: &ProtocolConfig,
+    currency: PaymentCurrency,
+    token_vault: Option<&Account<TokenAccount>>,
+    pda: Pubkey,
+) -> Result<Option<Pubkey>> {
+    if currency == PaymentCurrency::SOL {
+        return Ok(None);
+    }
+    let expected_mint = config
+        .currency_mint(currency)
+        .ok_or(XLiquidityEngineError::CurrencyMintNotConfigured)?;
+    let token_vault = token_vault.ok_or(XLiquidityEngineError::MissingTokenAccounts)?;
+    require_keys_eq!(token_vault.mint, expected_mint, XLiquidityEngineError::InvalidMint);
+    require_keys_eq!(token_vault.owner, pda, XLiquidityEngineError::InvalidTokenAccount);
+    Ok(Some(token_vault.key()))
+}
+
+/// Check that a provided token account is the one recorded on a PDA
+fn check_token_vault(expected: Option<Pubkey>, provided: Option<&Account<TokenAccount>>) -> Result<()> {
+    if let (Some(expected), Some(provided)) = (expected, provided) {
+        require_keys_eq!(provided.key(), expected, XLiquidityEngineError::InvalidTokenAccount);
+    }
+    Ok(())
+}
+
+/// Move a pending decision to `status` without executing it and release its
+/// slot in the position's `pending_decisions`
+fn retire_pending_decision(
+    decision: &mut RebalanceDecision,
+    position: &mut LiquidityPosition,
+    status: ExecutionStatus,
+    clock: &Clock,
+) -> Result<()> {
+    require!(
+        decision.execution_status == ExecutionStatus::Pending,
+        XLiquidityEngineError::InvalidExecutionStatus
+    );
+    decision.execution_status = status;
+    position.pending_decisions = position.pending_decisions.saturating_sub(1);
+    position.updated_at = clock.unix_timestamp;
+    Ok(())
+}
+
+/// `amount * bps / 10_000`, rounded down
+fn bps_share(amount: u64, bps: u16) -> Result<u64> {
+    u64::try_from(amount as u128 * bps as u128 / MAX_BPS as u128)
+        .map_err(|_| XLiquidityEngineError::MathOverflow.into())
+}
+
+/// Compute the chained hash of an audit log entry
+///
+/// Covers the previous entry's hash plus the entry contents, including its
+/// subject, sequence and timestamps, so any deletion, reordering or edit of an
+/// entry breaks every hash after it.
+fn compute_audit_event_hash(entry: &AuditLog) -> [u8; 32] {
+    hashv(&[
+        &entry.prev_event_hash,
+        entry.subject.as_ref(),
+        &entry.sequence.to_le_bytes(),
+        &[entry.event_type as u8],
+        entry.user.as_ref(),
+        &entry.position.unwrap_or_default().to_bytes(),
+        &entry.event_data,
+        &entry.created_at.to_le_bytes(),
+        &entry.slot.to_le_bytes(),
+    ])
+    .to_bytes()
+}
+
+/// Create an audit log entry (internal helper)
+///
+/// Populates a freshly initialized `AuditLog` PDA, links it to the previous
+/// entry of `subject`'s stream and advances that stream's sequence, which the
+/// PDA was derived from. Each subject chains its own entries, so handlers on
+/// different positions, payments or credit accounts don't write a shared
+/// account.
+#[allow(clippy::too_many_arguments)]
+fn create_audit_log_internal(
+    audit_stream: &mut Account<AuditStream>,
+    audit_stream_bump: u8,
+    audit_log: &mut Account<AuditLog>,
+    audit_log_bump: u8,
+    subject: Pubkey,
+    event_type: AuditEventType,
+    position: Option<Pubkey>,
+    user: Pubkey,
+    event_data: &[u8],
+    clock: &Clock,
+) -> Result<()> {
+    require!(
+        event_data.len() <= AuditLog::MAX_EVENT_DATA_LEN,
+        XLiquidityEngineError::AuditDataTooLarge
+    );
+
+    // First entry of the subject: the stream was just created
+    if audit_stream.subject == Pubkey::default() {
+        audit_stream.subject = subject;
+        audit_stream.stream_bump = audit_stream_bump;
+    }
+
+    let sequence = audit_stream.log_count;
+    audit_stream.log_count = sequence
+        .checked_add(1)
+        .ok_or(XLiquidityEngineError::MathOverflow)?;
+
+    audit_log.log_id = hashv(&[b"audit_log".as_ref(), subject.as_ref(), &sequence.to_le_bytes()]).to_bytes();
+    audit_log.log_bump = audit_log_bump;
+    audit_log.subject = subject;
+    audit_log.sequence = sequence;
+    audit_log.event_type = event_type;
+    audit_log.position = position;
+    audit_log.user = user;
+    audit_log.event_data = event_data.to_vec();
+    audit_log.prev_event_hash = audit_stream.last_hash;
+    audit_log.ai_model_version = None;
+    audit_log.decision_rationale = None;
+    audit_log.prediction_scores = None;
+    audit_log.regulatory_jurisdiction = None;
+    audit_log.created_at = clock.unix_timestamp;
+    audit_log.slot = clock.slot;
+    audit_log.event_hash = compute_audit_event_hash(audit_log);
+
+    audit_stream.last_hash = audit_log.event_hash;
+
+    msg!(
+        "Audit log {}#{}: {:?} for user: {}, position: {:?}",
+        subject,
+        sequence,
+        event_type,
+        user,
+        position
+    );
+    Ok(())
+}
+
+// ============================================================================
+// ENUMS AND TYPES
+// ============================================================================
+
+/// Basis point denominator (100%)
+pub const MAX_BPS: u16 = 10_000;
+/// Current layout of `ProtocolConfig`, `LiquidityPosition` and
+/// `RebalanceDecision`; older accounts are upgraded by the `migrate_*`
+/// instructions
+pub const LAYOUT_VERSION: u8 = 1;
+/// Upper bound for `min_rebalance_interval` (30 days)
+pub const MAX_REBALANCE_INTERVAL: u32 = 30 * 24 * 3600;
+/// Upper bound for `max_rebalance_frequency` (one per minute)
+pub const MAX_REBALANCE_FREQUENCY: u32 = 24 * 60;
+/// Upper bound for the x402 facilitator rotation overlap window (7 days)
+pub const MAX_FACILITATOR_OVERLAP: u32 = 7 * 24 * 3600;
+/// Maximum number of providers in `ai_model_registry`, so that an audit event
+/// holds the old and new registry
+pub const MAX_AI_MODEL_REGISTRY: usize = 7;
+/// Maximum number of provider balances tracked by an x402 revenue vault
+pub const MAX_REVENUE_ACCRUALS: usize = 16;
+// Every registered provider must always find an accrual slot
+const _: () = assert!(MAX_AI_MODEL_REGISTRY < MAX_REVENUE_ACCRUALS);
+/// Upper bound for the access duration of an x402 endpoint (30 days)
+pub const MAX_ACCESS_DURATION: u32 = 30 * 24 * 3600;
+/// Domain tag prefixed to every x402 payment message signed by a facilitator
+pub const X402_MESSAGE_DOMAIN: &[u8] = b"x-liquidity-engine:x402-payment:v2";
+/// Maximum number of x402 facilitators in the registry
+pub const MAX_X402_FACILITATORS: usize = 5;
+/// Maximum width of a Meteora DLMM position, in bins
+pub const MAX_BINS_PER_POSITION: i32 = 70;
+/// Seconds a rebalance decision stays executable (1 day)
+pub const REBALANCE_DECISION_TTL: i64 = 24 * 3600;
+/// Number of `PaymentCurrency` variants
+pub const PAYMENT_CURRENCY_COUNT: usize = 3;
+/// Share of a closed x402 payment's rent paid to whoever closed it (10%)
+pub const CLOSE_PAYMENT_REWARD_BPS: u16 = 1_000;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq)]
+pub enum DexType {
+    Raydium,
+    Orca,
+    Meteora,
     Unknown,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq)]
-pub enum PositionStatus {
-    Active,
-    Paused,
-    Closed,
-    Liquidated,
+/// How a Meteora DLMM position spreads its liquidity over its bins
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq)]
+pub enum DistributionShape {
+    /// Uniform across the range
+    Spot,
+    /// Concentrated around the active bin
+    Curve,
+    /// Concentrated at the range edges
+    BidAsk,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq)]
+pub enum PositionStatus {
+    Active,
+    Paused,
+    Closed,
+    Liquidated,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq)]
+pub enum ExecutionStatus {
+    Pending,
+    Executed,
+    Failed,
+    Rejected,
+    Cancelled,
+    Expired,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq)]
+pub enum PaymentStatus {
+    Pending,
+    Verified,
+    Settled,
+    Failed,
+    Refunded,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq)]
+pub enum PaymentCurrency {
+    SOL,
+    USDC,
+    USDT,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq)]
+pub enum RiskLevel {
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub enum StrategyType {
+    Conservative,
+    Balanced,
+    Aggressive,
+    Custom,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub enum RebalanceFrequency {
+    OnSignal,
+    Daily,
+    Weekly,
+    Monthly,
+    Manual,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub enum ComplianceMode {
+    Basic,
+    Enhanced,
+    Full,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug)]
+pub enum AuditEventType {
+    PositionCreated,
+    PositionClosed,
+    Rebalanced,
+    FeesCollected,
+    PaymentReceived,
+    PolicyViolation,
+    HumanApprovalRequired,
+    HumanApprovalGranted,
+    ConfigUpdated,
+    AuthorityProposed,
+    AuthorityTransferred,
+    AuthorityTransferCancelled,
+    FacilitatorUpdated,
+    FacilitatorRevoked,
+    FacilitatorRotated,
+    PaymentSettled,
+    PaymentRefunded,
+    EndpointPricingUpdated,
+    CreditDeposited,
+    CreditDebited,
+    PaymentClosed,
+    RevenueClaimed,
+    LiquidityDeposited,
+    LiquidityWithdrawn,
+    PositionPaused,
+    PositionResumed,
+    ProtocolPauseUpdated,
+    RebalanceCancelled,
+    RebalanceExpired,
+    CreditWithdrawn,
+    CreditAccountClosed,
+    LpRewardsDistributed,
+    AiModelRegistryUpdated,
+    LpRewardsPaid,
+}
+
+/// Split of x402 revenue between the protocol treasury, AI model/data
+/// providers (`ai_model_registry`) and the LP rewards pool
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq)]
+pub struct RevenueSplit {
+    pub treasury_bps: u16,
+    pub providers_bps: u16,
+    pub lp_pool_bps: u16,
+}
+
+impl RevenueSplit {
+    pub const LEN: usize = 2 + 2 + 2;
+
+    fn validate(&self) -> Result<()> {
+        let total = self.treasury_bps as u32 + self.providers_bps as u32 + self.lp_pool_bps as u32;
+        require!(
+            total == MAX_BPS as u32,
+            XLiquidityEngineError::InvalidConfigValue
+        );
+        Ok(())
+    }
+}
+
+/// Protocol-wide kill switches, one per area of the protocol
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Default)]
+pub struct ProtocolPauseFlags {
+    /// Blocks rebalance decisions, approvals and execution, and fee collection
+    pub rebalancing: bool,
+    /// Blocks position creation and deposits
+    pub new_positions: bool,
+    /// Blocks x402 payments, settlement and prepaid credit
+    pub x402_payments: bool,
+}
+
+impl ProtocolPauseFlags {
+    pub const LEN: usize = 1 + 1 + 1;
+}
+
+/// Bin range of a Meteora DLMM position (inclusive bin ids)
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq)]
+pub struct BinRange {
+    pub lower_bin_id: i32,
+    pub upper_bin_id: i32,
+    pub shape: DistributionShape,
+}
+
+impl BinRange {
+    pub const LEN: usize = 4 + 4 + 1;
+
+    /// Number of bins in the range
+    pub fn width(&self) -> i32 {
+        self.upper_bin_id - self.lower_bin_id + 1
+    }
+
+    /// Check that a bin range is given exactly for Meteora positions, and fits
+    /// in one DLMM position
+    fn validate_for(dex: DexType, bin_range: Option<&BinRange>) -> Result<()> {
+        match (dex, bin_range) {
+            (DexType::Meteora, Some(range)) => require!(
+                range.lower_bin_id <= range.upper_bin_id
+                    && range.width() <= MAX_BINS_PER_POSITION,
+                XLiquidityEngineError::InvalidBinRange
+            ),
+            (DexType::Meteora, None) | (_, Some(_)) => {
+                return err!(XLiquidityEngineError::InvalidBinRange)
+            }
+            (_, None) => {}
+        }
+        Ok(())
+    }
+}
+
+/// Optional swap leg of `execute_rebalance`
+///
+/// The aggregator's route instruction is built off chain; it runs between
+/// removing and re-adding liquidity and its result is checked against the
+/// vault balances.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct SwapParams {
+    /// Swap token A for token B (otherwise token B for token A)
+    pub a_to_b: bool,
+    /// Most of the input token the swap may spend
+    pub amount_in: u64,
+    /// Output the route was quoted at; the swap must receive at least this
+    /// less `slippage_tolerance_bps`, and the quote itself may be at most that
+    /// much below `amount_in` at the decision's reference price
+    pub quoted_amount_out: u64,
+    /// Route instruction data, passed to the aggregator as-is
+    pub route_data: Vec<u8>,
+    /// Number of trailing remaining accounts that belong to the swap, starting
+    /// with the aggregator program
+    pub accounts_len: u8,
+}
+
+/// Revenue owed to one provider by an x402 revenue vault
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct RevenueAccrual {
+    pub provider: Pubkey,
+    pub amount: u64,
+}
+
+impl RevenueAccrual {
+    pub const LEN: usize = 32 + 8;
+}
+
+/// Result of `check_x402_access`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub struct X402AccessStatus {
+    pub has_access: bool,
+    /// End of the paid access window (payment-based access)
+    pub access_expires_at: Option<i64>,
+    /// Credit balance left before this call (credit-based access)
+    pub remaining_credit: Option<u64>,
+}
+
+/// Price and access settings for `create_x402_endpoint_pricing` / `update_x402_endpoint_pricing`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct X402PricingParams {
+    /// Price per currency; `None` means the currency is not accepted
+    pub price_sol: Option<u64>,
+    pub price_usdc: Option<u64>,
+    pub price_usdt: Option<u64>,
+    /// Seconds of API access granted per payment
+    pub access_duration: u32,
+    pub enabled: bool,
+}
+
+impl X402PricingParams {
+    fn validate(&self) -> Result<()> {
+        require!(
+            self.access_duration > 0 && self.access_duration <= MAX_ACCESS_DURATION,
+            XLiquidityEngineError::InvalidConfigValue
+        );
+        require!(
+            self.price_sol.is_some() || self.price_usdc.is_some() || self.price_usdt.is_some(),
+            XLiquidityEngineError::InvalidConfigValue
+        );
+        Ok(())
+    }
+}
+
+/// A whitelisted x402 facilitator in `ProtocolConfig.x402_facilitators`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct X402FacilitatorEntry {
+    pub facilitator: Pubkey,
+    pub enabled: bool,
+    pub accepted_currencies: Vec<PaymentCurrency>,
+    pub min_payment: Option<u64>,
+    /// Set on the outgoing entry during a rotation overlap window
+    pub expires_at: Option<i64>,
+}
+
+impl X402FacilitatorEntry {
+    pub const LEN: usize = 32 + // facilitator
+        1 + // enabled
+        4 + PAYMENT_CURRENCY_COUNT + // accepted_currencies (Vec<PaymentCurrency>)
+        1 + 8 + // min_payment (Option<u64>)
+        1 + 8; // expires_at (Option<i64>)
+
+    fn is_active(&self, now: i64) -> bool {
+        self.enabled && self.expires_at.is_none_or(|expires_at| now < expires_at)
+    }
+}
+
+/// Settings for `set_x402_facilitator`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct X402FacilitatorParams {
+    pub facilitator: Pubkey,
+    pub enabled: bool,
+    pub accepted_currencies: Vec<PaymentCurrency>,
+    pub min_payment: Option<u64>,
+    pub expires_at: FacilitatorExpiryUpdate,
+}
+
+/// How `set_x402_facilitator` changes a facilitator's `expires_at`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq)]
+pub enum FacilitatorExpiryUpdate {
+    /// Keep the current expiry (none for a new facilitator)
+    Keep,
+    /// Remove the expiry, e.g. to cancel a rotation overlap
+    Clear,
+    /// Expire the facilitator at a future unix timestamp
+    At { timestamp: i64 },
+}
+
+impl FacilitatorExpiryUpdate {
+    fn apply(self, current: Option<i64>) -> Option<i64> {
+        match self {
+            Self::Keep => current,
+            Self::Clear => None,
+            Self::At { timestamp } => Some(timestamp),
+        }
+    }
+}
+
+impl X402FacilitatorParams {
+    fn validate(&self, now: i64) -> Result<()> {
+        require!(
+            self.facilitator != Pubkey::default(),
+            XLiquidityEngineError::InvalidFacilitator
+        );
+        require!(
+            !self.accepted_currencies.is_empty()
+                && self.accepted_currencies.len() <= PAYMENT_CURRENCY_COUNT,
+            XLiquidityEngineError::InvalidConfigValue
+        );
+        for (i, currency) in self.accepted_currencies.iter().enumerate() {
+            require!(
+                !self.accepted_currencies[..i].contains(currency),
+                XLiquidityEngineError::InvalidConfigValue
+            );
+        }
+        if let Some(min_payment) = self.min_payment {
+            require!(min_payment > 0, XLiquidityEngineError::InvalidConfigValue);
+        }
+        if let FacilitatorExpiryUpdate::At { timestamp } = self.expires_at {
+            require!(timestamp > now, XLiquidityEngineError::InvalidConfigValue);
+        }
+        Ok(())
+    }
+}
+
+/// How `update_protocol_config` changes an optional pubkey setting
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Default)]
+pub enum PubkeyUpdate {
+    /// Keep the current value
+    #[default]
+    Keep,
+    /// Unset the value
+    Clear,
+    /// Set a new value
+    Set { value: Pubkey },
+}
+
+impl PubkeyUpdate {
+    fn apply(self, current: Option<Pubkey>) -> Option<Pubkey> {
+        match self {
+            Self::Keep => current,
+            Self::Clear => None,
+            Self::Set { value } => Some(value),
+        }
+    }
+}
+
+impl From<Option<Pubkey>> for PubkeyUpdate {
+    fn from(value: Option<Pubkey>) -> Self {
+        match value {
+            Some(value) => Self::Set { value },
+            None => Self::Clear,
+        }
+    }
+}
+
+/// Optional new values for `update_protocol_config`; `None` / `Keep` leaves a
+/// field unchanged
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
+pub struct UpdateProtocolConfigParams {
+    pub performance_fee_bps: Option<u16>,
+    pub protocol_fee_bps: Option<u16>,
+    pub fee_recipient: Option<Pubkey>,
+    pub x402_min_payment: Option<u64>,
+    pub min_rebalance_interval: Option<u32>,
+    pub max_rebalance_frequency: Option<u32>,
+    pub default_slippage_tolerance_bps: Option<u16>,
+    pub max_position_size: Option<u64>,
+    pub max_single_trade_size: Option<u64>,
+    pub require_human_approval_threshold: Option<u64>,
+    pub compliance_mode: Option<ComplianceMode>,
+    pub x402_usdc_mint: PubkeyUpdate,
+    pub x402_usdt_mint: PubkeyUpdate,
+    pub revenue_split: Option<RevenueSplit>,
+    pub swap_program: PubkeyUpdate,
+    pub rebalance_keeper: PubkeyUpdate,
+}
+
+impl UpdateProtocolConfigParams {
+    /// Range-check every provided value against the resulting configuration
+    fn validate(&self, config: &ProtocolConfig) -> Result<()> {
+        if let Some(bps) = self.performance_fee_bps {
+            require!(bps <= MAX_BPS, XLiquidityEngineError::InvalidConfigValue);
+        }
+        if let Some(bps) = self.protocol_fee_bps {
+            require!(bps <= MAX_BPS, XLiquidityEngineError::InvalidConfigValue);
+        }
+        if let Some(amount) = self.x402_min_payment {
+            require!(amount > 0, XLiquidityEngineError::InvalidConfigValue);
+        }
+        if let Some(interval) = self.min_rebalance_interval {
+            require!(
+                interval <= MAX_REBALANCE_INTERVAL,
+                XLiquidityEngineError::InvalidConfigValue
+            );
+        }
+        if let Some(frequency) = self.max_rebalance_frequency {
+            require!(
+                frequency > 0 && frequency <= MAX_REBALANCE_FREQUENCY,
+                XLiquidityEngineError::InvalidConfigValue
+            );
+        }
+        if let Some(bps) = self.default_slippage_tolerance_bps {
+            require!(
+                bps > 0 && bps <= MAX_BPS,
+                XLiquidityEngineError::InvalidConfigValue
+            );
+        }
+
+        let max_position_size = self.max_position_size.unwrap_or(config.max_position_size);
+        let max_single_trade_size = self
+            .max_single_trade_size
+            .unwrap_or(config.max_single_trade_size);
+        require!(max_position_size > 0, XLiquidityEngineError::InvalidConfigValue);
+        require!(
+            max_single_trade_size > 0 && max_single_trade_size <= max_position_size,
+            XLiquidityEngineError::InvalidConfigValue
+        );
+        if let Some(threshold) = self.require_human_approval_threshold {
+            require!(
+                threshold <= max_position_size,
+                XLiquidityEngineError::InvalidConfigValue
+            );
+        }
+        if let Some(split) = &self.revenue_split {
+            split.validate()?;
+        }
+        // Token vaults of the current mint would no longer match the currency
+        for (update, currency) in [
+            (self.x402_usdc_mint, PaymentCurrency::USDC),
+            (self.x402_usdt_mint, PaymentCurrency::USDT),
+        ] {
+            let current = config.currency_mint(currency);
+            if update.apply(current) != current {
+                require!(
+                    config.x402_token_vaults[currency as usize] == 0,
+                    XLiquidityEngineError::CurrencyMintInUse
+                );
+            }
+        }
+        // The swap leg signs for the vaults; never hand that to the token program
+        if let PubkeyUpdate::Set { value: program } = self.swap_program {
+            require!(
+                program != token::ID && program != crate::ID,
+                XLiquidityEngineError::InvalidConfigValue
+            );
+        }
+        Ok(())
+    }
+}
+
+// ============================================================================
+// ACCOUNT STRUCTURES
+// ============================================================================
+
+/// Tracks individual LP positions managed by the protocol
+#[account]
+pub struct LiquidityPosition {
+    // Ownership & Identity
+    pub owner: Pubkey,
+    pub position_bump: u8,
+    
+    // Token Pair
+    pub token_a: Pubkey,
+    pub token_b: Pubkey,
+    pub token_a_vault: Pubkey,
+    pub token_b_vault: Pubkey,
+    
+    // DEX Integration
+    pub dex: DexType,
+    pub pool_address: Pubkey,
+    /// Raydium position NFT mint / Orca Whirlpool position mint
+    pub position_nft: Option<Pubkey>,
+    
+    // Price Range (Concentrated Liquidity)
+    pub current_tick_lower: i32,
+    pub current_tick_upper: i32,
+    pub current_price_lower: u128,
+    pub current_price_upper: u128,
+    
+    // Position Metrics
+    /// Liquidity of the DEX position (Raydium/Orca units); 0 without one and
+    /// for Meteora DLMM, whose liquidity is per bin
+    pub liquidity_amount: u128,
+    pub total_fees_earned_a: u64,
+    pub total_fees_earned_b: u64,
+    /// Vault balances plus deployed amounts, valued in token B at
+    /// `value_sqrt_price_x64` (raw units)
+    pub total_value_locked: u64,
+    
+    // Rebalancing History
+    pub last_rebalance_slot: u64,
+    pub last_rebalance_timestamp: i64,
+    pub rebalance_count: u32,
+    
+    // Performance Metrics
+    pub total_return_percentage: i16,
+    pub apy_estimate: u16,
+    
+    // Status & Configuration
+    pub status: PositionStatus,
+    pub auto_rebalance_enabled: bool,
+    pub min_rebalance_interval: u32,
+    
+    // Policy Controls (Compliance)
+    pub max_position_size: u64,
+    pub max_single_trade: u64,
+    pub allowed_dex_programs: Vec<Pubkey>,
+    
+    // Timestamps
+    pub created_at: i64,
+    pub updated_at: i64,
+    
+    // Layout v1 (appended; see `migrate_liquidity_position`)
+    pub layout_version: u8,
+    /// Meteora DLMM bins (Meteora positions only)
+    pub bin_range: Option<BinRange>,
+    pub deployed_amount_a: u64,
+    pub deployed_amount_b: u64,
+    pub pending_decisions: u16,
+    pub emergency_paused: bool,
+    /// Caps on `net_deposited_a` / `net_deposited_b` (raw token units)
+    pub max_amount_a: u64,
+    pub max_amount_b: u64,
+    /// Owner deposits less withdrawals (raw token units), saturating at 0
+    pub net_deposited_a: u64,
+    pub net_deposited_b: u64,
+    /// Q64.64 sqrt price `total_value_locked` was last valued at: the pool
+    /// price on deposits and refreshes, the reference price on rebalances
+    pub value_sqrt_price_x64: u128,
+}
+
+/// Stores AI decision metadata for compliance and auditability
+#[account]
+pub struct RebalanceDecision {
+    // Position Reference
+    pub position: Pubkey,
+    pub decision_bump: u8,
+    
+    // New Price Range
+    pub new_tick_lower: i32,
+    pub new_tick_upper: i32,
+    pub new_price_lower: u128,
+    pub new_price_upper: u128,
+    
+    // AI Model Information (Explainability)
+    pub ai_model_version: String,
+    pub ai_model_hash: [u8; 32],
+    pub prediction_confidence: u16,
+    
+    // Input Data (For Audit Trail)
+    pub market_sentiment_score: i16,
+    pub volatility_metric: u16,
+    pub whale_activity_score: u16,
+    pub on_chain_indicators: Vec<u64>,
+    
+    // Decision Rationale
+    pub decision_reason: String,
+    pub risk_assessment: RiskLevel,
+    
+    // Execution Details
+    pub execution_status: ExecutionStatus,
+    pub execution_tx_signature: Option<String>,
+    pub execution_slippage: Option<u16>,
+    
+    // Compliance & Audit
+    pub requires_human_approval: bool,
+    pub human_approver: Option<Pubkey>,
+    pub approval_timestamp: Option<i64>,
+    
+    // Timestamps
+    pub created_at: i64,
+    pub executed_at: Option<i64>,
+    
+    // Layout v1 (appended; see `migrate_rebalance_decision`)
+    pub layout_version: u8,
+    /// New Meteora DLMM bins (Meteora positions only)
+    pub new_bin_range: Option<BinRange>,
+    /// After this the decision can't be executed and anyone may expire it
+    pub expires_at: i64,
+    /// Q64.64 sqrt pool price (token B per token A) the decision was made at;
+    /// execution is priced against it rather than the pool's spot price
+    pub reference_sqrt_price_x64: u128,
+}
+
+/// Tracks x402 protocol payments for API access
+#[account]
+pub struct X402Payment {
+    // Payment Identity
+    pub payment_id: [u8; 32],
+    pub payment_bump: u8,
+    
+    // Payer Information
+    pub payer: Pubkey,
+    pub payer_wallet: Pubkey,
+    
+    // Payment Details
+    pub amount: u64,
+    pub currency: PaymentCurrency,
+    pub payment_status: PaymentStatus,
+    
+    // x402 Protocol Details
+    pub facilitator: Pubkey,
+    pub facilitator_signature: Option<[u8; 64]>,
+    pub payment_tx_signature: Option<String>,
+    
+    // API Access Details
+    pub api_endpoint: String,
+    pub api_version: String,
+    pub access_granted: bool,
+    pub access_expires_at: Option<i64>,
+    
+    // Timestamps
+    pub requested_at: i64,
+    pub verified_at: Option<i64>,
+    pub settled_at: Option<i64>,
+}
+
+/// Price and access duration of one x402 API endpoint/version
+#[account]
+pub struct X402EndpointPricing {
+    // Endpoint Identity
+    pub endpoint_hash: [u8; 32],
+    pub pricing_bump: u8,
+    pub api_endpoint: String,
+    pub api_version: String,
+    
+    // Pricing (None = currency not accepted)
+    pub price_sol: Option<u64>,
+    pub price_usdc: Option<u64>,
+    pub price_usdt: Option<u64>,
+    
+    // Access
+    pub access_duration: u32,
+    pub enabled: bool,
+    
+    // Timestamps
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+/// Prepaid x402 credit balance of one payer in one currency
+#[account]
+pub struct X402CreditAccount {
+    // Account Identity
+    pub owner: Pubkey,
+    pub currency: PaymentCurrency,
+    pub credit_bump: u8,
+    pub vault: Option<Pubkey>, // Token account holding USDC/USDT credit
+    
+    // Balance
+    pub balance: u64,
+    pub total_deposited: u64,
+    pub total_debited: u64,
+    
+    // Metering
+    pub calls_debited: u64,
+    pub last_debit_at: Option<i64>,
+    
+    // Timestamps
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+/// x402 revenue of one currency, held until each party claims its share
+#[account]
+pub struct X402RevenueVault {
+    // Vault Identity
+    pub currency: PaymentCurrency,
+    pub vault_bump: u8,
+    pub token_vault: Option<Pubkey>, // Token account holding USDC/USDT revenue
+    
+    // Accrued Balances
+    pub treasury_accrued: u64,
+    pub lp_pool_accrued: u64,
+    pub provider_accruals: Vec<RevenueAccrual>,
+    
+    // Totals
+    pub total_revenue: u64,
+    pub total_claimed: u64,
+    pub total_refunded: u64,
+    
+    // Timestamps
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+/// LP share of x402 revenue of one currency, held for LP reward distribution
+#[account]
+pub struct X402LpRewardsPool {
+    pub currency: PaymentCurrency,
+    pub pool_bump: u8,
+    pub token_vault: Option<Pubkey>, // Token account holding USDC/USDT rewards
+    pub total_received: u64,
+    pub total_paid: u64,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+/// Global protocol configuration and parameters
+#[account]
+pub struct ProtocolConfig {
+    // Authority
+    pub authority: Pubkey,
+    pub config_bump: u8,
+    
+    // Fee Structure
+    pub performance_fee_bps: u16,
+    pub protocol_fee_bps: u16,
+    pub fee_recipient: Pubkey,
+    
+    // x402 Configuration
+    /// Legacy single facilitator; moved into `x402_facilitators` by
+    /// `migrate_protocol_config` and always `None` afterwards
+    pub x402_facilitator: Option<Pubkey>,
+    pub x402_min_payment: u64,
+    pub x402_api_base_url: String,
+    
+    // Rebalancing Parameters
+    pub min_rebalance_interval: u32,
+    pub max_rebalance_frequency: u32,
+    pub default_slippage_tolerance_bps: u16,
+    
+    // Risk Management
+    pub max_position_size: u64,
+    pub max_single_trade_size: u64,
+    pub require_human_approval_threshold: u64,
+    
+    // AI Model Configuration
+    pub default_ai_model_version: String,
+    pub ai_model_registry: Vec<Pubkey>,
+    
+    // Compliance
+    pub audit_log_enabled: bool,
+    pub compliance_mode: ComplianceMode,
+    
+    // Timestamps
+    pub created_at: i64,
+    pub updated_at: i64,
+    
+    // Layout v1 (appended; see `migrate_protocol_config`)
+    pub layout_version: u8,
+    pub pending_authority: Option<Pubkey>,
+    pub x402_facilitators: Vec<X402FacilitatorEntry>,
+    pub x402_usdc_mint: Option<Pubkey>,
+    pub x402_usdt_mint: Option<Pubkey>,
+    pub revenue_split: RevenueSplit,
+    /// Jupiter-compatible aggregator allowed for `execute_rebalance` swaps;
+    /// `None` disables swaps
+    pub swap_program: Option<Pubkey>,
+    pub paused: ProtocolPauseFlags,
+    /// AI agent key allowed to create and execute rebalances of any position,
+    /// besides the position's owner
+    pub rebalance_keeper: Option<Pubkey>,
+    /// Open token vaults (revenue vaults, LP rewards pools and credit
+    /// accounts) per payment currency; a currency's mint can't change while
+    /// any are open
+    pub x402_token_vaults: [u32; PAYMENT_CURRENCY_COUNT],
+}
+
+/// User-defined strategy parameters and preferences
+#[account]
+pub struct UserStrategy {
+    // Ownership
+    pub user: Pubkey,
+    pub strategy_bump: u8,
+    
+    // Strategy Configuration
+    pub strategy_name: String,
+    pub strategy_type: StrategyType,
+    pub risk_tolerance: RiskTolerance,
+    
+    // Rebalancing Preferences
+    pub auto_rebalance_enabled: bool,
+    pub rebalance_frequency: RebalanceFrequency,
+    pub price_range_width: u16,
+    
+    // Risk Limits
+    pub max_position_size: Option<u64>,
+    pub max_single_trade: Option<u64>,
+    pub max_slippage_bps: Option<u16>,
+    
+    // Token Preferences
+    pub preferred_tokens: Vec<Pubkey>,
+    pub blacklisted_tokens: Vec<Pubkey>,
+    
+    // AI Model Selection
+    pub preferred_ai_model: Option<String>,
+    pub require_human_approval: bool,
+    
+    // Timestamps
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub enum RiskTolerance {
+    Low,
+    Medium,
+    High,
+}
+
+/// Head of one audited subject's chain of audit log entries
+///
+/// PDA: `["audit_stream", subject]`, where the subject is the protocol config,
+/// a position, an endpoint pricing, a payment, a credit account, a revenue
+/// vault or an LP rewards pool.
+#[account]
+pub struct AuditStream {
+    pub subject: Pubkey,
+    pub stream_bump: u8,
+    
+    // Chain head
+    pub log_count: u64,
+    pub last_hash: [u8; 32],
+}
+
+/// Immutable audit log for compliance and regulatory requirements
+///
+/// PDA: `["audit_log", subject, sequence]`, chained to the previous entry of
+/// the same subject through `prev_event_hash`.
+#[account]
+pub struct AuditLog {
+    // Log Identity
+    pub log_id: [u8; 32],
+    pub log_bump: u8,
+    pub subject: Pubkey,
+    pub sequence: u64,
+    
+    // Event Information
+    pub event_type: AuditEventType,
+    pub position: Option<Pubkey>,
+    pub user: Pubkey,
+    
+    // Event Data
+    pub event_data: Vec<u8>,
+    pub prev_event_hash: [u8; 32],
+    pub event_hash: [u8; 32],
+    
+    // AI Decision Context (if applicable)
+    pub ai_model_version: Option<String>,
+    pub decision_rationale: Option<String>,
+    pub prediction_scores: Option<Vec<u16>>,
+    
+    // Compliance Metadata
+    pub regulatory_jurisdiction: Option<String>,
+    
+    // Timestamps
+    pub created_at: i64,
+    pub slot: u64,
+}
+
+// ============================================================================
+// ERROR TYPES
+// ============================================================================
+
+#[error_code]
+pub enum XLiquidityEngineError {
+    #[msg("Invalid price range")]
+    InvalidPriceRange,
+    #[msg("Position exceeds maximum size")]
+    ExceedsMaxPositionSize,
+    #[msg("Trade exceeds maximum size")]
+    ExceedsMaxTradeSize,
+    #[msg("Position is not active")]
+    PositionNotActive,
+    #[msg("Rebalance too frequent")]
+    RebalanceTooFrequent,
+    #[msg("Invalid execution status")]
+    InvalidExecutionStatus,
+    #[msg("Human approval required")]
+    HumanApprovalRequired,
+    #[msg("Invalid approver")]
+    InvalidApprover,
+    #[msg("Slippage tolerance too high")]
+    SlippageTooHigh,
+    #[msg("Payment amount too small")]
+    PaymentTooSmall,
+    #[msg("Invalid facilitator")]
+    InvalidFacilitator,
+    #[msg("No fees to collect")]
+    NoFeesToCollect,
+    #[msg("Approval not required")]
+    ApprovalNotRequired,
+    #[msg("Audit event data too large")]
+    AuditDataTooLarge,
+    #[msg("Arithmetic overflow")]
+    MathOverflow,
+    #[msg("Audit chain verification failed")]
+    AuditChainBroken,
+    #[msg("Unauthorized")]
+    Unauthorized,
+    #[msg("Invalid config value")]
+    InvalidConfigValue,
+    #[msg("Invalid authority")]
+    InvalidAuthority,
+    #[msg("No pending authority transfer")]
+    NoPendingAuthority,
+    #[msg("Facilitator registry is full")]
+    FacilitatorRegistryFull,
+    #[msg("Payment currency not accepted")]
+    UnsupportedCurrency,
+    #[msg("Invalid facilitator signature")]
+    InvalidFacilitatorSignature,
+    #[msg("Payment authorization expired")]
+    PaymentAuthorizationExpired,
+    #[msg("Invalid payment status")]
+    InvalidPaymentStatus,
+    #[msg("No mint configured for payment currency")]
+    CurrencyMintNotConfigured,
+    #[msg("Token accounts required for this currency")]
+    MissingTokenAccounts,
+    #[msg("Invalid mint")]
+    InvalidMint,
+    #[msg("Invalid token account")]
+    InvalidTokenAccount,
+    #[msg("Revenue vault required to refund settled funds")]
+    MissingRefundSource,
+    #[msg("Endpoint is not priced or disabled")]
+    EndpointNotPriced,
+    #[msg("Credit amount must be greater than zero")]
+    InvalidCreditAmount,
+    #[msg("Insufficient x402 credit balance")]
+    InsufficientCredit,
+    #[msg("x402 access has expired")]
+    X402AccessExpired,
+    #[msg("Payment or credit account does not belong to payer and endpoint")]
+    InvalidAccessAccount,
+    #[msg("Payment access has not expired")]
+    PaymentNotExpired,
+    #[msg("Nothing to claim")]
+    NothingToClaim,
+    #[msg("Insufficient treasury revenue for refund; the fee recipient must co-sign")]
+    InsufficientRevenue,
+    #[msg("Liquidity amount must be greater than zero")]
+    InvalidLiquidityAmount,
+    #[msg("Insufficient liquidity in position vaults")]
+    InsufficientLiquidity,
+    #[msg("Position has a pending rebalance decision")]
+    RebalancePending,
+    #[msg("Position is not paused")]
+    PositionNotPaused,
+    #[msg("Position is under an emergency pause by the authority")]
+    EmergencyPauseActive,
+    #[msg("Protocol is paused for this operation")]
+    ProtocolPaused,
+    #[msg("DEX not supported for this operation")]
+    UnsupportedDex,
+    #[msg("DEX accounts missing or invalid")]
+    InvalidDexAccounts,
+    #[msg("Invalid or missing Meteora bin range")]
+    InvalidBinRange,
+    #[msg("Swap program not allowed or swap accounts invalid")]
+    InvalidSwap,
+    #[msg("Swap spent more than amount_in")]
+    SwapAmountExceeded,
+    #[msg("Slippage exceeds tolerance")]
+    SlippageExceeded,
+    #[msg("Account already uses the current layout")]
+    AccountAlreadyMigrated,
+    #[msg("Rebalance decision has expired")]
+    DecisionExpired,
+    #[msg("Rebalance decision has not expired yet")]
+    DecisionNotExpired,
+    #[msg("Reference price missing or invalid")]
+    InvalidReferencePrice,
+    #[msg("Pool price is too far from the decision's reference price")]
+    PoolPriceDeviation,
+    #[msg("Swap quote is below the reference price less slippage tolerance")]
+    SwapQuoteBelowReference,
+    #[msg("Revenue vault has no free provider accrual slot; providers must claim first")]
+    RevenueAccrualsFull,
+    #[msg("Currency mint can't change while token vaults of it are open")]
+    CurrencyMintInUse,
+    #[msg("Amount exceeds the LP rewards held by the pool")]
+    InsufficientLpRewards,
+}
+
+// ============================================================================
+// INSTRUCTION CONTEXTS
+// ============================================================================
+
+#[derive(Accounts)]
+pub struct InitializeProtocolConfig<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + ProtocolConfig::LEN,
+        seeds = [b"protocol_config"],
+        bump
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    /// CHECK: Fee recipient address
+    pub fee_recipient: AccountInfo<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateProtocolConfig<'info> {
+    #[account(
+        mut,
+        seeds = [b"protocol_config"],
+        bump = config.config_bump,
+        has_one = authority @ XLiquidityEngineError::Unauthorized
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + AuditStream::LEN,
+        seeds = [b"audit_stream".as_ref(), config.key().as_ref()],
+        bump
+    )]
+    pub audit_stream: Box<Account<'info, AuditStream>>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + AuditLog::LEN,
+        seeds = [b"audit_log".as_ref(), config.key().as_ref(), &audit_stream.log_count.to_le_bytes()],
+        bump
+    )]
+    pub audit_log: Box<Account<'info, AuditLog>>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetAiModelRegistry<'info> {
+    #[account(
+        mut,
+        seeds = [b"protocol_config"],
+        bump = config.config_bump,
+        has_one = authority @ XLiquidityEngineError::Unauthorized
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + AuditStream::LEN,
+        seeds = [b"audit_stream".as_ref(), config.key().as_ref()],
+        bump
+    )]
+    pub audit_stream: Box<Account<'info, AuditStream>>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + AuditLog::LEN,
+        seeds = [b"audit_log".as_ref(), config.key().as_ref(), &audit_stream.log_count.to_le_bytes()],
+        bump
+    )]
+    pub audit_log: Box<Account<'info, AuditLog>>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetProtocolPaused<'info> {
+    #[account(
+        mut,
+        seeds = [b"protocol_config"],
+        bump = config.config_bump,
+        has_one = authority @ XLiquidityEngineError::Unauthorized
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + AuditStream::LEN,
+        seeds = [b"audit_stream".as_ref(), config.key().as_ref()],
+        bump
+    )]
+    pub audit_stream: Box<Account<'info, AuditStream>>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + AuditLog::LEN,
+        seeds = [b"audit_log".as_ref(), config.key().as_ref(), &audit_stream.log_count.to_le_bytes()],
+        bump
+    )]
+    pub audit_log: Box<Account<'info, AuditLog>>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ProposeAuthority<'info> {
+    #[account(
+        mut,
+        seeds = [b"protocol_config"],
+        bump = config.config_bump,
+        has_one = authority @ XLiquidityEngineError::Unauthorized
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + AuditStream::LEN,
+        seeds = [b"audit_stream".as_ref(), config.key().as_ref()],
+        bump
+    )]
+    pub audit_stream: Box<Account<'info, AuditStream>>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + AuditLog::LEN,
+        seeds = [b"audit_log".as_ref(), config.key().as_ref(), &audit_stream.log_count.to_le_bytes()],
+        bump
+    )]
+    pub audit_log: Box<Account<'info, AuditLog>>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptAuthority<'info> {
+    #[account(
+        mut,
+        seeds = [b"protocol_config"],
+        bump = config.config_bump,
+        constraint = config.pending_authority == Some(new_authority.key()) @ XLiquidityEngineError::NoPendingAuthority
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+    
+    #[account(mut)]
+    pub new_authority: Signer<'info>,
+    
+    #[account(
+        init_if_needed,
+        payer = new_authority,
+        space = 8 + AuditStream::LEN,
+        seeds = [b"audit_stream".as_ref(), config.key().as_ref()],
+        bump
+    )]
+    pub audit_stream: Box<Account<'info, AuditStream>>,
+    
+    #[account(
+        init,
+        payer = new_authority,
+        space = 8 + AuditLog::LEN,
+        seeds = [b"audit_log".as_ref(), config.key().as_ref(), &audit_stream.log_count.to_le_bytes()],
+        bump
+    )]
+    pub audit_log: Box<Account<'info, AuditLog>>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CancelAuthorityTransfer<'info> {
+    #[account(
+        mut,
+        seeds = [b"protocol_config"],
+        bump = config.config_bump,
+        has_one = authority @ XLiquidityEngineError::Unauthorized
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + AuditStream::LEN,
+        seeds = [b"audit_stream".as_ref(), config.key().as_ref()],
+        bump
+    )]
+    pub audit_stream: Box<Account<'info, AuditStream>>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + AuditLog::LEN,
+        seeds = [b"audit_log".as_ref(), config.key().as_ref(), &audit_stream.log_count.to_le_bytes()],
+        bump
+    )]
+    pub audit_log: Box<Account<'info, AuditLog>>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetX402Facilitator<'info> {
+    #[account(
+        mut,
+        seeds = [b"protocol_config"],
+        bump = config.config_bump,
+        has_one = authority @ XLiquidityEngineError::Unauthorized
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + AuditStream::LEN,
+        seeds = [b"audit_stream".as_ref(), config.key().as_ref()],
+        bump
+    )]
+    pub audit_stream: Box<Account<'info, AuditStream>>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + AuditLog::LEN,
+        seeds = [b"audit_log".as_ref(), config.key().as_ref(), &audit_stream.log_count.to_le_bytes()],
+        bump
+    )]
+    pub audit_log: Box<Account<'info, AuditLog>>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(position_index: u8, token_a: Pubkey, token_b: Pubkey)]
+pub struct CreateLiquidityPosition<'info> {
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + LiquidityPosition::LEN,
+        seeds = [b"liquidity_position", owner.key().as_ref(), &[position_index]],
+        bump
+    )]
+    pub position: Account<'info, LiquidityPosition>,
+    
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = config.config_bump,
+        constraint = !config.paused.new_positions @ XLiquidityEngineError::ProtocolPaused
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+    
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    
+    #[account(address = token_a @ XLiquidityEngineError::InvalidMint)]
+    pub token_a_mint: Box<Account<'info, Mint>>,
+    
+    #[account(address = token_b @ XLiquidityEngineError::InvalidMint)]
+    pub token_b_mint: Box<Account<'info, Mint>>,
+    
+    /// Token A vault, owned by the position PDA
+    #[account(
+        init,
+        payer = owner,
+        seeds = [b"position_vault".as_ref(), position.key().as_ref(), token_a.as_ref()],
+        bump,
+        token::mint = token_a_mint,
+        token::authority = position
+    )]
+    pub token_a_vault: Box<Account<'info, TokenAccount>>,
+    
+    /// Token B vault, owned by the position PDA
+    #[account(
+        init,
+        payer = owner,
+        seeds = [b"position_vault".as_ref(), position.key().as_ref(), token_b.as_ref()],
+        bump,
+        token::mint = token_b_mint,
+        token::authority = position
+    )]
+    pub token_b_vault: Box<Account<'info, TokenAccount>>,
+    
+    /// CHECK: DEX pool address
+    pub pool: AccountInfo<'info>,
+    
+    pub token_program: Program<'info, Token>,
+    
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + AuditStream::LEN,
+        seeds = [b"audit_stream".as_ref(), position.key().as_ref()],
+        bump
+    )]
+    pub audit_stream: Box<Account<'info, AuditStream>>,
+    
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + AuditLog::LEN,
+        seeds = [b"audit_log".as_ref(), position.key().as_ref(), &audit_stream.log_count.to_le_bytes()],
+        bump
+    )]
+    pub audit_log: Box<Account<'info, AuditLog>>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(position_index: u8)]
+pub struct DepositLiquidity<'info> {
+    #[account(
+        mut,
+        seeds = [b"liquidity_position", owner.key().as_ref(), &[position_index]],
+        bump = position.position_bump,
+        has_one = owner @ XLiquidityEngineError::Unauthorized,
+        has_one = token_a_vault,
+        has_one = token_b_vault
+    )]
+    pub position: Account<'info, LiquidityPosition>,
+    
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = config.config_bump,
+        constraint = !config.paused.new_positions @ XLiquidityEngineError::ProtocolPaused
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+    
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    
+    /// CHECK: the position's DEX pool, read for its price by `dex::pool_price`
+    #[account(address = position.pool_address @ XLiquidityEngineError::InvalidDexAccounts)]
+    pub pool: UncheckedAccount<'info>,
+    
+    #[account(address = position.token_a @ XLiquidityEngineError::InvalidMint)]
+    pub token_a_mint: Box<Account<'info, Mint>>,
+    
+    #[account(address = position.token_b @ XLiquidityEngineError::InvalidMint)]
+    pub token_b_mint: Box<Account<'info, Mint>>,
+    
+    #[account(mut)]
+    pub token_a_vault: Box<Account<'info, TokenAccount>>,
+    
+    #[account(mut)]
+    pub token_b_vault: Box<Account<'info, TokenAccount>>,
+    
+    #[account(
+        mut,
+        token::mint = token_a_mint,
+        token::authority = owner
+    )]
+    pub owner_token_a: Box<Account<'info, TokenAccount>>,
+    
+    #[account(
+        mut,
+        token::mint = token_b_mint,
+        token::authority = owner
+    )]
+    pub owner_token_b: Box<Account<'info, TokenAccount>>,
+    
+    pub token_program: Program<'info, Token>,
+    
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + AuditStream::LEN,
+        seeds = [b"audit_stream".as_ref(), position.key().as_ref()],
+        bump
+    )]
+    pub audit_stream: Box<Account<'info, AuditStream>>,
+    
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + AuditLog::LEN,
+        seeds = [b"audit_log".as_ref(), position.key().as_ref(), &audit_stream.log_count.to_le_bytes()],
+        bump
+    )]
+    pub audit_log: Box<Account<'info, AuditLog>>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(position_index: u8)]
+pub struct WithdrawLiquidity<'info> {
+    #[account(
+        mut,
+        seeds = [b"liquidity_position", owner.key().as_ref(), &[position_index]],
+        bump = position.position_bump,
+        has_one = owner @ XLiquidityEngineError::Unauthorized,
+        has_one = token_a_vault,
+        has_one = token_b_vault
+    )]
+    pub position: Account<'info, LiquidityPosition>,
+    
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = config.config_bump
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+    
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    
+    #[account(address = position.token_a @ XLiquidityEngineError::InvalidMint)]
+    pub token_a_mint: Box<Account<'info, Mint>>,
+    
+    #[account(address = position.token_b @ XLiquidityEngineError::InvalidMint)]
+    pub token_b_mint: Box<Account<'info, Mint>>,
+    
+    #[account(mut)]
+    pub token_a_vault: Box<Account<'info, TokenAccount>>,
+    
+    #[account(mut)]
+    pub token_b_vault: Box<Account<'info, TokenAccount>>,
+    
+    #[account(
+        mut,
+        token::mint = token_a_mint,
+        token::authority = owner
+    )]
+    pub owner_token_a: Box<Account<'info, TokenAccount>>,
+    
+    #[account(
+        mut,
+        token::mint = token_b_mint,
+        token::authority = owner
+    )]
+    pub owner_token_b: Box<Account<'info, TokenAccount>>,
+    
+    pub token_program: Program<'info, Token>,
+    
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + AuditStream::LEN,
+        seeds = [b"audit_stream".as_ref(), position.key().as_ref()],
+        bump
+    )]
+    pub audit_stream: Box<Account<'info, AuditStream>>,
+    
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + AuditLog::LEN,
+        seeds = [b"audit_log".as_ref(), position.key().as_ref(), &audit_stream.log_count.to_le_bytes()],
+        bump
+    )]
+    pub audit_log: Box<Account<'info, AuditLog>>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(position_index: u8)]
+pub struct CloseLiquidityPosition<'info> {
+    #[account(
+        mut,
+        seeds = [b"liquidity_position", owner.key().as_ref(), &[position_index]],
+        bump = position.position_bump,
+        has_one = owner @ XLiquidityEngineError::Unauthorized,
+        has_one = token_a_vault,
+        has_one = token_b_vault
+    )]
+    pub position: Account<'info, LiquidityPosition>,
+    
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = config.config_bump
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+    
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    
+    #[account(address = position.token_a @ XLiquidityEngineError::InvalidMint)]
+    pub token_a_mint: Box<Account<'info, Mint>>,
+    
+    #[account(address = position.token_b @ XLiquidityEngineError::InvalidMint)]
+    pub token_b_mint: Box<Account<'info, Mint>>,
+    
+    #[account(mut)]
+    pub token_a_vault: Box<Account<'info, TokenAccount>>,
+    
+    #[account(mut)]
+    pub token_b_vault: Box<Account<'info, TokenAccount>>,
+    
+    #[account(
+        mut,
+        token::mint = token_a_mint,
+        token::authority = owner
+    )]
+    pub owner_token_a: Box<Account<'info, TokenAccount>>,
+    
+    #[account(
+        mut,
+        token::mint = token_b_mint,
+        token::authority = owner
+    )]
+    pub owner_token_b: Box<Account<'info, TokenAccount>>,
+    
+    pub token_program: Program<'info, Token>,
+    
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + AuditStream::LEN,
+        seeds = [b"audit_stream".as_ref(), position.key().as_ref()],
+        bump
+    )]
+    pub audit_stream: Box<Account<'info, AuditStream>>,
+    
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + AuditLog::LEN,
+        seeds = [b"audit_log".as_ref(), position.key().as_ref(), &audit_stream.log_count.to_le_bytes()],
+        bump
+    )]
+    pub audit_log: Box<Account<'info, AuditLog>>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(position_index: u8)]
+pub struct PausePosition<'info> {
+    #[account(
+        mut,
+        seeds = [b"liquidity_position", owner.key().as_ref(), &[position_index]],
+        bump = position.position_bump,
+        has_one = owner @ XLiquidityEngineError::Unauthorized
+    )]
+    pub position: Account<'info, LiquidityPosition>,
+    
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = config.config_bump
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+    
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + AuditStream::LEN,
+        seeds = [b"audit_stream".as_ref(), position.key().as_ref()],
+        bump
+    )]
+    pub audit_stream: Box<Account<'info, AuditStream>>,
+    
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + AuditLog::LEN,
+        seeds = [b"audit_log".as_ref(), position.key().as_ref(), &audit_stream.log_count.to_le_bytes()],
+        bump
+    )]
+    pub audit_log: Box<Account<'info, AuditLog>>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(position_index: u8)]
+pub struct EmergencyPausePosition<'info> {
+    #[account(
+        mut,
+        seeds = [b"liquidity_position", position.owner.as_ref(), &[position_index]],
+        bump = position.position_bump
+    )]
+    pub position: Account<'info, LiquidityPosition>,
+    
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = config.config_bump,
+        has_one = authority @ XLiquidityEngineError::Unauthorized
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + AuditStream::LEN,
+        seeds = [b"audit_stream".as_ref(), position.key().as_ref()],
+        bump
+    )]
+    pub audit_stream: Box<Account<'info, AuditStream>>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + AuditLog::LEN,
+        seeds = [b"audit_log".as_ref(), position.key().as_ref(), &audit_stream.log_count.to_le_bytes()],
+        bump
+    )]
+    pub audit_log: Box<Account<'info, AuditLog>>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(position_index: u8, decision_index: u32)]
+pub struct CreateRebalanceDecision<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + RebalanceDecision::LEN,
+        seeds = [b"rebalance_decision", position.key().as_ref(), &decision_index.to_le_bytes()],
+        bump
+    )]
+    pub decision: Account<'info, RebalanceDecision>,
+    
+    #[account(
+        mut,
+        seeds = [b"liquidity_position", position.owner.as_ref(), &[position_index]],
+        bump = position.position_bump
+    )]
+    pub position: Account<'info, LiquidityPosition>,
+    
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = config.config_bump,
+        constraint = !config.paused.rebalancing @ XLiquidityEngineError::ProtocolPaused
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+    
+    /// Position owner or the protocol's rebalance keeper
+    #[account(
+        mut,
+        constraint = config.can_rebalance(&position, payer.key) @ XLiquidityEngineError::Unauthorized
+    )]
+    pub payer: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(position_index: u8, decision_index: u32)]
+pub struct ExecuteRebalance<'info> {
+    #[account(
+        mut,
+        seeds = [b"rebalance_decision", position.key().as_ref(), &decision_index.to_le_bytes()],
+        bump = decision.decision_bump
+    )]
+    pub decision: Account<'info, RebalanceDecision>,
+    
+    #[account(
+        mut,
+        seeds = [b"liquidity_position", position.owner.as_ref(), &[position_index]],
+        bump = position.position_bump,
+        has_one = token_a_vault,
+        has_one = token_b_vault
+    )]
+    pub position: Account<'info, LiquidityPosition>,
+    
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = config.config_bump,
+        constraint = !config.paused.rebalancing @ XLiquidityEngineError::ProtocolPaused
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+    
+    /// CHECK: Approver (optional, only needed if human approval required)
+    pub approver: Option<Signer<'info>>,
+    
+    #[account(address = position.token_a @ XLiquidityEngineError::InvalidMint)]
+    pub token_a_mint: Box<Account<'info, Mint>>,
+    
+    #[account(address = position.token_b @ XLiquidityEngineError::InvalidMint)]
+    pub token_b_mint: Box<Account<'info, Mint>>,
+    
+    #[account(mut)]
+    pub token_a_vault: Box<Account<'info, TokenAccount>>,
+    
+    #[account(mut)]
+    pub token_b_vault: Box<Account<'info, TokenAccount>>,
+    
+    pub token_program: Program<'info, Token>,
+    
+    /// Position owner or the protocol's rebalance keeper
+    #[account(
+        mut,
+        constraint = config.can_rebalance(&position, payer.key) @ XLiquidityEngineError::Unauthorized
+    )]
+    pub payer: Signer<'info>,
+    
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + AuditStream::LEN,
+        seeds = [b"audit_stream".as_ref(), position.key().as_ref()],
+        bump
+    )]
+    pub audit_stream: Box<Account<'info, AuditStream>>,
+    
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + AuditLog::LEN,
+        seeds = [b"audit_log".as_ref(), position.key().as_ref(), &audit_stream.log_count.to_le_bytes()],
+        bump
+    )]
+    pub audit_log: Box<Account<'info, AuditLog>>,
+    
+    pub system_program: Program<'info, System>,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq)]
-pub enum ExecutionStatus {
-    Pending,
-    Executed,
-    Failed,
-    Rejected,
-    Cancelled,
+#[derive(Accounts)]
+#[instruction(api_endpoint: String, api_version: String)]
+pub struct CreateX402EndpointPricing<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + X402EndpointPricing::LEN,
+        seeds = [b"x402_pricing".as_ref(), &x402_endpoint_hash(&api_endpoint, &api_version)],
+        bump
+    )]
+    pub pricing: Account<'info, X402EndpointPricing>,
+    
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = config.config_bump,
+        has_one = authority @ XLiquidityEngineError::Unauthorized
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + AuditStream::LEN,
+        seeds = [b"audit_stream".as_ref(), pricing.key().as_ref()],
+        bump
+    )]
+    pub audit_stream: Box<Account<'info, AuditStream>>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + AuditLog::LEN,
+        seeds = [b"audit_log".as_ref(), pricing.key().as_ref(), &audit_stream.log_count.to_le_bytes()],
+        bump
+    )]
+    pub audit_log: Box<Account<'info, AuditLog>>,
+    
+    pub system_program: Program<'info, System>,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
-pub enum PaymentStatus {
-    Pending,
-    Verified,
-    Settled,
-    Failed,
-    Refunded,
+#[derive(Accounts)]
+pub struct UpdateX402EndpointPricing<'info> {
+    #[account(
+        mut,
+        seeds = [b"x402_pricing".as_ref(), pricing.endpoint_hash.as_ref()],
+        bump = pricing.pricing_bump
+    )]
+    pub pricing: Account<'info, X402EndpointPricing>,
+    
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = config.config_bump,
+        has_one = authority @ XLiquidityEngineError::Unauthorized
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + AuditStream::LEN,
+        seeds = [b"audit_stream".as_ref(), pricing.key().as_ref()],
+        bump
+    )]
+    pub audit_stream: Box<Account<'info, AuditStream>>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + AuditLog::LEN,
+        seeds = [b"audit_log".as_ref(), pricing.key().as_ref(), &audit_stream.log_count.to_le_bytes()],
+        bump
+    )]
+    pub audit_log: Box<Account<'info, AuditLog>>,
+    
+    pub system_program: Program<'info, System>,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
-pub enum PaymentCurrency {
-    SOL,
-    USDC,
-    USDT,
+#[derive(Accounts)]
+#[instruction(
+    payment_id: [u8; 32],
+    amount: u64,
+    currency: PaymentCurrency,
+    api_endpoint: String,
+    api_version: String
+)]
+pub struct VerifyX402Payment<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + X402Payment::LEN,
+        seeds = [b"x402_payment", payment_id.as_ref()],
+        bump
+    )]
+    pub payment: Account<'info, X402Payment>,
+    
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = config.config_bump,
+        constraint = !config.paused.x402_payments @ XLiquidityEngineError::ProtocolPaused
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+    
+    #[account(
+        seeds = [b"x402_pricing".as_ref(), &x402_endpoint_hash(&api_endpoint, &api_version)],
+        bump = pricing.pricing_bump
+    )]
+    pub pricing: Account<'info, X402EndpointPricing>,
+    
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    
+    /// CHECK: Payer wallet
+    pub payer_wallet: AccountInfo<'info>,
+    
+    /// CHECK: x402 Facilitator (authenticated by its ed25519 signature)
+    pub facilitator: AccountInfo<'info>,
+    
+    /// CHECK: Instructions sysvar, used to read the ed25519 signature instruction
+    #[account(address = solana_sdk_ids::sysvar::instructions::ID)]
+    pub instructions_sysvar: AccountInfo<'info>,
+    
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + AuditStream::LEN,
+        seeds = [b"audit_stream".as_ref(), payment.key().as_ref()],
+        bump
+    )]
+    pub audit_stream: Box<Account<'info, AuditStream>>,
+    
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + AuditLog::LEN,
+        seeds = [b"audit_log".as_ref(), payment.key().as_ref(), &audit_stream.log_count.to_le_bytes()],
+        bump
+    )]
+    pub audit_log: Box<Account<'info, AuditLog>>,
+    
+    pub system_program: Program<'info, System>,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq)]
-pub enum RiskLevel {
-    Low,
-    Medium,
-    High,
-    Critical,
+#[derive(Accounts)]
+pub struct SettleX402Payment<'info> {
+    #[account(
+        mut,
+        seeds = [b"x402_payment", payment.payment_id.as_ref()],
+        bump = payment.payment_bump,
+        has_one = payer_wallet @ XLiquidityEngineError::Unauthorized
+    )]
+    pub payment: Account<'info, X402Payment>,
+    
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = config.config_bump,
+        constraint = !config.paused.x402_payments @ XLiquidityEngineError::ProtocolPaused
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+    
+    #[account(mut)]
+    pub payer_wallet: Signer<'info>,
+    
+    #[account(
+        mut,
+        seeds = [b"x402_revenue".as_ref(), &[payment.currency as u8]],
+        bump = revenue_vault.vault_bump
+    )]
+    pub revenue_vault: Account<'info, X402RevenueVault>,
+    
+    /// Token accounts below are only required for USDC/USDT settlements
+    pub mint: Option<Account<'info, Mint>>,
+    
+    #[account(mut)]
+    pub payer_token_account: Option<Account<'info, TokenAccount>>,
+    
+    #[account(mut)]
+    pub revenue_token_vault: Option<Account<'info, TokenAccount>>,
+    
+    pub token_program: Option<Program<'info, Token>>,
+    
+    #[account(
+        init_if_needed,
+        payer = payer_wallet,
+        space = 8 + AuditStream::LEN,
+        seeds = [b"audit_stream".as_ref(), payment.key().as_ref()],
+        bump
+    )]
+    pub audit_stream: Box<Account<'info, AuditStream>>,
+    
+    #[account(
+        init,
+        payer = payer_wallet,
+        space = 8 + AuditLog::LEN,
+        seeds = [b"audit_log".as_ref(), payment.key().as_ref(), &audit_stream.log_count.to_le_bytes()],
+        bump
+    )]
+    pub audit_log: Box<Account<'info, AuditLog>>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RefundX402Payment<'info> {
+    #[account(
+        mut,
+        seeds = [b"x402_payment", payment.payment_id.as_ref()],
+        bump = payment.payment_bump,
+        has_one = payer_wallet @ XLiquidityEngineError::Unauthorized
+    )]
+    pub payment: Account<'info, X402Payment>,
+    
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = config.config_bump
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+    
+    /// Protocol authority or the payment's (active) facilitator
+    #[account(mut)]
+    pub refund_authority: Signer<'info>,
+    
+    /// CHECK: Payer wallet (receives the refund)
+    #[account(mut)]
+    pub payer_wallet: AccountInfo<'info>,
+    
+    /// Revenue vault of the payment currency, only required to refund settled funds
+    #[account(mut)]
+    pub revenue_vault: Option<Account<'info, X402RevenueVault>>,
+    
+    /// Fee recipient, only required when the treasury's accrued share no
+    /// longer covers the refund
+    #[account(
+        mut,
+        address = config.fee_recipient @ XLiquidityEngineError::Unauthorized
+    )]
+    pub treasury: Option<Signer<'info>>,
+    
+    /// Token accounts below are only required for settled USDC/USDT payments
+    pub mint: Option<Account<'info, Mint>>,
+    
+    #[account(mut)]
+    pub revenue_token_vault: Option<Account<'info, TokenAccount>>,
+    
+    #[account(mut)]
+    pub payer_token_account: Option<Account<'info, TokenAccount>>,
+    
+    /// Fee recipient's token account, paying the part of a USDC/USDT refund
+    /// the revenue vault no longer holds
+    #[account(mut)]
+    pub treasury_token_account: Option<Account<'info, TokenAccount>>,
+    
+    pub token_program: Option<Program<'info, Token>>,
+    
+    #[account(
+        init_if_needed,
+        payer = refund_authority,
+        space = 8 + AuditStream::LEN,
+        seeds = [b"audit_stream".as_ref(), payment.key().as_ref()],
+        bump
+    )]
+    pub audit_stream: Box<Account<'info, AuditStream>>,
+    
+    #[account(
+        init,
+        payer = refund_authority,
+        space = 8 + AuditLog::LEN,
+        seeds = [b"audit_log".as_ref(), payment.key().as_ref(), &audit_stream.log_count.to_le_bytes()],
+        bump
+    )]
+    pub audit_log: Box<Account<'info, AuditLog>>,
+    
+    pub system_program: Program<'info, System>,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
-pub enum StrategyType {
-    Conservative,
-    Balanced,
-    Aggressive,
-    Custom,
+#[derive(Accounts)]
+#[instruction(currency: PaymentCurrency)]
+pub struct OpenX402CreditAccount<'info> {
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + X402CreditAccount::LEN,
+        seeds = [b"x402_credit".as_ref(), owner.key().as_ref(), &[currency as u8]],
+        bump
+    )]
+    pub credit: Account<'info, X402CreditAccount>,
+    
+    #[account(
+        mut,
+        seeds = [b"protocol_config"],
+        bump = config.config_bump,
+        constraint = !config.paused.x402_payments @ XLiquidityEngineError::ProtocolPaused
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+    
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    
+    /// Token account owned by the credit PDA, only required for USDC/USDT
+    pub credit_vault: Option<Account<'info, TokenAccount>>,
+    
+    pub system_program: Program<'info, System>,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
-pub enum RebalanceFrequency {
-    OnSignal,
-    Daily,
-    Weekly,
-    Monthly,
-    Manual,
+#[derive(Accounts)]
+pub struct DepositX402Credit<'info> {
+    #[account(
+        mut,
+        seeds = [b"x402_credit".as_ref(), owner.key().as_ref(), &[credit.currency as u8]],
+        bump = credit.credit_bump,
+        has_one = owner @ XLiquidityEngineError::Unauthorized
+    )]
+    pub credit: Account<'info, X402CreditAccount>,
+    
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = config.config_bump,
+        constraint = !config.paused.x402_payments @ XLiquidityEngineError::ProtocolPaused
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+    
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    
+    /// Token accounts below are only required for USDC/USDT credit
+    pub mint: Option<Account<'info, Mint>>,
+    
+    #[account(mut)]
+    pub owner_token_account: Option<Account<'info, TokenAccount>>,
+    
+    #[account(mut)]
+    pub credit_vault: Option<Account<'info, TokenAccount>>,
+    
+    pub token_program: Option<Program<'info, Token>>,
+    
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + AuditStream::LEN,
+        seeds = [b"audit_stream".as_ref(), credit.key().as_ref()],
+        bump
+    )]
+    pub audit_stream: Box<Account<'info, AuditStream>>,
+    
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + AuditLog::LEN,
+        seeds = [b"audit_log".as_ref(), credit.key().as_ref(), &audit_stream.log_count.to_le_bytes()],
+        bump
+    )]
+    pub audit_log: Box<Account<'info, AuditLog>>,
+    
+    pub system_program: Program<'info, System>,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
-pub enum ComplianceMode {
-    Basic,
-    Enhanced,
-    Full,
+#[derive(Accounts)]
+pub struct WithdrawX402Credit<'info> {
+    #[account(
+        mut,
+        seeds = [b"x402_credit".as_ref(), owner.key().as_ref(), &[credit.currency as u8]],
+        bump = credit.credit_bump,
+        has_one = owner @ XLiquidityEngineError::Unauthorized
+    )]
+    pub credit: Account<'info, X402CreditAccount>,
+    
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = config.config_bump
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+    
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    
+    /// Token accounts below are only required for USDC/USDT credit
+    pub mint: Option<Account<'info, Mint>>,
+    
+    #[account(mut)]
+    pub owner_token_account: Option<Account<'info, TokenAccount>>,
+    
+    #[account(mut)]
+    pub credit_vault: Option<Account<'info, TokenAccount>>,
+    
+    pub token_program: Option<Program<'info, Token>>,
+    
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + AuditStream::LEN,
+        seeds = [b"audit_stream".as_ref(), credit.key().as_ref()],
+        bump
+    )]
+    pub audit_stream: Box<Account<'info, AuditStream>>,
+    
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + AuditLog::LEN,
+        seeds = [b"audit_log".as_ref(), credit.key().as_ref(), &audit_stream.log_count.to_le_bytes()],
+        bump
+    )]
+    pub audit_log: Box<Account<'info, AuditLog>>,
+    
+    pub system_program: Program<'info, System>,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug)]
-pub enum AuditEventType {
-    PositionCreated,
-    PositionClosed,
-    Rebalanced,
-    FeesCollected,
-    PaymentReceived,
-    PolicyViolation,
-    HumanApprovalRequired,
-    HumanApprovalGranted,
+#[derive(Accounts)]
+pub struct CloseX402CreditAccount<'info> {
+    #[account(
+        mut,
+        seeds = [b"x402_credit".as_ref(), owner.key().as_ref(), &[credit.currency as u8]],
+        bump = credit.credit_bump,
+        has_one = owner @ XLiquidityEngineError::Unauthorized,
+        close = owner
+    )]
+    pub credit: Account<'info, X402CreditAccount>,
+    
+    #[account(
+        mut,
+        seeds = [b"protocol_config"],
+        bump = config.config_bump
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+    
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    
+    /// Token accounts below are only required for USDC/USDT credit
+    pub mint: Option<Account<'info, Mint>>,
+    
+    #[account(mut)]
+    pub owner_token_account: Option<Account<'info, TokenAccount>>,
+    
+    #[account(mut)]
+    pub credit_vault: Option<Account<'info, TokenAccount>>,
+    
+    pub token_program: Option<Program<'info, Token>>,
+    
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + AuditStream::LEN,
+        seeds = [b"audit_stream".as_ref(), credit.key().as_ref()],
+        bump
+    )]
+    pub audit_stream: Box<Account<'info, AuditStream>>,
+    
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + AuditLog::LEN,
+        seeds = [b"audit_log".as_ref(), credit.key().as_ref(), &audit_stream.log_count.to_le_bytes()],
+        bump
+    )]
+    pub audit_log: Box<Account<'info, AuditLog>>,
+    
+    pub system_program: Program<'info, System>,
 }
 
-// ============================================================================
-// ACCOUNT STRUCTURES
-// ============================================================================
-
-/// Tracks individual LP positions managed by the protocol
-#[account]
-pub struct LiquidityPosition {
-    // Ownership & Identity
-    pub owner: Pubkey,
-    pub position_bump: u8,
+#[derive(Accounts)]
+#[instruction(call_count: u32, api_endpoint: String, api_version: String)]
+pub struct DebitX402Credit<'info> {
+    #[account(
+        mut,
+        seeds = [b"x402_credit".as_ref(), credit.owner.as_ref(), &[credit.currency as u8]],
+        bump = credit.credit_bump
+    )]
+    pub credit: Account<'info, X402CreditAccount>,
     
-    // Token Pair
-    pub token_a: Pubkey,
-    pub token_b: Pubkey,
-    pub token_a_vault: Pubkey,
-    pub token_b_vault: Pubkey,
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = config.config_bump,
+        constraint = !config.paused.x402_payments @ XLiquidityEngineError::ProtocolPaused
+    )]
+    pub config: Account<'info, ProtocolConfig>,
     
-    // DEX Integration
-    pub dex: DexType,
-    pub pool_address: Pubkey,
-    pub position_nft: Option<Pubkey>,
+    #[account(
+        seeds = [b"x402_pricing".as_ref(), &x402_endpoint_hash(&api_endpoint, &api_version)],
+        bump = pricing.pricing_bump
+    )]
+    pub pricing: Account<'info, X402EndpointPricing>,
     
-    // Price Range (Concentrated Liquidity)
-    pub current_tick_lower: i32,
-    pub current_tick_upper: i32,
-    pub current_price_lower: u128,
-    pub current_price_upper: u128,
+    /// Registered facilitator metering the calls
+    #[account(mut)]
+    pub facilitator: Signer<'info>,
     
-    // Position Metrics
-    pub liquidity_amount: u128,
-    pub total_fees_earned_a: u64,
-    pub total_fees_earned_b: u64,
-    pub total_value_locked: u64,
+    #[account(
+        mut,
+        seeds = [b"x402_revenue".as_ref(), &[credit.currency as u8]],
+        bump = revenue_vault.vault_bump
+    )]
+    pub revenue_vault: Account<'info, X402RevenueVault>,
     
-    // Rebalancing History
-    pub last_rebalance_slot: u64,
-    pub last_rebalance_timestamp: i64,
-    pub rebalance_count: u32,
+    /// Token accounts below are only required for USDC/USDT credit
+    pub mint: Option<Account<'info, Mint>>,
     
-    // Performance Metrics
-    pub total_return_percentage: i16,
-    pub apy_estimate: u16,
+    #[account(mut)]
+    pub credit_vault: Option<Account<'info, TokenAccount>>,
     
-    // Status & Configuration
-    pub status: PositionStatus,
-    pub auto_rebalance_enabled: bool,
-    pub min_rebalance_interval: u32,
+    #[account(mut)]
+    pub revenue_token_vault: Option<Account<'info, TokenAccount>>,
     
-    // Policy Controls (Compliance)
-    pub max_position_size: u64,
-    pub max_single_trade: u64,
-    pub allowed_dex_programs: Vec<Pubkey>,
+    pub token_program: Option<Program<'info, Token>>,
     
-    // Timestamps
-    pub created_at: i64,
-    pub updated_at: i64,
+    #[account(
+        init_if_needed,
+        payer = facilitator,
+        space = 8 + AuditStream::LEN,
+        seeds = [b"audit_stream".as_ref(), credit.key().as_ref()],
+        bump
+    )]
+    pub audit_stream: Box<Account<'info, AuditStream>>,
+    
+    #[account(
+        init,
+        payer = facilitator,
+        space = 8 + AuditLog::LEN,
+        seeds = [b"audit_log".as_ref(), credit.key().as_ref(), &audit_stream.log_count.to_le_bytes()],
+        bump
+    )]
+    pub audit_log: Box<Account<'info, AuditLog>>,
+    
+    pub system_program: Program<'info, System>,
 }
 
-/// Stores AI decision metadata for compliance and auditability
-#[account]
-pub struct RebalanceDecision {
-    // Position Reference
-    pub position: Pubkey,
-    pub decision_bump: u8,
+#[derive(Accounts)]
+#[instruction(payer: Pubkey, api_endpoint: String, api_version: String)]
+pub struct CheckX402Access<'info> {
+    #[account(
+        seeds = [b"x402_pricing".as_ref(), &x402_endpoint_hash(&api_endpoint, &api_version)],
+        bump = pricing.pricing_bump
+    )]
+    pub pricing: Account<'info, X402EndpointPricing>,
     
-    // New Price Range
-    pub new_tick_lower: i32,
-    pub new_tick_upper: i32,
-    pub new_price_lower: u128,
-    pub new_price_upper: u128,
+    /// Payment granting access to the endpoint, if any
+    pub payment: Option<Account<'info, X402Payment>>,
     
-    // AI Model Information (Explainability)
-    pub ai_model_version: String,
-    pub ai_model_hash: [u8; 32],
-    pub prediction_confidence: u16,
+    /// Prepaid credit account of the payer, if any
+    pub credit: Option<Account<'info, X402CreditAccount>>,
+}
+
+#[derive(Accounts)]
+pub struct CloseExpiredPayment<'info> {
+    #[account(
+        mut,
+        seeds = [b"x402_payment", payment.payment_id.as_ref()],
+        bump = payment.payment_bump,
+        has_one = payer @ XLiquidityEngineError::Unauthorized,
+        close = payer
+    )]
+    pub payment: Account<'info, X402Payment>,
     
-    // Input Data (For Audit Trail)
-    pub market_sentiment_score: i16,
-    pub volatility_metric: u16,
-    pub whale_activity_score: u16,
-    pub on_chain_indicators: Vec<u64>,
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = config.config_bump
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+    
+    /// CHECK: Original payer (receives the reclaimed rent)
+    #[account(mut)]
+    pub payer: AccountInfo<'info>,
     
-    // Decision Rationale
-    pub decision_reason: String,
-    pub risk_assessment: RiskLevel,
+    /// Anyone may close an expired payment; receives part of the rent
+    #[account(mut)]
+    pub closer: Signer<'info>,
     
-    // Execution Details
-    pub execution_status: ExecutionStatus,
-    pub execution_tx_signature: Option<String>,
-    pub execution_slippage: Option<u16>,
+    #[account(
+        init_if_needed,
+        payer = closer,
+        space = 8 + AuditStream::LEN,
+        seeds = [b"audit_stream".as_ref(), payment.key().as_ref()],
+        bump
+    )]
+    pub audit_stream: Box<Account<'info, AuditStream>>,
     
-    // Compliance & Audit
-    pub requires_human_approval: bool,
-    pub human_approver: Option<Pubkey>,
-    pub approval_timestamp: Option<i64>,
+    #[account(
+        init,
+        payer = closer,
+        space = 8 + AuditLog::LEN,
+        seeds = [b"audit_log".as_ref(), payment.key().as_ref(), &audit_stream.log_count.to_le_bytes()],
+        bump
+    )]
+    pub audit_log: Box<Account<'info, AuditLog>>,
     
-    // Timestamps
-    pub created_at: i64,
-    pub executed_at: Option<i64>,
+    pub system_program: Program<'info, System>,
 }
 
-/// Tracks x402 protocol payments for API access
-#[account]
-pub struct X402Payment {
-    // Payment Identity
-    pub payment_id: [u8; 32],
-    pub payment_bump: u8,
-    
-    // Payer Information
-    pub payer: Pubkey,
-    pub payer_wallet: Pubkey,
+#[derive(Accounts)]
+#[instruction(currency: PaymentCurrency)]
+pub struct InitializeX402RevenueVault<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + X402RevenueVault::LEN,
+        seeds = [b"x402_revenue".as_ref(), &[currency as u8]],
+        bump
+    )]
+    pub revenue_vault: Account<'info, X402RevenueVault>,
     
-    // Payment Details
-    pub amount: u64,
-    pub currency: PaymentCurrency,
-    pub payment_status: PaymentStatus,
+    #[account(
+        mut,
+        seeds = [b"protocol_config"],
+        bump = config.config_bump,
+        has_one = authority @ XLiquidityEngineError::Unauthorized
+    )]
+    pub config: Account<'info, ProtocolConfig>,
     
-    // x402 Protocol Details
-    pub facilitator: Pubkey,
-    pub facilitator_signature: Option<[u8; 64]>,
-    pub payment_tx_signature: Option<String>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
     
-    // API Access Details
-    pub api_endpoint: String,
-    pub api_version: String,
-    pub access_granted: bool,
-    pub access_expires_at: Option<i64>,
+    /// Token account owned by the vault PDA, only required for USDC/USDT
+    pub token_vault: Option<Account<'info, TokenAccount>>,
     
-    // Timestamps
-    pub requested_at: i64,
-    pub verified_at: Option<i64>,
-    pub settled_at: Option<i64>,
+    pub system_program: Program<'info, System>,
 }
 
-/// Global protocol configuration and parameters
-#[account]
-pub struct ProtocolConfig {
-    // Authority
-    pub authority: Pubkey,
-    pub config_bump: u8,
+#[derive(Accounts)]
+pub struct ClaimX402Revenue<'info> {
+    #[account(
+        mut,
+        seeds = [b"x402_revenue".as_ref(), &[revenue_vault.currency as u8]],
+        bump = revenue_vault.vault_bump
+    )]
+    pub revenue_vault: Account<'info, X402RevenueVault>,
     
-    // Fee Structure
-    pub performance_fee_bps: u16,
-    pub protocol_fee_bps: u16,
-    pub fee_recipient: Pubkey,
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = config.config_bump
+    )]
+    pub config: Account<'info, ProtocolConfig>,
     
-    // x402 Configuration
-    pub x402_facilitator: Option<Pubkey>,
-    pub x402_min_payment: u64,
-    pub x402_api_base_url: String,
+    /// Fee recipient or registered provider
+    #[account(mut)]
+    pub claimant: Signer<'info>,
     
-    // Rebalancing Parameters
-    pub min_rebalance_interval: u32,
-    pub max_rebalance_frequency: u32,
-    pub default_slippage_tolerance_bps: u16,
+    /// Token accounts below are only required for USDC/USDT revenue
+    pub mint: Option<Account<'info, Mint>>,
     
-    // Risk Management
-    pub max_position_size: u64,
-    pub max_single_trade_size: u64,
-    pub require_human_approval_threshold: u64,
+    #[account(mut)]
+    pub revenue_token_vault: Option<Account<'info, TokenAccount>>,
     
-    // AI Model Configuration
-    pub default_ai_model_version: String,
-    pub ai_model_registry: Vec<Pubkey>,
+    #[account(mut)]
+    pub claimant_token_account: Option<Account<'info, TokenAccount>>,
     
-    // Compliance
-    pub audit_log_enabled: bool,
-    pub compliance_mode: ComplianceMode,
+    pub token_program: Option<Program<'info, Token>>,
     
-    // Timestamps
-    pub created_at: i64,
-    pub updated_at: i64,
-}
-
-/// User-defined strategy parameters and preferences
-#[account]
-pub struct UserStrategy {
-    // Ownership
-    pub user: Pubkey,
-    pub strategy_bump: u8,
+    #[account(
+        init_if_needed,
+        payer = claimant,
+        space = 8 + AuditStream::LEN,
+        seeds = [b"audit_stream".as_ref(), revenue_vault.key().as_ref()],
+        bump
+    )]
+    pub audit_stream: Box<Account<'info, AuditStream>>,
     
-    // Strategy Configuration
-    pub strategy_name: String,
-    pub strategy_type: StrategyType,
-    pub risk_tolerance: RiskTolerance,
+    #[account(
+        init,
+        payer = claimant,
+        space = 8 + AuditLog::LEN,
+        seeds = [b"audit_log".as_ref(), revenue_vault.key().as_ref(), &audit_stream.log_count.to_le_bytes()],
+        bump
+    )]
+    pub audit_log: Box<Account<'info, AuditLog>>,
     
-    // Rebalancing Preferences
-    pub auto_rebalance_enabled: bool,
-    pub rebalance_frequency: RebalanceFrequency,
-    pub price_range_width: u16,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(currency: PaymentCurrency)]
+pub struct InitializeX402LpRewardsPool<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + X402LpRewardsPool::LEN,
+        seeds = [b"x402_lp_rewards".as_ref(), &[currency as u8]],
+        bump
+    )]
+    pub lp_rewards_pool: Account<'info, X402LpRewardsPool>,
     
-    // Risk Limits
-    pub max_position_size: Option<u64>,
-    pub max_single_trade: Option<u64>,
-    pub max_slippage_bps: Option<u16>,
+    #[account(
+        mut,
+        seeds = [b"protocol_config"],
+        bump = config.config_bump,
+        has_one = authority @ XLiquidityEngineError::Unauthorized
+    )]
+    pub config: Account<'info, ProtocolConfig>,
     
-    // Token Preferences
-    pub preferred_tokens: Vec<Pubkey>,
-    pub blacklisted_tokens: Vec<Pubkey>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
     
-    // AI Model Selection
-    pub preferred_ai_model: Option<String>,
-    pub require_human_approval: bool,
+    /// Token account owned by the pool PDA, only required for USDC/USDT
+    pub token_vault: Option<Account<'info, TokenAccount>>,
     
-    // Timestamps
-    pub created_at: i64,
-    pub updated_at: i64,
-}
-
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
-pub enum RiskTolerance {
-    Low,
-    Medium,
-    High,
+    pub system_program: Program<'info, System>,
 }
 
-/// Immutable audit log for compliance and regulatory requirements
-#[account]
-pub struct AuditLog {
-    // Log Identity
-    pub log_id: [u8; 32],
-    pub log_bump: u8,
+#[derive(Accounts)]
+pub struct DistributeX402LpRewards<'info> {
+    #[account(
+        mut,
+        seeds = [b"x402_revenue".as_ref(), &[revenue_vault.currency as u8]],
+        bump = revenue_vault.vault_bump
+    )]
+    pub revenue_vault: Account<'info, X402RevenueVault>,
     
-    // Event Information
-    pub event_type: AuditEventType,
-    pub position: Option<Pubkey>,
-    pub user: Pubkey,
+    #[account(
+        mut,
+        seeds = [b"x402_lp_rewards".as_ref(), &[revenue_vault.currency as u8]],
+        bump = lp_rewards_pool.pool_bump
+    )]
+    pub lp_rewards_pool: Account<'info, X402LpRewardsPool>,
     
-    // Event Data
-    pub event_data: Vec<u8>,
-    pub event_hash: [u8; 32],
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = config.config_bump
+    )]
+    pub config: Account<'info, ProtocolConfig>,
     
-    // AI Decision Context (if applicable)
-    pub ai_model_version: Option<String>,
-    pub decision_rationale: Option<String>,
-    pub prediction_scores: Option<Vec<u16>>,
+    #[account(mut)]
+    pub caller: Signer<'info>,
     
-    // Compliance Metadata
-    pub regulatory_jurisdiction: Option<String>,
+    /// Token accounts below are only required for USDC/USDT revenue
+    pub mint: Option<Account<'info, Mint>>,
     
-    // Timestamps
-    pub created_at: i64,
-    pub slot: u64,
-}
-
-// ============================================================================
-// ERROR TYPES
-// ============================================================================
-
-#[error_code]
-pub enum XLiquidityEngineError {
-    #[msg("Invalid price range")]
-    InvalidPriceRange,
-    #[msg("Position exceeds maximum size")]
-    ExceedsMaxPositionSize,
-    #[msg("Trade exceeds maximum size")]
-    ExceedsMaxTradeSize,
-    #[msg("Position is not active")]
-    PositionNotActive,
-    #[msg("Rebalance too frequent")]
-    RebalanceTooFrequent,
-    #[msg("Invalid execution status")]
-    InvalidExecutionStatus,
-    #[msg("Human approval required")]
-    HumanApprovalRequired,
-    #[msg("Invalid approver")]
-    InvalidApprover,
-    #[msg("Slippage tolerance too high")]
-    SlippageTooHigh,
-    #[msg("Payment amount too small")]
-    PaymentTooSmall,
-    #[msg("Invalid facilitator")]
-    InvalidFacilitator,
-    #[msg("No fees to collect")]
-    NoFeesToCollect,
-    #[msg("Approval not required")]
-    ApprovalNotRequired,
-}
-
-// ============================================================================
-// INSTRUCTION CONTEXTS
-// ============================================================================
+    #[account(mut)]
+    pub revenue_token_vault: Option<Account<'info, TokenAccount>>,
+    
+    #[account(mut)]
+    pub pool_token_vault: Option<Account<'info, TokenAccount>>,
+    
+    pub token_program: Option<Program<'info, Token>>,
+    
+    #[account(
+        init_if_needed,
+        payer = caller,
+        space = 8 + AuditStream::LEN,
+        seeds = [b"audit_stream".as_ref(), lp_rewards_pool.key().as_ref()],
+        bump
+    )]
+    pub audit_stream: Box<Account<'info, AuditStream>>,
+    
+    #[account(
+        init,
+        payer = caller,
+        space = 8 + AuditLog::LEN,
+        seeds = [b"audit_log".as_ref(), lp_rewards_pool.key().as_ref(), &audit_stream.log_count.to_le_bytes()],
+        bump
+    )]
+    pub audit_log: Box<Account<'info, AuditLog>>,
+    
+    pub system_program: Program<'info, System>,
+}
 
 #[derive(Accounts)]
-pub struct InitializeProtocolConfig<'info> {
+pub struct PayX402LpRewards<'info> {
+    #[account(
+        mut,
+        seeds = [b"x402_lp_rewards".as_ref(), &[lp_rewards_pool.currency as u8]],
+        bump = lp_rewards_pool.pool_bump
+    )]
+    pub lp_rewards_pool: Account<'info, X402LpRewardsPool>,
+    
     #[account(
-        init,
-        payer = authority,
-        space = 8 + ProtocolConfig::LEN,
         seeds = [b"protocol_config"],
-        bump
+        bump = config.config_bump,
+        has_one = authority @ XLiquidityEngineError::Unauthorized
     )]
     pub config: Account<'info, ProtocolConfig>,
     
     #[account(mut)]
     pub authority: Signer<'info>,
     
-    /// CHECK: Fee recipient address
-    pub fee_recipient: AccountInfo<'info>,
+    /// LP receiving the rewards (owner of `recipient_token_account` for USDC/USDT)
+    #[account(mut)]
+    pub recipient: SystemAccount<'info>,
+    
+    /// Token accounts below are only required for USDC/USDT rewards
+    pub mint: Option<Account<'info, Mint>>,
+    
+    #[account(mut)]
+    pub pool_token_vault: Option<Account<'info, TokenAccount>>,
+    
+    #[account(mut)]
+    pub recipient_token_account: Option<Account<'info, TokenAccount>>,
+    
+    pub token_program: Option<Program<'info, Token>>,
+    
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + AuditStream::LEN,
+        seeds = [b"audit_stream".as_ref(), lp_rewards_pool.key().as_ref()],
+        bump
+    )]
+    pub audit_stream: Box<Account<'info, AuditStream>>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + AuditLog::LEN,
+        seeds = [b"audit_log".as_ref(), lp_rewards_pool.key().as_ref(), &audit_stream.log_count.to_le_bytes()],
+        bump
+    )]
+    pub audit_log: Box<Account<'info, AuditLog>>,
     
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
 #[instruction(position_index: u8)]
-pub struct CreateLiquidityPosition<'info> {
+pub struct CollectFees<'info> {
     #[account(
-        init,
-        payer = owner,
-        space = 8 + LiquidityPosition::LEN,
-        seeds = [b"liquidity_position", owner.key().as_ref(), &[position_index]],
-        bump
+        mut,
+        seeds = [b"liquidity_position", position.owner.as_ref(), &[position_index]],
+        bump = position.position_bump,
+        constraint = position.owner == owner.key() @ XLiquidityEngineError::PositionNotActive,
+        has_one = token_a_vault,
+        has_one = token_b_vault
     )]
     pub position: Account<'info, LiquidityPosition>,
     
     #[account(
         seeds = [b"protocol_config"],
-        bump = config.config_bump
+        bump = config.config_bump,
+        constraint = !config.paused.rebalancing @ XLiquidityEngineError::ProtocolPaused
     )]
     pub config: Account<'info, ProtocolConfig>,
     
     #[account(mut)]
     pub owner: Signer<'info>,
     
-    /// CHECK: Token A vault
-    pub token_a_vault: AccountInfo<'info>,
+    #[account(address = position.token_a @ XLiquidityEngineError::InvalidMint)]
+    pub token_a_mint: Box<Account<'info, Mint>>,
     
-    /// CHECK: Token B vault
-    pub token_b_vault: AccountInfo<'info>,
+    #[account(address = position.token_b @ XLiquidityEngineError::InvalidMint)]
+    pub token_b_mint: Box<Account<'info, Mint>>,
     
-    /// CHECK: DEX pool address
-    pub pool: AccountInfo<'info>,
+    #[account(mut)]
+    pub token_a_vault: Box<Account<'info, TokenAccount>>,
     
-    /// CHECK: Audit log account (simplified for now)
-    pub audit_log: AccountInfo<'info>,
+    #[account(mut)]
+    pub token_b_vault: Box<Account<'info, TokenAccount>>,
+    
+    pub token_program: Program<'info, Token>,
+    
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + AuditStream::LEN,
+        seeds = [b"audit_stream".as_ref(), position.key().as_ref()],
+        bump
+    )]
+    pub audit_stream: Box<Account<'info, AuditStream>>,
     
-    pub system_program: Program<'info, System>,
-}
-
-#[derive(Accounts)]
-#[instruction(position_index: u8, decision_index: u32)]
-pub struct CreateRebalanceDecision<'info> {
     #[account(
         init,
-        payer = payer,
-        space = 8 + RebalanceDecision::LEN,
-        seeds = [b"rebalance_decision", position.key().as_ref(), &decision_index.to_le_bytes()],
+        payer = owner,
+        space = 8 + AuditLog::LEN,
+        seeds = [b"audit_log".as_ref(), position.key().as_ref(), &audit_stream.log_count.to_le_bytes()],
         bump
     )]
-    pub decision: Account<'info, RebalanceDecision>,
+    pub audit_log: Box<Account<'info, AuditLog>>,
     
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(position_index: u8)]
+pub struct RefreshDeployedAmounts<'info> {
     #[account(
         mut,
         seeds = [b"liquidity_position", position.owner.as_ref(), &[position_index]],
-        bump = position.position_bump
+        bump = position.position_bump,
+        has_one = token_a_vault,
+        has_one = token_b_vault
     )]
     pub position: Account<'info, LiquidityPosition>,
     
@@ -931,15 +6009,19 @@ pub struct CreateRebalanceDecision<'info> {
     )]
     pub config: Account<'info, ProtocolConfig>,
     
-    #[account(mut)]
-    pub payer: Signer<'info>,
+    #[account(
+        constraint = config.can_rebalance(&position, &caller.key()) @ XLiquidityEngineError::Unauthorized
+    )]
+    pub caller: Signer<'info>,
     
-    pub system_program: Program<'info, System>,
+    pub token_a_vault: Box<Account<'info, TokenAccount>>,
+    
+    pub token_b_vault: Box<Account<'info, TokenAccount>>,
 }
 
 #[derive(Accounts)]
 #[instruction(position_index: u8, decision_index: u32)]
-pub struct ExecuteRebalance<'info> {
+pub struct CancelRebalanceDecision<'info> {
     #[account(
         mut,
         seeds = [b"rebalance_decision", position.key().as_ref(), &decision_index.to_le_bytes()],
@@ -949,8 +6031,9 @@ pub struct ExecuteRebalance<'info> {
     
     #[account(
         mut,
-        seeds = [b"liquidity_position", position.owner.as_ref(), &[position_index]],
-        bump = position.position_bump
+        seeds = [b"liquidity_position", owner.key().as_ref(), &[position_index]],
+        bump = position.position_bump,
+        has_one = owner @ XLiquidityEngineError::Unauthorized
     )]
     pub position: Account<'info, LiquidityPosition>,
     
@@ -960,54 +6043,44 @@ pub struct ExecuteRebalance<'info> {
     )]
     pub config: Account<'info, ProtocolConfig>,
     
-    /// CHECK: Approver (optional, only needed if human approval required)
-    pub approver: Option<Signer<'info>>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
     
-    /// CHECK: Audit log account
-    pub audit_log: AccountInfo<'info>,
-}
-
-#[derive(Accounts)]
-#[instruction(payment_id: [u8; 32])]
-pub struct VerifyX402Payment<'info> {
     #[account(
-        init,
-        payer = payer,
-        space = 8 + X402Payment::LEN,
-        seeds = [b"x402_payment", payment_id.as_ref()],
+        init_if_needed,
+        payer = owner,
+        space = 8 + AuditStream::LEN,
+        seeds = [b"audit_stream".as_ref(), position.key().as_ref()],
         bump
     )]
-    pub payment: Account<'info, X402Payment>,
+    pub audit_stream: Box<Account<'info, AuditStream>>,
     
     #[account(
-        seeds = [b"protocol_config"],
-        bump = config.config_bump
+        init,
+        payer = owner,
+        space = 8 + AuditLog::LEN,
+        seeds = [b"audit_log".as_ref(), position.key().as_ref(), &audit_stream.log_count.to_le_bytes()],
+        bump
     )]
-    pub config: Account<'info, ProtocolConfig>,
-    
-    #[account(mut)]
-    pub payer: Signer<'info>,
-    
-    /// CHECK: Payer wallet
-    pub payer_wallet: AccountInfo<'info>,
-    
-    /// CHECK: x402 Facilitator
-    pub facilitator: AccountInfo<'info>,
-    
-    /// CHECK: Audit log account
-    pub audit_log: AccountInfo<'info>,
+    pub audit_log: Box<Account<'info, AuditLog>>,
     
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-#[instruction(position_index: u8)]
-pub struct CollectFees<'info> {
+#[instruction(position_index: u8, decision_index: u32)]
+pub struct ExpireRebalanceDecision<'info> {
+    #[account(
+        mut,
+        seeds = [b"rebalance_decision", position.key().as_ref(), &decision_index.to_le_bytes()],
+        bump = decision.decision_bump
+    )]
+    pub decision: Account<'info, RebalanceDecision>,
+    
     #[account(
         mut,
         seeds = [b"liquidity_position", position.owner.as_ref(), &[position_index]],
-        bump = position.position_bump,
-        constraint = position.owner == owner.key() @ XLiquidityEngineError::PositionNotActive
+        bump = position.position_bump
     )]
     pub position: Account<'info, LiquidityPosition>,
     
@@ -1018,10 +6091,27 @@ pub struct CollectFees<'info> {
     pub config: Account<'info, ProtocolConfig>,
     
     #[account(mut)]
-    pub owner: Signer<'info>,
+    pub caller: Signer<'info>,
+    
+    #[account(
+        init_if_needed,
+        payer = caller,
+        space = 8 + AuditStream::LEN,
+        seeds = [b"audit_stream".as_ref(), position.key().as_ref()],
+        bump
+    )]
+    pub audit_stream: Box<Account<'info, AuditStream>>,
+    
+    #[account(
+        init,
+        payer = caller,
+        space = 8 + AuditLog::LEN,
+        seeds = [b"audit_log".as_ref(), position.key().as_ref(), &audit_stream.log_count.to_le_bytes()],
+        bump
+    )]
+    pub audit_log: Box<Account<'info, AuditLog>>,
     
-    /// CHECK: Audit log account
-    pub audit_log: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
@@ -1037,11 +6127,89 @@ pub struct ApproveRebalance<'info> {
     /// CHECK: Position account (for validation)
     pub position: Account<'info, LiquidityPosition>,
     
-    #[account(mut)]
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = config.config_bump,
+        constraint = !config.paused.rebalancing @ XLiquidityEngineError::ProtocolPaused
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+    
+    /// Position owner or the protocol authority
+    #[account(
+        mut,
+        constraint = approver.key() == position.owner
+            || approver.key() == config.authority @ XLiquidityEngineError::Unauthorized
+    )]
     pub approver: Signer<'info>,
     
-    /// CHECK: Audit log account
-    pub audit_log: AccountInfo<'info>,
+    #[account(
+        init_if_needed,
+        payer = approver,
+        space = 8 + AuditStream::LEN,
+        seeds = [b"audit_stream".as_ref(), position.key().as_ref()],
+        bump
+    )]
+    pub audit_stream: Box<Account<'info, AuditStream>>,
+    
+    #[account(
+        init,
+        payer = approver,
+        space = 8 + AuditLog::LEN,
+        seeds = [b"audit_log".as_ref(), position.key().as_ref(), &audit_stream.log_count.to_le_bytes()],
+        bump
+    )]
+    pub audit_log: Box<Account<'info, AuditLog>>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct VerifyAuditChain<'info> {
+    #[account(
+        seeds = [b"audit_stream".as_ref(), audit_stream.subject.as_ref()],
+        bump = audit_stream.stream_bump
+    )]
+    pub audit_stream: Account<'info, AuditStream>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateProtocolConfig<'info> {
+    /// CHECK: Layout-v0 config; checked by `migration::upgrade` before it is decoded
+    #[account(
+        mut,
+        seeds = [b"protocol_config"],
+        bump
+    )]
+    pub config: AccountInfo<'info>,
+    
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateLiquidityPosition<'info> {
+    /// CHECK: Layout-v0 position; checked by `migration::upgrade` before it is decoded
+    #[account(mut)]
+    pub position: AccountInfo<'info>,
+    
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateRebalanceDecision<'info> {
+    /// CHECK: Layout-v0 decision; checked by `migration::upgrade` before it is decoded
+    #[account(mut)]
+    pub decision: AccountInfo<'info>,
+    
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
 }
 
 // ============================================================================
@@ -1054,7 +6222,7 @@ impl ProtocolConfig {
         2 + // performance_fee_bps
         2 + // protocol_fee_bps
         32 + // fee_recipient
-        1 + 32 + // x402_facilitator (Option<Pubkey>)
+        1 + 32 + // x402_facilitator (Option<Pubkey>, legacy)
         8 + // x402_min_payment
         4 + 50 + // x402_api_base_url (String, max 50 chars)
         4 + // min_rebalance_interval
@@ -1064,11 +6232,71 @@ impl ProtocolConfig {
         8 + // max_single_trade_size
         8 + // require_human_approval_threshold
         4 + 20 + // default_ai_model_version (String, max 20 chars)
-        4 + (32 * 10) + // ai_model_registry (Vec<Pubkey>, max 10)
+        4 + (32 * MAX_AI_MODEL_REGISTRY) + // ai_model_registry (Vec<Pubkey>, max 7)
         1 + // audit_log_enabled
         1 + // compliance_mode
         8 + // created_at
-        8; // updated_at
+        8 + // updated_at
+        1 + // layout_version
+        1 + 32 + // pending_authority (Option<Pubkey>)
+        4 + (X402FacilitatorEntry::LEN * MAX_X402_FACILITATORS) + // x402_facilitators (Vec, max 5)
+        1 + 32 + // x402_usdc_mint (Option<Pubkey>)
+        1 + 32 + // x402_usdt_mint (Option<Pubkey>)
+        RevenueSplit::LEN + // revenue_split
+        1 + 32 + // swap_program (Option<Pubkey>)
+        ProtocolPauseFlags::LEN + // paused
+        1 + 32 + // rebalance_keeper (Option<Pubkey>)
+        4 * PAYMENT_CURRENCY_COUNT; // x402_token_vaults
+}
+
+impl ProtocolConfig {
+    /// SPL mint used to settle `currency`; `None` for native SOL or if unset
+    fn currency_mint(&self, currency: PaymentCurrency) -> Option<Pubkey> {
+        match currency {
+            PaymentCurrency::SOL => None,
+            PaymentCurrency::USDC => self.x402_usdc_mint,
+            PaymentCurrency::USDT => self.x402_usdt_mint,
+        }
+    }
+
+    fn find_facilitator(&self, facilitator: &Pubkey) -> Option<&X402FacilitatorEntry> {
+        self.x402_facilitators
+            .iter()
+            .find(|entry| entry.facilitator == *facilitator)
+    }
+
+    /// Look up an enabled, unexpired facilitator
+    fn find_active_facilitator(
+        &self,
+        facilitator: &Pubkey,
+        now: i64,
+    ) -> Option<&X402FacilitatorEntry> {
+        self.find_facilitator(facilitator)
+            .filter(|entry| entry.is_active(now))
+    }
+
+    /// Count a token vault of `currency`'s mint as opened or closed
+    fn track_token_vault(&mut self, currency: PaymentCurrency, opened: bool) -> Result<()> {
+        let count = &mut self.x402_token_vaults[currency as usize];
+        *count = if opened {
+            count.checked_add(1)
+        } else {
+            count.checked_sub(1)
+        }
+        .ok_or(XLiquidityEngineError::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Whether `signer` may create or execute rebalances of `position`
+    fn can_rebalance(&self, position: &LiquidityPosition, signer: &Pubkey) -> bool {
+        *signer == position.owner || self.rebalance_keeper == Some(*signer)
+    }
+
+    /// Drop outgoing facilitators whose rotation overlap has ended
+    fn prune_expired_facilitators(&mut self, now: i64) {
+        self.x402_facilitators
+            .retain(|entry| entry.expires_at.is_none_or(|expires_at| now < expires_at));
+    }
 }
 
 impl LiquidityPosition {
@@ -1101,7 +6329,34 @@ impl LiquidityPosition {
         8 + // max_single_trade
         4 + (32 * 5) + // allowed_dex_programs (Vec<Pubkey>, max 5)
         8 + // created_at
-        8; // updated_at
+        8 + // updated_at
+        1 + // layout_version
+        1 + BinRange::LEN + // bin_range (Option<BinRange>)
+        8 + // deployed_amount_a
+        8 + // deployed_amount_b
+        2 + // pending_decisions
+        1 + // emergency_paused
+        8 + // max_amount_a
+        8 + // max_amount_b
+        8 + // net_deposited_a
+        8 + // net_deposited_b
+        16; // value_sqrt_price_x64
+
+    /// Recompute `total_value_locked` from the vault balances and the amounts
+    /// deployed to the DEX, valued in token B at `value_sqrt_price_x64`
+    fn refresh_value_locked(&mut self, vault_a: &TokenAccount, vault_b: &TokenAccount) -> Result<()> {
+        let amount_a = vault_a
+            .amount
+            .checked_add(self.deployed_amount_a)
+            .ok_or(XLiquidityEngineError::MathOverflow)?;
+        let amount_b = vault_b
+            .amount
+            .checked_add(self.deployed_amount_b)
+            .ok_or(XLiquidityEngineError::MathOverflow)?;
+        let value = dex::math::value_in_b(self.value_sqrt_price_x64, (amount_a, amount_b))?;
+        self.total_value_locked = u64::try_from(value).map_err(|_| XLiquidityEngineError::MathOverflow)?;
+        Ok(())
+    }
 }
 
 impl RebalanceDecision {
@@ -1127,7 +6382,11 @@ impl RebalanceDecision {
         1 + 32 + // human_approver (Option<Pubkey>)
         1 + 8 + // approval_timestamp (Option<i64>)
         8 + // created_at
-        1 + 8; // executed_at (Option<i64>)
+        1 + 8 + // executed_at (Option<i64>)
+        1 + // layout_version
+        1 + BinRange::LEN + // new_bin_range (Option<BinRange>)
+        8 + // expires_at
+        16; // reference_sqrt_price_x64
 }
 
 impl X402Payment {
@@ -1150,6 +6409,175 @@ impl X402Payment {
         1 + 8; // settled_at (Option<i64>)
 }
 
+impl X402EndpointPricing {
+    pub const MAX_ENDPOINT_LEN: usize = 100;
+    pub const MAX_VERSION_LEN: usize = 20;
+
+    pub const LEN: usize = 32 + // endpoint_hash
+        1 + // pricing_bump
+        4 + Self::MAX_ENDPOINT_LEN + // api_endpoint (String, max 100 chars)
+        4 + Self::MAX_VERSION_LEN + // api_version (String, max 20 chars)
+        1 + 8 + // price_sol (Option<u64>)
+        1 + 8 + // price_usdc (Option<u64>)
+        1 + 8 + // price_usdt (Option<u64>)
+        4 + // access_duration
+        1 + // enabled
+        8 + // created_at
+        8; // updated_at
+
+    fn price_for(&self, currency: PaymentCurrency) -> Option<u64> {
+        match currency {
+            PaymentCurrency::SOL => self.price_sol,
+            PaymentCurrency::USDC => self.price_usdc,
+            PaymentCurrency::USDT => self.price_usdt,
+        }
+    }
+
+    fn apply(&mut self, params: &X402PricingParams) {
+        self.price_sol = params.price_sol;
+        self.price_usdc = params.price_usdc;
+        self.price_usdt = params.price_usdt;
+        self.access_duration = params.access_duration;
+        self.enabled = params.enabled;
+    }
+}
+
+impl X402CreditAccount {
+    pub const LEN: usize = 32 + // owner
+        1 + // currency
+        1 + // credit_bump
+        1 + 32 + // vault (Option<Pubkey>)
+        8 + // balance
+        8 + // total_deposited
+        8 + // total_debited
+        8 + // calls_debited
+        1 + 8 + // last_debit_at (Option<i64>)
+        8 + // created_at
+        8; // updated_at
+}
+
+impl X402RevenueVault {
+    pub const LEN: usize = 1 + // currency
+        1 + // vault_bump
+        1 + 32 + // token_vault (Option<Pubkey>)
+        8 + // treasury_accrued
+        8 + // lp_pool_accrued
+        4 + (RevenueAccrual::LEN * MAX_REVENUE_ACCRUALS) + // provider_accruals (Vec, max 16)
+        8 + // total_revenue
+        8 + // total_claimed
+        8 + // total_refunded
+        8 + // created_at
+        8; // updated_at
+
+    /// Split `amount` according to `config.revenue_split`
+    ///
+    /// Providers share their cut equally. Rounding dust, and the provider cut
+    /// when no provider is registered, goes to the treasury.
+    fn accrue(&mut self, amount: u64, config: &ProtocolConfig) -> Result<()> {
+        let split = &config.revenue_split;
+        let lp_share = bps_share(amount, split.lp_pool_bps)?;
+
+        let mut providers_total = 0u64;
+        let providers = &config.ai_model_registry;
+        if !providers.is_empty() {
+            let per_provider = bps_share(amount, split.providers_bps)? / providers.len() as u64;
+            if per_provider > 0 {
+                for provider in providers {
+                    self.credit_provider(*provider, per_provider, providers)?;
+                    providers_total += per_provider;
+                }
+            }
+        }
+
+        let treasury_share = amount
+            .checked_sub(lp_share)
+            .and_then(|rest| rest.checked_sub(providers_total))
+            .ok_or(XLiquidityEngineError::MathOverflow)?;
+        self.treasury_accrued = self
+            .treasury_accrued
+            .checked_add(treasury_share)
+            .ok_or(XLiquidityEngineError::MathOverflow)?;
+        self.lp_pool_accrued = self
+            .lp_pool_accrued
+            .checked_add(lp_share)
+            .ok_or(XLiquidityEngineError::MathOverflow)?;
+        self.total_revenue = self
+            .total_revenue
+            .checked_add(amount)
+            .ok_or(XLiquidityEngineError::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Add to a provider's balance, taking a free accrual slot if it has none
+    ///
+    /// When every slot is taken, the unclaimed balances of providers no longer
+    /// in `registry` move to the treasury to free their slots.
+    fn credit_provider(&mut self, provider: Pubkey, amount: u64, registry: &[Pubkey]) -> Result<()> {
+        if let Some(entry) = self
+            .provider_accruals
+            .iter_mut()
+            .find(|entry| entry.provider == provider)
+        {
+            entry.amount = entry
+                .amount
+                .checked_add(amount)
+                .ok_or(XLiquidityEngineError::MathOverflow)?;
+            return Ok(());
+        }
+        if self.provider_accruals.len() >= MAX_REVENUE_ACCRUALS {
+            let mut reclaimed = 0u64;
+            for entry in self.provider_accruals.iter() {
+                if !registry.contains(&entry.provider) {
+                    reclaimed = reclaimed
+                        .checked_add(entry.amount)
+                        .ok_or(XLiquidityEngineError::MathOverflow)?;
+                }
+            }
+            self.provider_accruals
+                .retain(|entry| registry.contains(&entry.provider));
+            self.treasury_accrued = self
+                .treasury_accrued
+                .checked_add(reclaimed)
+                .ok_or(XLiquidityEngineError::MathOverflow)?;
+        }
+        require!(
+            self.provider_accruals.len() < MAX_REVENUE_ACCRUALS,
+            XLiquidityEngineError::RevenueAccrualsFull
+        );
+        self.provider_accruals.push(RevenueAccrual { provider, amount });
+        Ok(())
+    }
+
+    /// Take everything `claimant` is owed as treasury (fee recipient) and/or
+    /// provider
+    fn take_claim(&mut self, claimant: &Pubkey, config: &ProtocolConfig) -> Result<u64> {
+        let mut amount = 0u64;
+        if *claimant == config.fee_recipient {
+            amount = std::mem::take(&mut self.treasury_accrued);
+        }
+        if let Some(index) = self
+            .provider_accruals
+            .iter()
+            .position(|entry| entry.provider == *claimant)
+        {
+            amount = amount
+                .checked_add(self.provider_accruals.remove(index).amount)
+                .ok_or(XLiquidityEngineError::MathOverflow)?;
+        }
+        Ok(amount)
+    }
+}
+
+impl X402LpRewardsPool {
+    pub const LEN: usize = 1 + // currency
+        1 + // pool_bump
+        1 + 32 + // token_vault (Option<Pubkey>)
+        8 + // total_received
+        8 + // total_paid
+        8 + // created_at
+        8; // updated_at
+}
+
 impl UserStrategy {
     pub const LEN: usize = 32 + // user
         1 + // strategy_bump
@@ -1170,13 +6598,25 @@ impl UserStrategy {
         8; // updated_at
 }
 
+impl AuditStream {
+    pub const LEN: usize = 32 + // subject
+        1 + // stream_bump
+        8 + // log_count
+        32; // last_hash
+}
+
 impl AuditLog {
+    pub const MAX_EVENT_DATA_LEN: usize = 500;
+
     pub const LEN: usize = 32 + // log_id
         1 + // log_bump
+        32 + // subject
+        8 + // sequence
         1 + // event_type
         1 + 32 + // position (Option<Pubkey>)
         32 + // user
-        4 + 500 + // event_data (Vec<u8>, max 500 bytes)
+        4 + Self::MAX_EVENT_DATA_LEN + // event_data (Vec<u8>, max 500 bytes)
+        32 + // prev_event_hash
         32 + // event_hash
         1 + 50 + // ai_model_version (Option<String>, max 50 chars)
         1 + 200 + // decision_rationale (Option<String>, max 200 chars)
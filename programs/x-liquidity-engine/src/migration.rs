@@ -0,0 +1,155 @@
+//! Account layout migrations
+//!
+//! Fields added after the first deployment are appended to the end of
+//! `ProtocolConfig`, `LiquidityPosition` and `RebalanceDecision`, so an
+//! account written by the original program (layout v0) is a prefix of the
+//! current layout. [`upgrade`] grows such an account to the current size and
+//! zeroes everything after its v0 data, from which the appended fields decode
+//! as `0`, `None`, empty or `false`; the `migrate_*` instructions then fill in
+//! real defaults and set `layout_version`.
+
+use anchor_lang::error::ErrorCode;
+use anchor_lang::prelude::*;
+use anchor_lang::system_program;
+use anchor_lang::Discriminator;
+
+use crate::{ComplianceMode, DexType, ExecutionStatus, PositionStatus, RiskLevel, XLiquidityEngineError};
+
+/// `ProtocolConfig` as written by layout v0
+#[derive(AnchorDeserialize)]
+#[allow(dead_code)] // Only parsed to find where the v0 data ends
+pub struct ProtocolConfigV0 {
+    authority: Pubkey,
+    config_bump: u8,
+    performance_fee_bps: u16,
+    protocol_fee_bps: u16,
+    fee_recipient: Pubkey,
+    x402_facilitator: Option<Pubkey>,
+    x402_min_payment: u64,
+    x402_api_base_url: String,
+    min_rebalance_interval: u32,
+    max_rebalance_frequency: u32,
+    default_slippage_tolerance_bps: u16,
+    max_position_size: u64,
+    max_single_trade_size: u64,
+    require_human_approval_threshold: u64,
+    default_ai_model_version: String,
+    ai_model_registry: Vec<Pubkey>,
+    audit_log_enabled: bool,
+    compliance_mode: ComplianceMode,
+    created_at: i64,
+    updated_at: i64,
+}
+
+/// `LiquidityPosition` as written by layout v0
+#[derive(AnchorDeserialize)]
+#[allow(dead_code)] // Only parsed to find where the v0 data ends
+pub struct LiquidityPositionV0 {
+    owner: Pubkey,
+    position_bump: u8,
+    token_a: Pubkey,
+    token_b: Pubkey,
+    token_a_vault: Pubkey,
+    token_b_vault: Pubkey,
+    dex: DexType,
+    pool_address: Pubkey,
+    position_nft: Option<Pubkey>,
+    current_tick_lower: i32,
+    current_tick_upper: i32,
+    current_price_lower: u128,
+    current_price_upper: u128,
+    liquidity_amount: u128,
+    total_fees_earned_a: u64,
+    total_fees_earned_b: u64,
+    total_value_locked: u64,
+    last_rebalance_slot: u64,
+    last_rebalance_timestamp: i64,
+    rebalance_count: u32,
+    total_return_percentage: i16,
+    apy_estimate: u16,
+    status: PositionStatus,
+    auto_rebalance_enabled: bool,
+    min_rebalance_interval: u32,
+    max_position_size: u64,
+    max_single_trade: u64,
+    allowed_dex_programs: Vec<Pubkey>,
+    created_at: i64,
+    updated_at: i64,
+}
+
+/// `RebalanceDecision` as written by layout v0
+#[derive(AnchorDeserialize)]
+#[allow(dead_code)] // Only parsed to find where the v0 data ends
+pub struct RebalanceDecisionV0 {
+    position: Pubkey,
+    decision_bump: u8,
+    new_tick_lower: i32,
+    new_tick_upper: i32,
+    new_price_lower: u128,
+    new_price_upper: u128,
+    ai_model_version: String,
+    ai_model_hash: [u8; 32],
+    prediction_confidence: u16,
+    market_sentiment_score: i16,
+    volatility_metric: u16,
+    whale_activity_score: u16,
+    on_chain_indicators: Vec<u64>,
+    decision_reason: String,
+    risk_assessment: RiskLevel,
+    execution_status: ExecutionStatus,
+    execution_tx_signature: Option<String>,
+    execution_slippage: Option<u16>,
+    requires_human_approval: bool,
+    human_approver: Option<Pubkey>,
+    approval_timestamp: Option<i64>,
+    created_at: i64,
+    executed_at: Option<i64>,
+}
+
+/// Grow a layout-v0 `T` account (laid out as `V0`) to `space` bytes and zero
+/// everything after its v0 data
+///
+/// The payer tops the account up to the new rent-exempt minimum. Accounts
+/// that are already `space` bytes long use the current layout and are
+/// rejected before anything is written.
+pub fn upgrade<'info, T: Discriminator, V0: AnchorDeserialize>(
+    account: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    space: usize,
+) -> Result<()> {
+    require_keys_eq!(*account.owner, crate::ID, ErrorCode::AccountOwnedByWrongProgram);
+    let v0_len = {
+        let data = account.try_borrow_data()?;
+        require!(
+            data.starts_with(T::DISCRIMINATOR),
+            ErrorCode::AccountDiscriminatorMismatch
+        );
+        require!(
+            data.len() < space,
+            XLiquidityEngineError::AccountAlreadyMigrated
+        );
+        let mut rest = &data[T::DISCRIMINATOR.len()..];
+        V0::deserialize(&mut rest).map_err(|_| ErrorCode::AccountDidNotDeserialize)?;
+        data.len() - rest.len()
+    };
+
+    let shortfall = Rent::get()?
+        .minimum_balance(space)
+        .saturating_sub(account.lamports());
+    if shortfall > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                system_program.clone(),
+                system_program::Transfer {
+                    from: payer.clone(),
+                    to: account.clone(),
+                },
+            ),
+            shortfall,
+        )?;
+    }
+    account.resize(space)?;
+    account.try_borrow_mut_data()?[v0_len..].fill(0);
+    Ok(())
+}